@@ -7,21 +7,37 @@ extern crate crossterm;
 extern crate clap;
 
 use crossterm::cursor;
-use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::style::Print;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap};
+use crossterm::event::{poll, read, Event, KeyCode, KeyModifiers};
+use crossterm::style::{style, Color, Colorize, ContentStyle, Print, StyledContent, Styler};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap, SetTitle};
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
 use std::io::stdout;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::sync::{Arc, Mutex, mpsc};
-use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use std::cmp;
 
 use itertools::Itertools;
 
-use board::{Board, PushState};
+use board::{Board, Cell, Generation, PushState, Symmetry, View};
+use campaign::Campaign;
+use game::Game;
+use keymap::{Action, KeyPreset, Keymap, Motion};
+use replay::{Playback, Replay, Step};
+use render::{Renderer, Terminal};
+use score::Score;
+use screen::Screen;
+use solver::Strategy;
+use theme::{paint, Capabilities, Theme, ThemeName};
 
 
 arg_enum! {
@@ -41,648 +57,12237 @@ impl Difficulty {
             Difficulty::Expert => 0.2062,
         }
     }
+
+    fn size(&self) -> (u16, u16, u16) {
+        match *self {
+            Difficulty::Beginner => (9, 9, 10),
+            Difficulty::Intermediate => (22, 12, 41),
+            Difficulty::Expert => (22, 22, 100),
+        }
+    }
 }
 
 
-fn main() {
+const MIN_MIDDLE: u16 = 10;
+const MIN_WIDTH: u16 = 8;
 
+fn main() {
     let matches = App::new("rs-minesweeper")
-        .arg(
-            Arg::with_name("width")
-                .help("Sets the width of the board. The minimum is 22, and the maximum is 2 less then your terminal width")
-                .long("width")
-                .short("w")
-                .value_name("WIDTH")
-                .takes_value(true)
+        .args(&play_args())
+        .subcommand(
+            SubCommand::with_name("play")
+                .about("Starts a game. This is the default when no subcommand is given")
+                .args(&play_args())
+        )
+        .subcommand(
+            SubCommand::with_name("solve")
+                .about("Runs the solver on a saved game and shows how far it gets without guessing")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The save file to solve")
+                        .value_name("FILE")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .help("Sets the strategy the solver uses")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .takes_value(true)
+                        .possible_values(&Strategy::variants())
+                        .case_insensitive(true)
+                )
         )
-        .arg(
-            Arg::with_name("height")
-                .help("Sets the height of the board. The minimum is 1, and the maximum is 5 less then your terminal height")
-                .long("height")
-                .short("h")
-                .value_name("HEIGHT")
-                .takes_value(true)
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Plays back a game recorded with --record. Space pauses, + and - change the speed, the arrows or h and l step \
+                    through the moves, Home and End jump to the start and end, [ and ] move between notes, n writes a note \
+                    and q or Esc quits")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The replay file to watch")
+                        .value_name("FILE")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("cast")
+                        .help("Also writes the playback to FILE as an asciinema cast")
+                        .long("cast")
+                        .value_name("FILE")
+                        .takes_value(true)
+                )
         )
-        .arg(
-            Arg::with_name("mine_num")
-                .help("Sets the number of mines on the board. Must be one less then the total number of tiles")
-                .long("mines")
-                .short("m")
-                .value_name("MINES")
-                .takes_value(true)
+        .subcommand(
+            SubCommand::with_name("spectate")
+                .about("Watches a game started with --spectate-port from another terminal. Ctrl-C stops watching")
+                .arg(
+                    Arg::with_name("address")
+                        .help("Where the game is played, as HOST:PORT")
+                        .value_name("ADDRESS")
+                        .required(true)
+                )
         )
-        .arg(
-            Arg::with_name("max_width")
-                .help("Sets the width to its maximum. Not compatible with -w [WIDTH]")
-                .long("max-width")
-                .conflicts_with("width")
+        .subcommand(
+            SubCommand::with_name("simulate")
+                .about("Plays many games with a built in strategy and reports how it did")
+                .arg(
+                    Arg::with_name("games")
+                        .help("Sets the number of games to play")
+                        .long("games")
+                        .short("n")
+                        .value_name("N")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .help("Sets the strategy used to play. Strategies guess at random whenever they are stuck")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .takes_value(true)
+                        .possible_values(&Strategy::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("difficulty")
+                        .help("Sets the board size and mine count used for every game")
+                        .short("d")
+                        .long("difficulty")
+                        .value_name("DIFFICULTY")
+                        .takes_value(true)
+                        .possible_values(&Difficulty::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("Sets the seed of the first game. Each following game uses the next seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .takes_value(true)
+                )
         )
-        .arg(
-            Arg::with_name("max_height")
-                .help("Sets the width to its maximum. Not compatible with -h [HEIGHT]")
-                .long("max-height")
-                .conflicts_with("height")
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Plays the same boards with several strategies and compares how they did")
+                .arg(
+                    Arg::with_name("games")
+                        .help("Sets the number of games each strategy plays on each difficulty")
+                        .long("games")
+                        .short("n")
+                        .value_name("N")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("strategies")
+                        .help("Sets the strategies to compare, all of them by default")
+                        .long("strategies")
+                        .value_name("STRATEGY")
+                        .takes_value(true)
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .possible_values(&Strategy::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("difficulty")
+                        .help("Only plays boards of one difficulty, instead of every difficulty")
+                        .short("d")
+                        .long("difficulty")
+                        .value_name("DIFFICULTY")
+                        .takes_value(true)
+                        .possible_values(&Difficulty::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("Sets the seed of the first game. Each following game uses the next seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .takes_value(true)
+                )
         )
-        .arg(
-            Arg::with_name("difficulty")
-                .help("Creates a board of either beginner, intermediate, or expert difficulty")
-                .short("d")
-                .long("difficulty")
-                .conflicts_with_all(&["height", "width", "width_max", "height_max"])
-                .value_name("DIFFICULTY")
-                .takes_value(true)
-                .possible_values(&Difficulty::variants())
-                .case_insensitive(true)
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Times board generation and the solver on every difficulty")
+                .arg(
+                    Arg::with_name("boards")
+                        .help("Sets the number of boards generated for each difficulty")
+                        .long("boards")
+                        .short("n")
+                        .value_name("N")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .help("Sets the strategy the solver uses")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .takes_value(true)
+                        .possible_values(&Strategy::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("Sets the seed of the first board. Each following board uses the next seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .takes_value(true)
+                )
         )
-        .arg(
-            Arg::with_name("smart_difficulty")
-                .help("Sets the number of mines based on preset difficulty ratios")
-                .short("s")
-                .long("smart-difficulty")
-                .conflicts_with_all(&["mine_num", "difficulty"])
-                .value_name("DIFFICULTY")
-                .takes_value(true)
-                .possible_values(&Difficulty::variants())
-                .case_insensitive(true)
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Shows your results for every board you have played")
+                .arg(
+                    Arg::with_name("file")
+                        .help("Reads the stats from FILE instead of the default location")
+                        .long("file")
+                        .value_name("FILE")
+                        .takes_value(true)
+                )
+                .args(&stats_args())
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Prints every recorded game, oldest first, as CSV or JSON")
+                        .arg(
+                            Arg::with_name("format")
+                                .help("Sets the format")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .takes_value(true)
+                                .possible_values(&["csv", "json"])
+                                .default_value("csv")
+                        )
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("campaign")
+                .about("Plays a run of boards that grow bigger and denser, with lives and time carrying over between levels. Progress is saved between runs")
+                .arg(
+                    Arg::with_name("new")
+                        .help("Starts a new campaign, throwing away the saved one")
+                        .long("new")
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .help("Sets the seed of a new campaign")
+                        .long("seed")
+                        .value_name("SEED")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("theme")
+                        .help("Sets the color theme")
+                        .short("t")
+                        .long("theme")
+                        .value_name("THEME")
+                        .takes_value(true)
+                        .possible_values(&ThemeName::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("keys")
+                        .help("Sets the key binding preset")
+                        .short("k")
+                        .long("keys")
+                        .value_name("PRESET")
+                        .takes_value(true)
+                        .possible_values(&KeyPreset::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("no_color")
+                        .help("Draws everything without colors. Also turned on by setting NO_COLOR")
+                        .long("no-color")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("editor")
+                .about("Opens an editor for placing mines by hand, and saves the board so it can be played with --load")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The save file to write")
+                        .value_name("FILE")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("width")
+                        .help("Sets the width of the board")
+                        .long("width")
+                        .short("w")
+                        .value_name("WIDTH")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("height")
+                        .help("Sets the height of the board")
+                        .long("height")
+                        .short("h")
+                        .value_name("HEIGHT")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("trainer")
+                .about("Drills classic patterns like 1-2-1 on small boards, and keeps track of how well you know each one")
+                .arg(
+                    Arg::with_name("keys")
+                        .help("Sets the key binding preset")
+                        .short("k")
+                        .long("keys")
+                        .value_name("PRESET")
+                        .takes_value(true)
+                        .possible_values(&KeyPreset::variants())
+                        .case_insensitive(true)
+                )
+                .arg(
+                    Arg::with_name("no_color")
+                        .help("Draws everything without colors. Also turned on by setting NO_COLOR")
+                        .long("no-color")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Shows what is in a save and what the solver makes of it, and with --solvable whether it can be cleared without guessing")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The save or board file to analyze")
+                        .value_name("FILE")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("solvable")
+                        .help("Plays the board through with the solver from a starting tile, and reports where it has to guess")
+                        .long("solvable")
+                )
+                .arg(
+                    Arg::with_name("start")
+                        .help("Sets the starting tile, counted from 1 in the top left corner. By default an opening is used if the board has one")
+                        .long("start")
+                        .value_names(&["X", "Y"])
+                        .number_of_values(2)
+                        .requires("solvable")
+                )
+                .arg(
+                    Arg::with_name("strategy")
+                        .help("Sets the strategy the solver uses")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .takes_value(true)
+                        .possible_values(&Strategy::variants())
+                        .case_insensitive(true)
+                        .requires("solvable")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Checks that a save or replay hasn't been changed since the game wrote it, and shows what is in it")
+                .arg(
+                    Arg::with_name("file")
+                        .help("The save or replay file to check")
+                        .value_name("FILE")
+                        .required(true)
+                )
         )
         .get_matches();
 
-    const SPACING: u16 = 12;
+    // Anything that does go wrong mid game shouldn't leave the terminal stuck in raw mode
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        execute!(stdout(), EnableLineWrap, cursor::Show);
+        disable_raw_mode();
+        hook(info);
+    }));
+
+    match matches.subcommand() {
+        ("play", Some(i)) => play(i),
+        ("solve", Some(i)) => solve(i),
+        ("replay", Some(i)) => replay(i),
+        ("spectate", Some(i)) => spectate(i),
+        ("simulate", Some(i)) => simulate(i),
+        ("compare", Some(i)) => compare(i),
+        ("bench", Some(i)) => bench(i),
+        ("stats", Some(i)) => stats(i),
+        ("campaign", Some(i)) => campaign(i),
+        ("editor", Some(i)) => editor(i),
+        ("trainer", Some(i)) => trainer(i),
+        ("analyze", Some(i)) => analyze(i),
+        ("verify", Some(i)) => verify(i),
+        _ => play(&matches),
+    }
+}
+
+#[allow(unused_mut)]
+fn stats_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![];
+
+    #[cfg(feature = "sqlite")]
+    args.push(
+        Arg::with_name("days")
+            .help("Sets how many days back the history section of the stats goes")
+            .long("days")
+            .value_name("DAYS")
+            .takes_value(true)
+    );
+
+    args
+}
+
+fn play_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![
+        Arg::with_name("width")
+            .help("Sets the width of the board. The minimum is 8, and the maximum is 2 less then your terminal width")
+            .long("width")
+            .short("w")
+            .value_name("WIDTH")
+            .takes_value(true),
+        Arg::with_name("height")
+            .help("Sets the height of the board. The minimum is 1, and the maximum is 5 less then your terminal height (6 for boards narrower than 22)")
+            .long("height")
+            .short("h")
+            .value_name("HEIGHT")
+            .takes_value(true),
+        Arg::with_name("mine_num")
+            .help("Sets the number of mines on the board. Must be one less then the total number of tiles")
+            .long("mines")
+            .short("m")
+            .value_name("MINES")
+            .takes_value(true),
+        Arg::with_name("max_width")
+            .help("Sets the width to its maximum. Not compatible with -w [WIDTH]")
+            .long("max-width")
+            .conflicts_with("width"),
+        Arg::with_name("max_height")
+            .help("Sets the width to its maximum. Not compatible with -h [HEIGHT]")
+            .long("max-height")
+            .conflicts_with("height"),
+        Arg::with_name("difficulty")
+            .help("Creates a board of either beginner, intermediate, or expert difficulty")
+            .short("d")
+            .long("difficulty")
+            .conflicts_with_all(&["height", "width", "width_max", "height_max"])
+            .value_name("DIFFICULTY")
+            .takes_value(true)
+            .possible_values(&Difficulty::variants())
+            .case_insensitive(true),
+        Arg::with_name("smart_difficulty")
+            .help("Sets the number of mines based on preset difficulty ratios")
+            .short("s")
+            .long("smart-difficulty")
+            .conflicts_with_all(&["mine_num", "difficulty"])
+            .value_name("DIFFICULTY")
+            .takes_value(true)
+            .possible_values(&Difficulty::variants())
+            .case_insensitive(true),
+        Arg::with_name("keys")
+            .help("Sets the key binding preset. The vim preset uses hjkl to move, space to uncover, f to flag, and gg/G to jump to the top/bottom row")
+            .short("k")
+            .long("keys")
+            .value_name("PRESET")
+            .takes_value(true)
+            .possible_values(&KeyPreset::variants())
+            .case_insensitive(true),
+        Arg::with_name("no_flag")
+            .help("Disables flagging entirely, so the board must be cleared by uncovering alone")
+            .long("no-flag"),
+        Arg::with_name("auto_flag")
+            .help("Flags covered tiles automatically once a number leaves no doubt that they are mines. Games played this way are kept apart in the stats")
+            .long("auto-flag")
+            .conflicts_with("no_flag"),
+        Arg::with_name("strict_flags")
+            .help("Stops placing flags once there are as many flags as mines, instead of letting the counter go negative")
+            .long("strict-flags"),
+        Arg::with_name("hardcore")
+            .help("Makes flags permanent, and placing a flag on a safe tile loses the game")
+            .long("hardcore")
+            .conflicts_with("no_flag"),
+        Arg::with_name("seed")
+            .help("Sets the seed used to generate the board, so the same board can be played again")
+            .long("seed")
+            .value_name("SEED")
+            .takes_value(true),
+        Arg::with_name("code")
+            .help("Prints a short code for the board when the game ends, which plays the same board again with --from-code")
+            .long("code")
+            .conflicts_with_all(&["load", "saves", "treasure", "liar", "anti_mines", "stacked_mines", "infinite", "json_events"]),
+        Arg::with_name("from_code")
+            .help("Plays the board a code printed by --code stands for, with its size, mines, seed and rules")
+            .long("from-code")
+            .value_name("CODE")
+            .takes_value(true)
+            .conflicts_with_all(&["width", "height", "mine_num", "max_width", "max_height", "difficulty", "smart_difficulty", "rated", "seed", "layers", "load", "saves", "daily", "infinite", "no_flag", "hardcore", "strict_flags", "line_hints"]),
+        Arg::with_name("daily")
+            .help("Plays the daily puzzle, which is the same board for everyone playing with the same size on the same day (in UTC). Winning it keeps your daily streak going")
+            .long("daily")
+            .conflicts_with_all(&["seed", "load", "saves", "rated", "infinite"]),
+        Arg::with_name("load")
+            .help("Loads a game saved with the :save command")
+            .long("load")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with_all(&["width", "height", "mine_num", "max_width", "max_height", "difficulty", "smart_difficulty", "seed", "no_flag", "hardcore", "strict_flags"]),
+        Arg::with_name("import_board")
+            .help("Plays a board exported from another minesweeper, either a text grid or a Minesweeper X board (.mbf). See the README for the formats")
+            .long("import-board")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "saves", "width", "height", "mine_num", "max_width", "max_height", "difficulty", "smart_difficulty", "rated", "seed", "layers", "daily", "infinite", "from_code", "code", "record", "treasure", "liar", "anti_mines", "stacked_mines", "rush"]),
+        Arg::with_name("saves")
+            .help("Lists the saves in the current directory with their size, time and progress, to load, rename or delete them before playing")
+            .long("saves")
+            .conflicts_with_all(&["load", "headless", "infinite", "rated"]),
+        Arg::with_name("theme")
+            .help("Sets the color theme")
+            .short("t")
+            .long("theme")
+            .value_name("THEME")
+            .takes_value(true)
+            .possible_values(&ThemeName::variants())
+            .case_insensitive(true),
+        Arg::with_name("theme_file")
+            .help("Loads a theme from a TOML file. See the README for the format")
+            .long("theme-file")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with("theme"),
+        Arg::with_name("no_color")
+            .help("Draws everything without colors. Also turned on by setting NO_COLOR")
+            .long("no-color"),
+        Arg::with_name("idle")
+            .help("Pauses the timer after SECONDS without any input. Any key resumes the game")
+            .long("idle")
+            .value_name("SECONDS")
+            .takes_value(true),
+        Arg::with_name("highlight_neighbors")
+            .help("Highlights the neighbors of the number under the cursor")
+            .long("highlight-neighbors"),
+        Arg::with_name("history")
+            .help("Shows the most recent moves next to the board. Press H to toggle it during the game")
+            .long("history"),
+        Arg::with_name("show_deductions")
+            .help("Colors covered tiles that can be proven safe green and proven mines red. Press I to toggle it during the game. Games played this way are kept apart in the stats")
+            .long("show-deductions"),
+        Arg::with_name("score")
+            .help("Shows a score in the header instead of the progress, and again once the game ends. See the README for how it is worked out")
+            .long("score"),
+        Arg::with_name("hint_penalty")
+            .help("Sets how many seconds are added to the time for each hint, each automatic flag and each time the deductions are shown")
+            .long("hint-penalty")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .default_value("10"),
+        Arg::with_name("headless")
+            .help("Plays without drawing the game, reading commands like those of the : prompt from stdin, one per line, and printing the board after each move")
+            .long("headless"),
+        Arg::with_name("json_events")
+            .help("Prints one JSON object per game event instead of the board. Only works with --headless")
+            .long("json-events")
+            .requires("headless"),
+        Arg::with_name("rated")
+            .help("Picks a board to match your rating, which goes up with wins and down with losses. The rating is worked out from the stats, leaving out assisted games")
+            .long("rated")
+            .conflicts_with_all(&["width", "height", "mine_num", "max_width", "max_height", "difficulty", "smart_difficulty", "load"]),
+        Arg::with_name("infinite")
+            .help("Plays on a board without edges that is generated as you explore it, until you hit a mine. The mine density changes from area to area around the one set with --smart-difficulty")
+            .long("infinite")
+            .conflicts_with_all(&["width", "height", "mine_num", "max_width", "max_height", "difficulty", "load", "rated", "record", "headless", "casual"]),
+        Arg::with_name("hotseat")
+            .help("Two players take turns on the same board. Every tile a player uncovers is worth a point, and hitting a mine loses the game. Hotseat games are not recorded in the stats")
+            .long("hotseat")
+            .conflicts_with_all(&["casual", "rated", "headless"]),
+        Arg::with_name("coop")
+            .help("Adds a second cursor for a second player on the same keyboard, moved with the arrow keys, uncovering with comma and flagging with period")
+            .long("coop")
+            .conflicts_with_all(&["hotseat", "headless"]),
+        Arg::with_name("vs_bot")
+            .help("Races the solver with the given strategy on a copy of the board, with its progress shown under the board")
+            .long("vs-bot")
+            .value_name("STRATEGY")
+            .takes_value(true)
+            .possible_values(&Strategy::variants())
+            .case_insensitive(true)
+            .conflicts_with_all(&["hotseat", "coop", "headless"]),
+        Arg::with_name("bot_pace")
+            .help("Sets how many milliseconds the bot from --vs-bot waits between moves, 500 by default")
+            .long("bot-pace")
+            .value_name("MILLIS")
+            .takes_value(true)
+            .requires("vs_bot"),
+        Arg::with_name("arcade")
+            .help("Opening 20 or more tiles at once earns a power-up, used with the number keys: 1 reveals a random safe tile, 2 freezes the timer for 10 seconds and 3 defuses the mine under the flag at the cursor. Arcade games are not recorded in the stats")
+            .long("arcade")
+            .conflicts_with_all(&["hotseat", "vs_bot", "headless", "record", "rated"]),
+        Arg::with_name("treasure")
+            .help("Hides treasure under N safe tiles. Every treasure found takes 5 seconds off the clock. Treasure games are not recorded in the stats")
+            .long("treasure")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated", "headless"]),
+        Arg::with_name("treasure_hints")
+            .help("Draws numbers next to treasure that hasn't been found in the treasure color")
+            .long("treasure-hints")
+            .requires("treasure"),
+        Arg::with_name("liar")
+            .help("Shows PERCENT of the numbers one higher or one lower than they should be. Blanks never lie, and numbers never lie down to a blank. Liar games are not recorded in the stats")
+            .long("liar")
+            .value_name("PERCENT")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated"]),
+        Arg::with_name("anti_mines")
+            .help("Turns N of the mines into anti-mines, which take one away from the numbers around them instead of adding one, so numbers can be zero or below. Anti-mines are flagged by flagging a tile twice. Games with anti-mines are not recorded in the stats")
+            .long("anti-mines")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated", "liar", "arcade", "hardcore", "auto_flag", "infinite"]),
+        Arg::with_name("stacked_mines")
+            .help("Adds N more mines on top of tiles that already have one, up to 3 on a tile. Numbers count every mine, going on as letters past 9, and flags stack up to 3 by flagging a tile again. Games with stacked mines are not recorded in the stats")
+            .long("stacked-mines")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated", "liar", "anti_mines", "arcade", "hardcore", "auto_flag", "infinite"]),
+        Arg::with_name("line_hints")
+            .help("Shows how many mines are left in every row to the right of the board and in every column under it, counting flags as mines found. Games with line hints are not recorded in the stats")
+            .long("line-hints")
+            .conflicts_with_all(&["load", "rated", "anti_mines", "stacked_mines", "layers", "rush", "infinite"]),
+        Arg::with_name("no_border_mines")
+            .help("Keeps mines off the outermost ring of tiles. Games without border mines are not recorded in the stats")
+            .long("no-border-mines")
+            .conflicts_with_all(&["load", "import_board", "rated", "daily", "code", "from_code", "layers", "rush", "infinite"]),
+        Arg::with_name("symmetry")
+            .help("Mirrors every mine onto the other side of the board: horizontal mirrors the left half onto the right, vertical the top half onto the bottom, and rotational turns the board half way round. An odd number of mines needs a middle column, row or tile for the last one. Symmetric games are not recorded in the stats")
+            .long("symmetry")
+            .value_name("MODE")
+            .takes_value(true)
+            .possible_values(&Symmetry::variants())
+            .conflicts_with_all(&["load", "import_board", "rated", "daily", "code", "from_code", "layers", "rush", "infinite"]),
+        Arg::with_name("min_opening")
+            .help("Only uses boards with a patch of at least TILES blank tiles that open together, and clears the mines away from the first uncover so it opens that many too. Games with a minimum opening are not recorded in the stats")
+            .long("min-opening")
+            .value_name("TILES")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "import_board", "rated", "daily", "code", "from_code", "layers", "rush", "infinite"]),
+        Arg::with_name("blind")
+            .help("Numbers fade back to blank SECONDS after they are opened, so they have to be remembered. Blind games are not recorded in the stats")
+            .long("blind")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .conflicts_with_all(&["blind_moves", "headless", "rated"]),
+        Arg::with_name("blind_moves")
+            .help("Like --blind, but numbers fade after N more moves instead")
+            .long("blind-moves")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["headless", "rated"]),
+        Arg::with_name("rush")
+            .help("Scrolls the board up a row every SECONDS once you start, with new rows of mines coming in at the bottom. Every mine that goes off the top without a flag costs one of 3 lives, and the game goes on until they run out. Rush games are not recorded in the stats")
+            .long("rush")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated", "record", "casual", "headless", "infinite", "hotseat", "coop", "vs_bot", "liar", "anti_mines", "stacked_mines", "blind", "blind_moves", "auto_flag"]),
+        Arg::with_name("layers")
+            .help("Stacks N layers of the board on top of each other, with every tile touching the ones around the same place on the layers above and below, up to 26 in all. Page Up and Page Down switch layers. --mines counts the mines on every layer. Games with layers are not recorded in the stats")
+            .long("layers")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "rated", "record", "headless", "infinite", "stacked_mines", "rush"]),
+        Arg::with_name("autosave")
+            .help("Saves the game to the autosave every SECONDS while you play, so a crash or a dropped connection loses at most that much of it. The autosave is offered again the next time you start a game of the same size")
+            .long("autosave")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .conflicts_with_all(&["autosave_moves", "headless", "rush"]),
+        Arg::with_name("autosave_moves")
+            .help("Like --autosave, but saves every N moves instead")
+            .long("autosave-moves")
+            .value_name("N")
+            .takes_value(true)
+            .conflicts_with_all(&["headless", "rush"]),
+        Arg::with_name("cell_size")
+            .help("Draws every tile N characters wide and N / 2 rows tall, for bigger tiles that are easier to read. N goes from 1 to 4")
+            .long("cell-size")
+            .value_name("N")
+            .takes_value(true),
+        Arg::with_name("square")
+            .help("Puts a space after every tile, which makes the board about square like in classic minesweeper")
+            .long("square")
+            .conflicts_with("cell_size"),
+        Arg::with_name("max_fps")
+            .help("Limits how many times a second the screen is updated, 30 by default. Updates in between are drawn together, which helps over slow connections. 0 turns the limit off")
+            .long("max-fps")
+            .value_name("FPS")
+            .takes_value(true),
+        Arg::with_name("casual")
+            .help("Hitting a mine offers to rewind to just before that move instead of ending the game. Games with a rewind are kept apart in the stats")
+            .long("casual"),
+        Arg::with_name("record")
+            .help("Writes a replay of the game to FILE when it ends, which can be watched with the replay subcommand")
+            .long("record")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with_all(&["load", "casual"]),
+        Arg::with_name("cast")
+            .help("Writes what the game drew and when to FILE as an asciinema cast when it ends, to be played with asciinema play or embedded on a web page")
+            .long("cast")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with("headless"),
+        Arg::with_name("submit")
+            .help("Sends the seed, board, time and a checksum of the replay of a win to the leaderboard at URL, which has to be http://. Can be turned off for good with submit = false in the config file")
+            .long("submit")
+            .value_name("URL")
+            .takes_value(true)
+            .conflicts_with_all(&["headless", "load"]),
+        Arg::with_name("spectate_port")
+            .help("Lets others watch the game live with the spectate subcommand, by listening for them on PORT")
+            .long("spectate-port")
+            .value_name("PORT")
+            .takes_value(true)
+            .conflicts_with("headless"),
+        Arg::with_name("spectate_addr")
+            .help("Listens for spectators on ADDRESS instead of 127.0.0.1, which only lets in this computer. 0.0.0.0 lets in anyone who can reach the port")
+            .long("spectate-addr")
+            .value_name("ADDRESS")
+            .takes_value(true)
+            .requires("spectate_port"),
+    ];
+
+    #[cfg(feature = "gui")]
+    args.push(
+        Arg::with_name("gui")
+            .help("Opens the game in a window instead of the terminal")
+            .long("gui")
+    );
+
+    #[cfg(feature = "log")]
+    args.push(
+        Arg::with_name("log")
+            .help("Writes what happens during the game to FILE, one JSON object per line, for tracking down bugs")
+            .long("log")
+            .value_name("FILE")
+            .takes_value(true)
+    );
+
+    args
+}
+
+fn play(matches: &ArgMatches) {
+    let gui = matches.is_present("gui");
+    let headless = matches.is_present("headless");
+    let infinite = matches.is_present("infinite");
+    let daily = matches.is_present("daily");
+    let import = matches.value_of("import_board");
+    let terminal = !gui && !headless;
 
     let mut width = value_t!(matches, "width", u16).unwrap_or(22);
     let mut height = value_t!(matches, "height", u16).unwrap_or(12);
     let mut mine_num = value_t!(matches, "mine_num", u16).unwrap_or(41);
 
-    let size = size().unwrap();
-    let size = (size.0 - 2, size.1 - 5);
+    let size = if terminal { size().unwrap() } else { (u16::MAX, u16::MAX) };
 
-    if width > size.0 { 
-        println!("error: width cannot be larger then the terminal width - 2");
-        return; 
-    }
+    let square = matches.is_present("square");
+    let cell_size = value_t!(matches, "cell_size", u16).unwrap_or(if square { 2 } else { 1 });
+    let (cell_width, cell_height) = cell_dimensions(cell_size);
 
-    if height > size.1 { 
-        println!("error: height cannot be larger then the terminal height - 5");
-        return; 
+    if cell_size == 0 || cell_size > 4 {
+        println!("error: the cell size must be between 1 and 4");
+        return;
     }
 
-    if mine_num >= width * height {
-        println!("error: number of mines cannot be equal to or larger then the total number of tiles");
-        return;
+    #[cfg(feature = "log")]
+    if let Some(path) = matches.value_of("log") {
+        if let Err(e) = log::open(path) {
+            println!("error: {}", e);
+            return;
+        }
     }
 
     if matches.is_present("max_width") {
-        width = size.0;
+        width = (size.0 - 2) / cell_width;
     }
 
     if matches.is_present("max_height") {
-        height = size.1;
+        height = size.1.saturating_sub(Layout::new(width, 1, mine_num, cell_size).board_top + 2) / cell_height;
     }
 
     if let Ok(i) = value_t!(matches, "difficulty", Difficulty) {
-        match i {
-            Difficulty::Beginner => {
-                width = 22;
-                height = 4;
-                mine_num = 11;
-            },
-            Difficulty::Intermediate => {
-                width = 22;
-                height = 12;
-                mine_num = 41;
-            }
-            Difficulty::Expert => {
-                width = 22;
-                height = 22;
-                mine_num = 100;
-            }
-        }
+        let (w, h, m) = i.size();
+        width = w;
+        height = h;
+        mine_num = m;
     }
 
     if let Ok(i) = value_t!(matches, "smart_difficulty", Difficulty) {
-        mine_num = ((width * height) as f32 * Difficulty::value(&i)) as u16;
+        mine_num = ((width as usize * height as usize) as f32 * Difficulty::value(&i)) as u16;
     }
 
-    let mut working_board = Board::new(width as usize, height as usize, mine_num as usize).unwrap();
+    let mut records = stats::default_path().and_then(|i| stats::load(&i).ok()).unwrap_or_default();
+    let rating = rating::rating(&records);
 
-    let mut stdout = stdout();
-    enable_raw_mode().unwrap();
+    let code = match matches.value_of("from_code").map(code::Code::decode) {
+        Some(Ok(i)) => Some(i),
+        Some(Err(e)) => {
+            println!("error: invalid board code: {}", e);
+            return;
+        },
+        None => None,
+    };
 
-    execute!(
-        stdout, 
-        Clear(ClearType::All), 
-        cursor::MoveTo(0, 0),
-        cursor::DisableBlinking,
-        DisableLineWrap,
-    );
+    if let Some(i) = &code {
+        width = i.width;
+        height = i.height;
+        mine_num = i.mines;
+    }
 
-    print!("╔═════╦");
-    for _ in 0..width - SPACING { print!("═") }
-    print!("╦═════╗\r\n");
+    if matches.is_present("rated") {
+        let (w, h, m) = rating::propose(rating);
+        width = w;
+        height = h;
+        mine_num = m;
+    }
 
-    print!("║ {:03} ║", cmp::min(working_board.mine_total, 999));
+    let layers = match &code {
+        Some(i) => i.layers,
+        None => value_t!(matches, "layers", usize).unwrap_or(1),
+    };
 
-    for _ in 0..width - SPACING { print!(" ") }
+    if layers == 0 {
+        println!("error: there has to be at least one layer");
+        return;
+    }
 
-    print!("║ 000 ║\r\n");
-    print!("╠═════╩");
-    for _ in 0..width - SPACING { print!("═") }
-    print!("╩═════╣\r\n");
+    let load = if matches.is_present("saves") {
+        match browse_saves() {
+            Some(i) => Some(i),
+            None => return,
+        }
+    } else {
+        matches.value_of("load").map(PathBuf::from)
+    };
 
-    print!("{}\r\n", working_board);
+    // Offers to carry on a game that was quit on a board of the same size
+    let load = match (load, saves::autosave_path()) {
+        (None, Some(path)) if terminal && !infinite && !daily && import.is_none() && saves::autosave(&path, width as usize, height as usize, mine_num as usize, layers).is_some() => {
+            print!("Resume previous game? (y/n) ");
+            std::io::Write::flush(&mut std::io::stdout()).unwrap();
 
-    print!("╚");
-    for _ in 0..width {
-        print!("═");
-    }
-    print!("╝");
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).unwrap_or_default();
 
-    execute!(
-        stdout,
-        cursor::MoveTo(1, 3),
-    );
+            if line.trim().eq_ignore_ascii_case("y") { Some(path) } else { None }
+        },
+        (load, _) => load,
+    };
 
-    let cursor_pos = Arc::new(Mutex::new((0u16, 0u16)));
+    if load.is_none() && import.is_none() {
+        if let (true, false, Err(e)) = (terminal, infinite, check_fit(width, height, mine_num, cell_size, size)) {
+            println!("error: {}", e);
+            return;
+        }
 
-    let (main_tx, clock_rx) = mpsc::channel::<bool>();
-    launch_clock(Arc::clone(&cursor_pos), width.clone(), clock_rx);
+        // Without a terminal to fit in, headless and gui boards are held to the same size as imported ones
+        if !terminal && (width as usize > import::MAX_SIDE || height as usize > import::MAX_SIDE) {
+            println!("error: the board is {}x{}, larger than {} on a side", width, height, import::MAX_SIDE);
+            return;
+        }
 
-    loop {  
-        match read().unwrap() {
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('q'),
-                modifiers: KeyModifiers::CONTROL,
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Esc, ..
-            }) => {
-                execute!(stdout.lock(), cursor::MoveTo(0, 0), Clear(ClearType::All));
-                break
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Right, ..
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Char('d'), ..
-            }) => {
-                let mut pos = cursor_pos.lock().unwrap();
+        if mine_num as usize >= width as usize * height as usize * layers {
+            println!("error: number of mines cannot be equal to or larger then the total number of tiles");
+            return;
+        }
+    }
 
-                if pos.0 < width - 1 {
-                    execute!(stdout.lock(), cursor::MoveRight(1)).unwrap();
-                    pos.0 += 1;
-                }
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Left, ..
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Char('a'), ..
-            }) => {
-                let mut pos = cursor_pos.lock().unwrap();
+    let theme = match matches.value_of("theme_file") {
+        Some(path) => match Theme::from_file(path) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: invalid theme file {}: {}", path, e);
+                return;
+            }
+        },
+        None => Theme::builtin(value_t!(matches, "theme", ThemeName).unwrap_or(ThemeName::Default)),
+    };
+    let theme = fit_theme(theme, matches.is_present("no_color"));
 
-                if pos.0 > 0 {
-                    execute!(stdout.lock(), cursor::MoveLeft(1)).unwrap();
-                    pos.0 -= 1;
-                }
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Up, ..
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Char('w'), ..
-            }) => {
-                let mut pos = cursor_pos.lock().unwrap();
+    let settings = Settings {
+        highlight_neighbors: matches.is_present("highlight_neighbors"),
+        show_deductions: matches.is_present("show_deductions"),
+        hint_penalty: value_t_or_exit!(matches, "hint_penalty", u64),
+        show_score: matches.is_present("score"),
+        casual: matches.is_present("casual"),
+        show_history: matches.is_present("history"),
+        hotseat: matches.is_present("hotseat"),
+        coop: matches.is_present("coop"),
+        cell_size: cell_size,
+        square: square,
+        max_fps: value_t!(matches, "max_fps", u32).unwrap_or(30),
+        theme: theme,
+    };
 
-                if pos.1 > 0 {
-                    execute!(stdout.lock(), cursor::MoveUp(1)).unwrap();
-                    pos.1 -= 1;
-                }
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Down, ..
-            }) | Event::Key(KeyEvent {
-                code: KeyCode::Char('s'), ..
-            }) => {
-                let mut pos = cursor_pos.lock().unwrap();
+    let keymap = Keymap::new(value_t!(matches, "keys", KeyPreset).unwrap_or(KeyPreset::Default));
 
-                if pos.1 < height - 1 {
-                    execute!(stdout.lock(), cursor::MoveDown(1)).unwrap();
-                    pos.1 += 1;
-                }
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('q'), ..
-            }) => {
-                let pos = cursor_pos.lock().unwrap();
+    if infinite {
+        let density = value_t!(matches, "smart_difficulty", Difficulty).unwrap_or(Difficulty::Intermediate).value();
+        let seed = value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random());
 
-                working_board.push_state(pos.0 as usize, pos.1 as usize, PushState::Uncover);
-                refresh_board(&pos, &working_board, &width, &main_tx);
+        play_infinite(seed, density as f64, settings.theme, keymap);
+        return;
+    }
 
-                if working_board.won.is_some() { 
-                    execute!(stdout.lock(), cursor::MoveTo(0, height + 4));
-                    break 
-                }
-            },
-            Event::Key(KeyEvent {
-                code: KeyCode::Char('e'), ..
-            }) => {
-                let pos = cursor_pos.lock().unwrap();
+    let no_flag = matches.is_present("no_flag") || code.as_ref().map_or(false, |i| i.no_flag);
+    let idle_after = value_t!(matches, "idle", u64).ok().map(Duration::from_secs);
 
-                working_board.push_state(pos.0 as usize, pos.1 as usize, PushState::Flag);
-                refresh_board(&pos, &working_board, &width, &main_tx);
+    let mut working_board = match (&load, import) {
+        (Some(path), _) => match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|i| Board::from_save(&i)) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: could not load {}: {}", path.display(), e);
+                return;
+            }
+        },
+        (None, Some(path)) => match fs::read(path).map_err(|e| e.to_string()).and_then(|i| import::board(&i, Path::new(path))) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: could not import {}: {}", path, e);
+                return;
+            }
+        },
+        (None, None) => {
+            let seed = match (daily, &code) {
+                (true, _) => daily::seed(daily::today()),
+                (false, Some(i)) => i.seed,
+                (false, None) => value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random()),
+            };
+            let generation = Generation {
+                no_border_mines: matches.is_present("no_border_mines"),
+                symmetry: value_t!(matches, "symmetry", Symmetry).ok(),
+                min_opening: value_t!(matches, "min_opening", usize).unwrap_or(0),
+            };
 
-                if working_board.won.is_some() { 
-                    execute!(stdout.lock(), cursor::MoveTo(0, height + 4));
-                    break 
+            let mut board = match Board::generate(width as usize, height as usize * layers, mine_num as usize, seed, generation) {
+                Ok(i) => i,
+                Err(e) => {
+                    println!("error: {}", e);
+                    return;
                 }
-            },
-            _ => (),
-        }
-    }
+            };
 
-    execute!(stdout, EnableLineWrap);
-    disable_raw_mode().unwrap();
-}
+            board.split_layers(layers);
+            board
+        }
+    };
 
-fn launch_clock(cursor_pos: Arc<Mutex<(u16, u16)>>, width: u16, rx: mpsc::Receiver<bool>) {
-    let stdout = stdout();
-    let mut time = 0;
+    if load.is_some() || import.is_some() {
+        width = working_board.width() as u16;
+        height = working_board.layer_height() as u16;
+        mine_num = working_board.mine_total as u16;
 
-    thread::spawn(move || { 
-        if !rx.recv().unwrap() {
+        if let (true, Err(e)) = (terminal, check_fit(width, height, mine_num, cell_size, size)) {
+            println!("error: the {} board does not fit in the terminal: {}", if load.is_some() { "saved" } else { "imported" }, e);
             return;
         }
+    }
+
+    if load.is_none() {
+        working_board.no_flag = no_flag;
+        working_board.hardcore = matches.is_present("hardcore") || code.as_ref().map_or(false, |i| i.hardcore);
+        working_board.strict_flags = matches.is_present("strict_flags") || code.as_ref().map_or(false, |i| i.strict_flags);
+
+        if matches.is_present("treasure") {
+            working_board.place_treasure(value_t_or_exit!(matches, "treasure", usize));
+        }
 
-        for _ in 0..1000 {
-            thread::sleep(Duration::from_secs(1));
+        if matches.is_present("liar") {
+            let chance = value_t_or_exit!(matches, "liar", usize);
 
-            if Ok(false) == rx.try_recv() {
+            if chance > 100 {
+                println!("error: the liar percent cannot be larger then 100");
                 return;
             }
 
-            let mut stdout_handle = stdout.lock();
-            let pos = cursor_pos.lock().unwrap();
-            time += 1;
-
-            execute!(
-                stdout_handle,
-                cursor::MoveTo(width - 3, 1),
-                Print(&format!("{:03}", time)[..]),
-                cursor::MoveTo(pos.0 + 1, pos.1 + 3),
-            );
+            working_board.add_lies(chance);
         }
-    });
-}
 
-fn refresh_board(pos: &(u16, u16), working_board: &Board, width: &u16, tx: &mpsc::Sender<bool>) {
-    let stdout = stdout();
-    let mut stdout_handle = stdout.lock();
+        if matches.is_present("anti_mines") {
+            working_board.add_anti_mines(value_t_or_exit!(matches, "anti_mines", usize));
+        }
 
-    execute!(
-        stdout_handle, 
-        cursor::Hide,
-        cursor::MoveTo(0, 3),
-        Print(working_board),
-    );
+        if matches.is_present("stacked_mines") {
+            working_board.stack_mines(value_t_or_exit!(matches, "stacked_mines", usize));
+        }
 
-    execute!(
-        stdout_handle, 
-        cursor::MoveTo(2, 1),
-        Print(&format!("{:03}", cmp::min(working_board.mine_total - working_board.flag_total, 999))[..])
-    );
+        working_board.treasure_hints = matches.is_present("treasure_hints");
+        working_board.line_hints = matches.is_present("line_hints") || code.as_ref().map_or(false, |i| i.line_hints);
+    }
 
-    if let Some(i) = working_board.won {
-        let spacer = width / 2 - 3;
-
-        execute!(
-            stdout_handle, 
-            cursor::MoveTo(spacer, 1),
-            Print(match i {
-                true if width % 2 == 0 => "YOU  WON",
-                true => " YOU WON ",
-                false if width % 2 == 0 => "YOU LOST",
-                false => "YOU  LOST"
-            }),
-        );
+    working_board.auto_flag = matches.is_present("auto_flag");
 
-        let _ = tx.send(false);
-    } else {
-        let _ = tx.send(true);
+    #[cfg(feature = "gui")]
+    {
+        if gui {
+            gui::run(working_board);
+            return;
+        }
     }
 
-    execute!(
-        stdout_handle,
-        cursor::MoveTo(pos.0 + 1, pos.1 + 3),
-        cursor::Show,
-    );
-}
-
-mod board {
-    use rand::thread_rng;
-    use rand::seq::SliceRandom;
-    use std::fmt;
+    let mut game = Game::new(working_board, settings, keymap, idle_after);
 
-    use crossterm::style::Colorize;
+    if terminal && game.board.line_hints && (game.layout.status >= size.1 || game.layout.width + 2 + game.layout.gutters.0 > size.0) {
+        println!("error: the board does not fit in the terminal with its line hints");
+        return;
+    }
 
-    use super::*;
+    if let Ok(i) = value_t!(matches, "vs_bot", Strategy) {
+        let pace = Duration::from_millis(value_t!(matches, "bot_pace", u64).unwrap_or(500));
+        game.bot = Some(game::Bot::new(game.board.clone(), i, pace));
+    }
 
-    fn get_manhattan() -> Vec<(i32, i32)> {
-        vec![
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, -1),
-            (0, 1),
-            (1, -1),
-            (1, 0),
-            (1, 1)
-        ]
+    if matches.is_present("arcade") {
+        game.arcade = Some(game::Arcade::new(game.board.seed));
     }
 
-    fn get_2d(i: usize, width: usize) -> (usize, usize) {
-        (i % width, i / width)
+    if daily {
+        let days = daily::default_path().and_then(|i| daily::load(&i).ok()).unwrap_or_default();
+        game.daily = Some(daily::Daily::new(daily::today(), &days));
     }
 
-    fn get_1d(x: usize, y: usize, width: usize) -> usize {
-        y * width + x
+    match config::load().map(|i| i.acceleration) {
+        Ok(Some(i)) if i.is_empty() || i.contains(&0) => {
+            println!("error: acceleration in the config file needs at least one step, and every step has to be 1 or more");
+            return;
+        },
+        Ok(i) => game.acceleration = i.unwrap_or_else(|| vec![1, 2, 4]),
+        Err(e) => {
+            println!("error: could not read the config file: {}", e);
+            return;
+        }
     }
 
-    fn get_1d_manhattan(i: usize, width: usize) -> Vec<usize> {
-        let (x, y) = get_2d(i, width);
+    let fade = if matches.is_present("blind") {
+        Some(game::Interval::Seconds(value_t_or_exit!(matches, "blind", u64)))
+    } else if matches.is_present("blind_moves") {
+        Some(game::Interval::Moves(value_t_or_exit!(matches, "blind_moves", usize)))
+    } else {
+        None
+    };
 
-        get_manhattan().iter()
-            .map(|i| (i.0 + x as i32, i.1 + y as i32))
-            .filter_map(|i| match i {
-                (x, y) if width as i32 > x && x >= 0 && y >= 0 => Some(
-                    get_1d(x as usize, y as usize, width)
-                ),
-                _ => None,
-            }).collect()
+    game.blind = fade.map(|i| game::Blind::new(i, game.board.tiles.len()));
+
+    let every = if matches.is_present("autosave") {
+        Some(game::Interval::Seconds(value_t_or_exit!(matches, "autosave", u64)))
+    } else if matches.is_present("autosave_moves") {
+        Some(game::Interval::Moves(value_t_or_exit!(matches, "autosave_moves", usize)))
+    } else {
+        None
+    };
+
+    game.autosave = every.and_then(|i| saves::autosave_path().map(|path| game::Autosave::new(path, i)));
+
+    if matches.is_present("spectate_port") {
+        let address = matches.value_of("spectate_addr").unwrap_or("127.0.0.1");
+
+        match spectate::Server::bind(address, value_t_or_exit!(matches, "spectate_port", u16)) {
+            Ok(i) => game.spectators = Some(i),
+            Err(e) => {
+                println!("error: could not listen for spectators: {}", e);
+                return;
+            }
+        }
     }
 
-    #[derive(PartialEq, Hash, Debug, Clone)]
-    enum State {
-        Uncovered,
-        Covered,
-        Flagged,
-        FlagRevealed,
+    if matches.is_present("rush") {
+        game.board.start_rush();
+        game.rush = Some(game::Rush::new(Duration::from_secs(value_t_or_exit!(matches, "rush", u64))));
     }
 
-    pub enum PushState {
-        Uncover,
-        Flag,
+    let game = if headless {
+        headless::run(game, matches.is_present("json_events"))
+    } else {
+        run(game, None, matches.value_of("cast"))
+    };
+
+    if matches.is_present("code") {
+        println!("Board code: {}", code::Code::of(&game.board).encode());
     }
 
-    #[derive(PartialEq, Hash, Debug, Clone)]
-    pub struct Tile {
-        state: State,
-        mine: bool,
-        mines_surrounding: usize,
+    if let Some(path) = matches.value_of("record") {
+        if let Err(e) = fs::write(path, seal::sign(&game.replay().to_text())) {
+            println!("error: could not write the replay to {}: {}", path, e);
+        }
     }
 
-    impl fmt::Display for Tile {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{}", match self.state {
-                State::Covered => String::from("░"),
-                State::Uncovered if self.mine => String::from("Ø").red().to_string(),
-                State::Uncovered if self.mines_surrounding > 0 => self.mines_surrounding.to_string(),
-                State::Uncovered => String::from(" "),
-                State::Flagged => String::from("Þ").green().to_string(),
-                State::FlagRevealed if self.mine => String::from("Þ").green().to_string(),
-                State::FlagRevealed => String::from("Þ").yellow().to_string(),
-            })
+    // Quitting in the middle of a game keeps it for next time, and finishing one clears it
+    if let (Some(path), false) = (saves::autosave_path(), headless) {
+        if game.board.won.is_some() {
+            fs::remove_file(&path).ok();
+        } else if !game.steps.is_empty() && game.rush.is_none() {
+            let mut board = game.board.clone();
+            board.elapsed = game.clock.seconds();
+
+            if let Err(e) = saves::write_autosave(&path, &board) {
+                println!("error: could not save the game to {}: {}", path.display(), e);
+            }
         }
     }
 
-    impl Tile {
-        fn new(mine: &bool, mines_surrounding: &usize) -> Tile {
-            Tile {
-                state: State::Covered,
-                mine: *mine,
-                mines_surrounding: *mines_surrounding
+    // The puzzle counts for the day it was started on, so one finished just after midnight isn't lost
+    if let (Some(puzzle), Some(path), false) = (&game.daily, daily::default_path(), headless) {
+        if game.board.won == Some(true) {
+            match daily::append(&path, puzzle.day).and_then(|_| daily::load(&path)) {
+                Ok(days) => {
+                    let (current, best) = daily::streaks(&days, daily::today());
+                    println!("Daily puzzle for {} solved. Streak: {} day{} (best {})", daily::date(puzzle.day), current, if current == 1 { "" } else { "s" }, best);
+                },
+                Err(e) => println!("error: could not record the daily puzzle in {}: {}", path.display(), e),
             }
         }
     }
 
-    #[derive(PartialEq, Debug)]
-    pub struct Board {
-        pub tiles: Vec<Tile>,
-        pub won: Option<bool>,
-        width: usize,
-        pub mine_total: usize,
-        pub flag_total: usize,
-        flag_correct: usize,
-        first_uncover: bool,
+    let mut session = game.session.clone();
+
+    if !game.steps.is_empty() {
+        session.push((game.board.won, game.clock.seconds()));
     }
 
-    impl Board {
-        pub fn new(width: usize, height: usize, mine_num: usize) -> Result<Board, String> {
-            let total = width * height;
+    if session.len() > 1 && !headless {
+        print!("{}", stats::session(&session));
+    }
 
-            if total < mine_num {
-                return Err(String::from("There cannot be more mines then there are tiles"));
+    // Anything but a plain game plays too differently to compare with others
+    let variant = game.hotseat.is_some() || game.arcade.is_some() || game.blind.is_some() || game.board.treasure().1 > 0 || game.board.lie_chance > 0 || game.board.anti_total > 0 || game.board.stacked > 0 || game.rush.is_some() || game.board.layers() > 1 || game.board.line_hints || game.board.generation != Generation::default();
 
-            } else if total == mine_num {
-                return Err(String::from("At least one tile must be safe"));
-            }
+    if let (Some(url), Some(true)) = (matches.value_of("submit"), game.board.won) {
+        submit(url, &game, variant);
+    }
 
-            let mut mine_values = vec![true; mine_num];
-            mine_values.extend(vec![false; total - mine_num]);
-            mine_values.shuffle(&mut thread_rng());
+    if let (Some(_), Some(path), false) = (game.board.won, stats::default_path(), headless || variant) {
+        let record = stats::Record::from_board(&game.board, game.clock.seconds());
 
-            let mine_totals: Vec<usize> = mine_values.iter().enumerate()
-                .map(|i| get_1d_manhattan(i.0, width))
-                .map(|i| {
-                    i.iter()
-                        .filter_map(|n| mine_values.get(*n as usize))
-                        .fold(0, |t, n| t + *n as usize)
-                }).collect();
+        #[cfg(feature = "sqlite")]
+        {
+            let recorded = history::default_path().ok_or_else(|| String::from("no place to keep it"))
+                .and_then(|i| history::open(&i))
+                .and_then(|i| history::record(&i, &record, game.board.three_bv().0, history::now()));
 
-            let tile_data = mine_values.iter().zip(mine_totals.iter());
-            let tiles: Vec<_> = tile_data.map(|i| Tile::new(i.0, i.1)).collect();
-                        
-            Ok(Board {
-                tiles: tiles,
-                width: width,
-                mine_total: mine_num,
-                flag_total: 0,
-                flag_correct: 0,
-                won: None,
-                first_uncover: true,
-            })
-        }
-        
-        pub fn push_state(&mut self, x: usize, y: usize, update: PushState) {
-            if self.won.is_some() {
-                return
+            if let Err(e) = recorded {
+                println!("error: could not record the game in the history: {}", e);
             }
+        }
 
-            let old_tile = self.get_tile(x, y).unwrap();
+        if let Err(e) = stats::append(&path, &record) {
+            println!("error: could not record the game in {}: {}", path.display(), e);
+        } else if matches.is_present("rated") {
+            records.push(record);
+            println!("Rating: {:.0} -> {:.0}", rating, rating::rating(&records));
+        }
+    }
+}
 
-            match (&old_tile.state, update) {
-                (State::Covered, PushState::Uncover) => {
-                    self.uncover_tile(x, y);
-                },
-                (State::Uncovered, _) => {
-                    let manhattan_tile_coords = get_1d_manhattan(
-                        get_1d(x, y, self.width), self.width);
+// Runs a game in the terminal until it ends or the player quits. With a playback, a replay's moves are
+// played back at the pace they were recorded instead of reading moves from the keyboard, and with a cast
+// every frame is also written there as an asciinema recording
+fn run(mut game: Game, mut playback: Option<Playback>, cast: Option<&str>) -> Game {
+    let mut stdout = stdout();
+    enable_raw_mode().unwrap();
 
-                    let flags_surrounding = manhattan_tile_coords.iter()
-                        .filter_map(|i| self.tiles.get(*i))
-                        .fold(0, |t, i| t + (i.state == State::Flagged) as usize);
-                    
-                    if flags_surrounding == old_tile.mines_surrounding {
-                        for coord in manhattan_tile_coords {
-                            if let Some(t) = self.tiles.get(coord) {
-                                if t.state == State::Covered {
-                                    let coords = get_2d(coord, self.width);
-                                    self.uncover_tile(coords.0, coords.1);
-                                }
-                            }
-                        }
-                    }
-                },
-                (State::Flagged, PushState::Flag) => {
-                    self.flag_total -= 1;
-                    self.set_tile_state(x, y, State::Covered);
+    execute!(
+        stdout, 
+        cursor::DisableBlinking,
+        DisableLineWrap,
+        Print(render::PUSH_TITLE),
+    );
 
-                    if self.get_tile(x, y).unwrap().mine {
-                        self.flag_correct -= 1;
-                    }
-                },
-                (State::Covered, PushState::Flag) => {
-                    if self.flag_total < self.mine_total {
-                        self.flag_total += 1;
-                        self.set_tile_state(x, y, State::Flagged);
+    let mut renderer = Terminal::new(game.settings.max_fps);
 
-                        if self.get_tile(x, y).unwrap().mine {
-                            self.flag_correct += 1;
-                        }
-                    }
-                },
-                _ => (),
-            };
+    if cast.is_some() {
+        renderer.cast = Some(cast::Cast::new(size().unwrap()));
+    }
 
-            let uncover_correct = self.tiles.iter()
-                .filter(|i| i.state == State::Uncovered && !i.mine)
-                .collect::<Vec<_>>().len();
+    renderer.draw_frame(&game);
 
-            if self.won.is_none() {
-                if self.flag_correct == self.mine_total || uncover_correct == self.tiles.len() - self.mine_total {
-                    self.end_game(true);
-                }
-            }
-        }
+    if game.board.lie_chance > 0 {
+        renderer.flash_message(&game, &format!("Liar mode: {}% of the numbers are off by one", game.board.lie_chance));
+    }
+
+    if let Some(i) = &playback {
+        game.clock.stop();
+        renderer.show_prompt(&game, &i.status());
+    }
+
+    let events = launch_events();
+    let mut ticked = Instant::now();
+
+    loop {  
+        let message = match renderer.due() {
+            Some(i) => match events.messages.recv_timeout(i) {
+                Ok(i) => i,
+                Err(_) => {
+                    renderer.flush();
+                    continue;
+                }
+            },
+            None => events.messages.recv().unwrap(),
+        };
+
+        #[cfg(feature = "log")]
+        if let Message::Input(i) = &message {
+            log::event("input", serde_json::json!({ "input": format!("{:?}", i) }));
+        }
+
+        let running = match (message, playback.as_mut()) {
+            (Message::Input(i), None) => game.input(i, &mut renderer),
+            (Message::Input(Event::Key(key)), Some(playback)) => replay_key(&mut game, playback, key.code, &mut renderer),
+            (Message::Input(_), Some(_)) => true,
+            (Message::Tick, None) => game.tick(&mut renderer),
+            (Message::Tick, Some(playback)) => {
+                if let Some(played) = playback.advance(ticked.elapsed()) {
+                    show_replay(&mut game, playback, played, &mut renderer);
+                }
+
+                ticked = Instant::now();
+                game.clock.elapsed = playback.position();
+                game.tick(&mut renderer)
+            },
+            #[cfg(feature = "gamepad")]
+            (Message::Pad(i), None) => game.pad(i, &mut renderer),
+            #[cfg(feature = "gamepad")]
+            (Message::Pad(_), Some(_)) => true,
+        };
+
+        if let Some(i) = &game.spectators {
+            i.send(&game);
+        }
+
+        if !running {
+            break
+        }
+    }
+
+    renderer.flush();
+    execute!(stdout, EnableLineWrap, Print(render::POP_TITLE));
+    disable_raw_mode().unwrap();
+
+    if let (Some(path), Some(recording)) = (cast, renderer.cast) {
+        if let Err(e) = fs::write(path, recording.to_text()) {
+            println!("error: could not write the cast to {}: {}", path, e);
+        }
+    }
+
+    game
+}
+
+// Says exactly what goes to the leaderboard before sending it
+fn submit(url: &str, game: &Game, variant: bool) {
+    match config::load() {
+        Ok(i) if i.submit == Some(false) => {
+            println!("Not submitting to {}, since submitting is turned off in the config file", url);
+            return;
+        },
+        Ok(_) => (),
+        Err(e) => {
+            println!("error: could not read the config file: {}", e);
+            return;
+        }
+    }
+
+    if variant || game.board.assisted {
+        println!("Not submitting to {}, since only unassisted games without variants go on the leaderboard", url);
+        return;
+    }
+
+    let result = leaderboard::Submission::of(game);
+
+    println!("Submitting to {}: {}", url, result.describe());
+
+    match leaderboard::post(url, &result.to_json()) {
+        Ok(()) => println!("Submitted"),
+        Err(e) => println!("error: could not submit: {}", e),
+    }
+}
+
+fn spectate(matches: &ArgMatches) {
+    let address = matches.value_of("address").unwrap();
+
+    execute!(stdout(), cursor::Hide);
+    let result = spectate::watch(address);
+    execute!(stdout(), cursor::Show);
+
+    match result {
+        Ok(()) => println!("\r\nThe game has ended"),
+        Err(e) => println!("\r\nerror: could not watch {}: {}", address, e),
+    }
+}
+
+fn replay_key(game: &mut Game, playback: &mut Playback, key: KeyCode, renderer: &mut Terminal) -> bool {
+    let played = playback.played;
+
+    if let Some(text) = playback.writing.as_mut() {
+        match key {
+            KeyCode::Enter => if let Err(e) = playback.finish_note() {
+                renderer.show_prompt(game, &format!("Could not save the note: {}", e));
+                return true;
+            },
+            KeyCode::Esc => playback.writing = None,
+            KeyCode::Backspace => { text.pop(); },
+            KeyCode::Char(c) => text.push(c),
+            _ => (),
+        }
+
+        renderer.show_prompt(game, &playback.status());
+        return true;
+    }
+
+    match key {
+        KeyCode::Char('q') | KeyCode::Esc => return false,
+        KeyCode::Char(' ') => playback.paused = !playback.paused,
+        KeyCode::Char('+') | KeyCode::Char('=') => playback.faster(),
+        KeyCode::Char('-') => playback.slower(),
+        KeyCode::Right | KeyCode::Char('l') => {
+            playback.paused = true;
+            show_replay(game, playback, played + 1, renderer);
+        },
+        KeyCode::Left | KeyCode::Char('h') => {
+            playback.paused = true;
+            show_replay(game, playback, played.saturating_sub(1), renderer);
+        },
+        KeyCode::Home => show_replay(game, playback, 0, renderer),
+        KeyCode::End => show_replay(game, playback, usize::MAX, renderer),
+        KeyCode::Char('[') | KeyCode::Char(']') => if let Some(i) = playback.next_note(key == KeyCode::Char(']')) {
+            playback.paused = true;
+            show_replay(game, playback, i, renderer);
+        },
+        KeyCode::Char('n') if played > 0 => {
+            playback.paused = true;
+            playback.writing = Some(playback.notes.get(&played).cloned().unwrap_or_default());
+        },
+        KeyCode::Char('n') => {
+            renderer.show_prompt(game, "Step to a move to write a note on it");
+            return true;
+        },
+        _ => (),
+    }
+
+    renderer.show_prompt(game, &playback.status());
+    true
+}
+
+// Shows the board as it was after some number of the replay's moves, which can be behind where it is now
+fn show_replay(game: &mut Game, playback: &mut Playback, played: usize, renderer: &mut Terminal) {
+    game.board = playback.seek(played);
+    game.clock.elapsed = playback.position();
+    game.clock.bonus = game.board.treasure().0 as u64 * game::TREASURE_BONUS;
+
+    if let Some(step) = played.checked_sub(1).and_then(|i| playback.steps.get(i)) {
+        game.pos = (step.x as u16, step.y as u16);
+    }
+
+    renderer.draw_board(game);
+    renderer.draw_header(game);
+    renderer.show_prompt(game, &playback.status());
+}
+
+fn solve(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let strategy = value_t!(matches, "strategy", Strategy).unwrap_or(Strategy::Csp);
+
+    let mut board = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|i| Board::from_save(&i)) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: could not load {}: {}", path, e);
+            return;
+        }
+    };
+
+    let mut deductions = 0;
+
+    while board.won.is_none() {
+        let found = solver::deduce(&board.visible_state(), strategy);
+
+        if found.is_empty() {
+            break;
+        }
+
+        deductions += found.safe.len() + found.mines.len();
+        solver::apply(&mut board, &found);
+    }
+
+    println!("{}", board);
+
+    let covered = (0..board.height())
+        .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+        .filter(|i| board.cell(i.0, i.1) == Cell::Covered)
+        .count();
+
+    match board.won {
+        Some(true) => println!("solved with {} deductions", deductions),
+        Some(false) => println!("the {} strategy hit a mine, which should never happen", strategy),
+        None if board.progress() == 0 => println!("nothing is uncovered yet, so the first move has to be a guess"),
+        None => println!("stuck after {} deductions, the next move is a guess with {} tiles still covered", deductions, covered),
+    }
+}
+
+fn replay(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+
+    let replay = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|i| Replay::parse(&i)) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: could not load {}: {}", path, e);
+            return;
+        }
+    };
+
+    let board = match replay.board() {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: invalid replay {}: {}", path, e);
+            return;
+        }
+    };
+
+    if let Err(e) = check_fit(replay.width as u16, replay.height as u16, replay.mines as u16, 1, size().unwrap()) {
+        println!("error: the replay does not fit in the terminal: {}", e);
+        return;
+    }
+
+    let settings = Settings { hint_penalty: 0, ..Settings::new(fit_theme(Theme::default(), false)) };
+
+    let mut playback = Playback::new(&board, replay.steps);
+    let notes_path = replay::notes_path(Path::new(path));
+
+    playback.notes = match fs::read_to_string(&notes_path) {
+        Ok(text) => match replay::parse_notes(&text) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: could not load the notes in {}: {}", notes_path.display(), e);
+                return;
+            }
+        },
+        Err(_) => Default::default(),
+    };
+    playback.notes_path = Some(notes_path);
+
+    run(Game::new(board, settings, Keymap::new(KeyPreset::Default), None), Some(playback), matches.value_of("cast"));
+}
+
+fn analyze(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+
+    // Saves hold a position, while boards in the formats --import-board reads are analyzed from the start
+    let board = fs::read(path).map_err(|e| e.to_string()).and_then(|i| match String::from_utf8(i.clone()) {
+        Ok(text) if text.starts_with("rs-minesweeper save") => Board::from_save(&text),
+        _ => import::board(&i, Path::new(path)),
+    });
+
+    let board = match board {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: could not load {}: {}", path, e);
+            return;
+        }
+    };
+
+    println!("{}: {}, 3BV {}", path, saves::summary(&board), board.three_bv().0);
+    println!("{}", solver::assess(&board));
+
+    if !matches.is_present("solvable") {
+        return;
+    }
+
+    let start = match matches.values_of("start") {
+        Some(values) => {
+            let values: Vec<Option<usize>> = values.map(|i| i.parse::<usize>().ok().and_then(|i| i.checked_sub(1))).collect();
+
+            match (values[0], values[1]) {
+                (Some(x), Some(y)) if x < board.width() && y < board.height() => (x, y),
+                _ => {
+                    println!("error: the start has to be a tile on the board");
+                    return;
+                }
+            }
+        },
+        None => solver::default_start(&board),
+    };
+
+    let strategy = value_t!(matches, "strategy", Strategy).unwrap_or(Strategy::Csp);
+
+    match solver::solvability(&board, start, strategy) {
+        Ok(i) => println!("{}", i.report(start)),
+        Err(e) => println!("error: can't start there: {}", e),
+    }
+}
+
+fn verify(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+
+    let text = match fs::read_to_string(path) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: could not read {}: {}", path, e);
+            return;
+        }
+    };
+
+    let body = match seal::verify(&text) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("{} can't be trusted: {}", path, e);
+            return;
+        }
+    };
+
+    // Plays the replay through to check how it ends and when, rather than trusting its last move
+    if let Ok(replay) = Replay::parse(body) {
+        let mut board = match replay.board() {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: invalid replay {}: {}", path, e);
+                return;
+            }
+        };
+
+        for step in &replay.steps {
+            if board.won.is_some() {
+                break;
+            }
+
+            board.push_state(step.x, step.y, step.update);
+        }
+
+        let result = match board.won {
+            Some(true) => "won",
+            Some(false) => "lost",
+            None => "unfinished",
+        };
+        let seconds = replay.steps.last().map_or(0, |i| i.time / 1000);
+        let moves = if replay.steps.len() == 1 { String::from("1 move") } else { format!("{} moves", replay.steps.len()) };
+
+        println!("{} is unmodified: seed {}, {}, {} after {} seconds", path, replay.seed, moves, result, seconds);
+    } else {
+        match Board::from_save(body) {
+            Ok(board) => println!("{} is unmodified: a save of {}", path, saves::summary(&board)),
+            Err(e) => println!("error: {} is not a save or a replay: {}", path, e),
+        }
+    }
+}
+
+fn campaign(matches: &ArgMatches) {
+    let path = match campaign::default_path() {
+        Some(i) => i,
+        None => {
+            println!("error: could not find a place to keep the campaign, set XDG_DATA_HOME or HOME");
+            return;
+        }
+    };
+
+    let mut state = match fs::read_to_string(&path) {
+        Ok(text) if !matches.is_present("new") => match Campaign::parse(&text) {
+            Ok(i) => i,
+            Err(e) => {
+                println!("error: could not read {}: {}", path.display(), e);
+                return;
+            }
+        },
+        _ => Campaign::new(value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random())),
+    };
+
+    let theme = Theme::builtin(value_t!(matches, "theme", ThemeName).unwrap_or(ThemeName::Default));
+    let theme = fit_theme(theme, matches.is_present("no_color"));
+    let preset = value_t!(matches, "keys", KeyPreset).unwrap_or(KeyPreset::Default);
+    let clock = |seconds: u64| format!("{}:{:02}", seconds / 60, seconds % 60);
+    // None once stdin is closed, like after ctrl-d, which stops the campaign the same way q does
+    let read_line = || {
+        let mut line = String::new();
+
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_lowercase()),
+        }
+    };
+
+    loop {
+        let (width, height, mines) = state.board(size().unwrap());
+
+        if let Err(e) = check_fit(width as u16, height as u16, mines as u16, 1, size().unwrap()) {
+            println!("error: level {} does not fit in the terminal: {}", state.level, e);
+            return;
+        }
+
+        println!(
+            "Level {}: {}x{} with {} mines. {} left, {} played so far. Press enter to start, or q to stop",
+            state.level, width, height, mines, campaign::lives_text(state.lives), clock(state.seconds),
+        );
+
+        if read_line().map_or(true, |i| i == "q") {
+            break;
+        }
+
+        let settings = Settings::new(theme.clone());
+
+        let board = Board::with_seed(width, height, mines, state.board_seed()).unwrap();
+        let game = run(Game::new(board, settings, Keymap::new(preset), None), None, None);
+        let seconds = game.clock.seconds();
+
+        match game.board.won {
+            Some(true) => {
+                let reward = state.won(seconds);
+                println!("Level {} cleared in {}", state.level - 1, clock(seconds));
+
+                if reward {
+                    println!("Pick a reward: 1) an extra life, or 2) {} seconds off your time", campaign::TIME_REWARD);
+
+                    let reward = loop {
+                        match read_line().as_deref() {
+                            Some("1") => break Some(campaign::Reward::Life),
+                            Some("2") => break Some(campaign::Reward::Time),
+                            Some(_) => println!("Type 1 or 2"),
+                            None => break None,
+                        }
+                    };
+
+                    match reward {
+                        Some(i) => state.reward(i),
+                        None => break,
+                    }
+                }
+            },
+            Some(false) => {
+                state.lost(seconds);
+
+                if state.lives == 0 {
+                    println!("Out of lives on level {}, after {}", state.level, clock(state.seconds));
+                    if let Err(e) = fs::remove_file(&path) {
+                        println!("error: could not remove {}: {}", path.display(), e);
+                    }
+
+                    return;
+                }
+
+                println!("You lost a life, {} left", campaign::lives_text(state.lives));
+            },
+            None => {
+                state.quit(seconds);
+                break;
+            },
+        }
+
+        if let Err(e) = campaign::save(&path, &state) {
+            println!("error: could not save the campaign to {}: {}", path.display(), e);
+            return;
+        }
+    }
+
+    match campaign::save(&path, &state) {
+        Ok(_) => println!("Saved at level {}, run campaign again to carry on", state.level),
+        Err(e) => println!("error: could not save the campaign to {}: {}", path.display(), e),
+    }
+}
+
+fn stats(matches: &ArgMatches) {
+    let path = match matches.value_of("file").map(PathBuf::from).or_else(stats::default_path) {
+        Some(i) => i,
+        None => {
+            println!("error: could not find a place to keep stats, set XDG_DATA_HOME or HOME");
+            return;
+        }
+    };
+
+    if let Some(export) = matches.subcommand_matches("export") {
+        match stats::load(&path) {
+            Ok(i) if export.value_of("format") == Some("json") => println!("{}", stats::export_json(&i)),
+            Ok(i) => print!("{}", stats::export_csv(&i)),
+            Err(e) => println!("error: could not read {}: {}", path.display(), e),
+        }
+
+        return;
+    }
+
+    match stats::load(&path) {
+        Ok(i) if i.is_empty() => println!("no games recorded in {} yet", path.display()),
+        Ok(i) => {
+            if let Some(days) = daily::default_path().and_then(|i| daily::load(&i).ok()).filter(|i| !i.is_empty()) {
+                let (current, best) = daily::streaks(&days, daily::today());
+                println!("daily streak: {} day{} (best {})\n", current, if current == 1 { "" } else { "s" }, best);
+            }
+
+            print!("{}", stats::summary(&i));
+            print!("{}", stats::trends(&i, 20));
+
+            #[cfg(feature = "sqlite")]
+            {
+                let days = value_t!(matches, "days", u64).unwrap_or(30);
+                let summary = history::default_path().filter(|i| i.exists()).map(|i| history::open(&i).and_then(|i| history::summary(&i, days, history::now())));
+
+                match summary {
+                    Some(Ok(i)) => print!("{}", i),
+                    Some(Err(e)) => println!("\nerror: could not read the history: {}", e),
+                    None => (),
+                }
+            }
+
+            print!("{}", stats::leaderboard(&i, 10));
+            println!("\nrating: {:.0}", rating::rating(&i));
+        },
+        Err(e) => println!("error: could not read {}: {}", path.display(), e),
+    }
+}
+
+fn editor(matches: &ArgMatches) {
+    let path = matches.value_of("file").unwrap();
+    let width = value_t!(matches, "width", usize).unwrap_or(9);
+    let height = value_t!(matches, "height", usize).unwrap_or(9);
+    let terminal = size().unwrap();
+
+    if width == 0 || height == 0 || width + 2 > terminal.0 as usize || height + 4 > terminal.1 as usize {
+        println!("error: the board does not fit in the terminal");
+        return;
+    }
+
+    let mut mines = vec![false; width * height];
+    let mut pos = (0, 0);
+    let mut keymap = Keymap::new(KeyPreset::Default);
+    let mut stdout = stdout();
+
+    enable_raw_mode().unwrap();
+    execute!(stdout, DisableLineWrap);
+
+    let saved = loop {
+        draw_editor(&mines, width, pos, path);
+
+        let key = match read().unwrap() {
+            Event::Key(i) => i,
+            _ => continue,
+        };
+
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            break false;
+        }
+
+        match keymap.feed(key) {
+            Some(Action::Move(motion)) => pos = match motion {
+                Motion::Left => (pos.0.saturating_sub(1), pos.1),
+                Motion::Right => (cmp::min(pos.0 + 1, width - 1), pos.1),
+                Motion::Up => (pos.0, pos.1.saturating_sub(1)),
+                Motion::Down => (pos.0, cmp::min(pos.1 + 1, height - 1)),
+                Motion::Top => (pos.0, 0),
+                Motion::Bottom => (pos.0, height - 1),
+                Motion::RowStart => (0, pos.1),
+                Motion::RowEnd => (width - 1, pos.1),
+                _ => pos,
+            },
+            Some(Action::Uncover) | Some(Action::Flag) => {
+                let i = pos.1 * width + pos.0;
+                mines[i] = !mines[i];
+            },
+            Some(Action::Quit) => break true,
+            _ => (),
+        }
+    };
+
+    execute!(stdout, cursor::MoveTo(0, 0), Clear(ClearType::All), EnableLineWrap);
+    disable_raw_mode().unwrap();
+
+    if !saved {
+        return;
+    }
+
+    if mines.iter().all(|i| *i) {
+        println!("error: at least one tile must be safe");
+        return;
+    }
+
+    let mut save = format!("rs-minesweeper save\nwidth {}\nfirst-uncover false\n\n", width);
+
+    for row in mines.chunks(width) {
+        save.extend(row.iter().map(|i| if *i { '*' } else { '.' }));
+        save.push('\n');
+    }
+
+    match fs::write(path, &save) {
+        Ok(_) => println!("Saved a {}x{} board with {} mines to {}", width, height, mines.iter().filter(|i| **i).count(), path),
+        Err(e) => {
+            println!("error: could not save to {}: {}", path, e);
+            return;
+        }
+    }
+
+    // Hand placed mines easily make a board that can't be cleared without guessing, so say so right away
+    if let Ok(board) = Board::from_layout(width, &mines) {
+        let start = solver::default_start(&board);
+
+        if let Ok(i) = solver::solvability(&board, start, Strategy::Csp) {
+            println!("{}", i.report(start));
+        }
+    }
+}
+
+// Picks a save to load from the current directory. Returns None if the browser was closed without loading one
+fn browse_saves() -> Option<PathBuf> {
+    let dir = PathBuf::from(".");
+    let mut slots = saves::list(&dir);
+
+    if slots.is_empty() {
+        println!("no saves in the current directory");
+        return None;
+    }
+
+    let mut selected = 0;
+    let mut status = String::new();
+    let mut keymap = Keymap::new(KeyPreset::Default);
+    let mut stdout = stdout();
+
+    enable_raw_mode().unwrap();
+    execute!(stdout, DisableLineWrap);
+
+    let read_key = || loop {
+        if let Event::Key(i) = read().unwrap() {
+            return i;
+        }
+    };
+
+    let chosen = loop {
+        selected = cmp::min(selected, slots.len().saturating_sub(1));
+        draw_saves(&slots, selected, &status);
+        status.clear();
+
+        let key = read_key();
+
+        match (key.code, slots.get(selected)) {
+            (KeyCode::Enter, Some(slot)) => break Some(slot.path.clone()),
+            (KeyCode::Char('x'), Some(slot)) | (KeyCode::Delete, Some(slot)) => {
+                draw_saves(&slots, selected, &format!("Delete {}? (y/n)", slot.name()));
+
+                if read_key().code == KeyCode::Char('y') {
+                    status = match fs::remove_file(&slot.path) {
+                        Ok(_) => format!("Deleted {}", slot.name()),
+                        Err(e) => format!("Could not delete {}: {}", slot.name(), e),
+                    };
+                    slots = saves::list(&dir);
+                }
+            },
+            (KeyCode::Char('r'), Some(slot)) => {
+                let mut name = String::new();
+
+                let renamed = loop {
+                    draw_saves(&slots, selected, &format!("Rename {} to: {}", slot.name(), name));
+
+                    match read_key().code {
+                        KeyCode::Enter => break true,
+                        KeyCode::Esc => break false,
+                        KeyCode::Backspace => { name.pop(); },
+                        KeyCode::Char(c) => name.push(c),
+                        _ => (),
+                    }
+                };
+
+                if renamed {
+                    status = match saves::rename(&slot.path, &name) {
+                        Ok(i) => format!("Renamed to {}", i.file_name().unwrap().to_string_lossy()),
+                        Err(e) => format!("Could not rename {}: {}", slot.name(), e),
+                    };
+                    slots = saves::list(&dir);
+                }
+            },
+            _ => match keymap.feed(key) {
+                Some(Action::Move(Motion::Up)) => selected = selected.saturating_sub(1),
+                Some(Action::Move(Motion::Down)) => selected += 1,
+                Some(Action::Uncover) => if let Some(slot) = slots.get(selected) {
+                    break Some(slot.path.clone());
+                },
+                Some(Action::Quit) => break None,
+                _ => (),
+            },
+        }
+    };
+
+    execute!(stdout, cursor::MoveTo(0, 0), Clear(ClearType::All), EnableLineWrap);
+    disable_raw_mode().unwrap();
+
+    chosen
+}
+
+fn draw_saves(slots: &[saves::Slot], selected: usize, status: &str) {
+    // Scrolls to keep the selected save on screen, with room for the title and the status line
+    let rows = size().map_or(24, |i| i.1 as usize).saturating_sub(4).max(1);
+    let first = (selected + 1).saturating_sub(rows);
+    let name_width = slots.iter().map(|i| i.name().chars().count()).max().unwrap_or(0);
+
+    execute!(stdout().lock(), cursor::MoveTo(0, 0), Clear(ClearType::All));
+    print!("Saves in the current directory\r\n\r\n");
+
+    if slots.is_empty() {
+        print!("No saves left\r\n");
+    }
+
+    for (i, slot) in slots.iter().enumerate().skip(first).take(rows) {
+        let line = format!("{:<2$}  {}", slot.name(), slot.summary, name_width);
+
+        if i == selected {
+            print!("{}\r\n", style(line).reverse());
+        } else {
+            print!("{}\r\n", line);
+        }
+    }
+
+    if status.is_empty() {
+        print!("\r\nEnter or Q loads, R renames, X deletes, ESC closes");
+    } else {
+        print!("\r\n{}", status);
+    }
+
+    execute!(stdout().lock(), cursor::Hide);
+}
+
+// Shows patterns one after another until the player quits, and records whether each one was solved
+fn trainer(matches: &ArgMatches) {
+    let path = match trainer::default_path() {
+        Some(i) => i,
+        None => {
+            println!("error: could not find a place to keep your results, set XDG_DATA_HOME or HOME");
+            return;
+        }
+    };
+
+    let mut tries = match trainer::load(&path) {
+        Ok(i) => i,
+        Err(e) => {
+            println!("error: could not read {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let theme = fit_theme(Theme::default(), matches.is_present("no_color"));
+    let mut keymap = Keymap::new(value_t!(matches, "keys", KeyPreset).unwrap_or(KeyPreset::Default));
+    let mut rng = StdRng::from_entropy();
+    let mut session = (0, 0);
+    let mut stdout = stdout();
+
+    enable_raw_mode().unwrap();
+    execute!(stdout, DisableLineWrap);
+
+    let read_key = || loop {
+        if let Event::Key(i) = read().unwrap() {
+            return i;
+        }
+    };
+
+    'patterns: loop {
+        let pattern = trainer::pick(&tries, &mut rng);
+        let mut board = pattern.board(rng.gen_range(0..8));
+        let mut pos = (0, 0);
+
+        while board.won.is_none() {
+            draw_trainer(&board, &theme, Some(pos), &format!("{}. Q uncovers the safe tiles, E flags the mines", pattern.name), session);
+
+            match keymap.feed(read_key()) {
+                Some(Action::Move(motion)) => pos = match motion {
+                    Motion::Left => (pos.0.saturating_sub(1), pos.1),
+                    Motion::Right => (cmp::min(pos.0 + 1, board.width() - 1), pos.1),
+                    Motion::Up => (pos.0, pos.1.saturating_sub(1)),
+                    Motion::Down => (pos.0, cmp::min(pos.1 + 1, board.height() - 1)),
+                    _ => pos,
+                },
+                Some(Action::Uncover) => board.push_state(pos.0, pos.1, PushState::Uncover),
+                Some(Action::Flag) => board.push_state(pos.0, pos.1, PushState::Flag),
+                Some(Action::Quit) => break 'patterns,
+                _ => (),
+            }
+        }
+
+        let solved = board.won == Some(true);
+        session = (session.0 + solved as usize, session.1 + 1);
+        tries.push((pattern.id.to_string(), solved));
+
+        let mut message = String::from(if solved { "Solved!" } else { "Missed, that one was a mine" });
+
+        if let Err(e) = trainer::append(&path, pattern.id, solved) {
+            message = format!("error: could not record the result in {}: {}", path.display(), e);
+        }
+
+        draw_trainer(&board, &theme, None, &format!("{} Any key for the next pattern, ESC stops", message), session);
+
+        if keymap.feed(read_key()) == Some(Action::Quit) {
+            break;
+        }
+    }
+
+    execute!(stdout, cursor::MoveTo(0, 0), Clear(ClearType::All), EnableLineWrap);
+    disable_raw_mode().unwrap();
+
+    if session.1 > 0 {
+        println!("Solved {} of {} patterns this time\n", session.0, session.1);
+        print!("{}", trainer::summary(&tries));
+    }
+}
+
+fn draw_trainer(board: &Board, theme: &Theme, pos: Option<(usize, usize)>, status: &str, session: (usize, usize)) {
+    let border = &theme.border;
+    let horizontal = border.horizontal.to_string().repeat(board.width());
+    let view = match pos {
+        Some(i) => board.view(theme).cursor(i.0, i.1),
+        None => board.view(theme),
+    };
+
+    execute!(stdout().lock(), cursor::Hide, cursor::MoveTo(0, 0), Clear(ClearType::All));
+    print!("Pattern trainer, {} of {} solved\r\n\r\n", session.0, session.1);
+    print!("{}{}{}\r\n", border.top_left, horizontal, border.top_right);
+    print!("{}\r\n", view);
+    print!("{}{}{}\r\n\r\n", border.bottom_left, horizontal, border.bottom_right);
+    print!("{}", status);
+
+    execute!(stdout().lock(), cursor::Show);
+}
+
+fn draw_editor(mines: &[bool], width: usize, pos: (usize, usize), path: &str) {
+    let border = Theme::default().border;
+    let horizontal = border.horizontal.to_string().repeat(width);
+    let count = mines.iter().filter(|i| **i).count();
+
+    execute!(stdout().lock(), cursor::MoveTo(0, 0), Clear(ClearType::All));
+    print!("{}{}{}\r\n", border.top_left, horizontal, border.top_right);
+
+    for (y, row) in mines.chunks(width).enumerate() {
+        print!("{}", border.vertical);
+
+        for (x, mine) in row.iter().enumerate() {
+            let tile = match (*mine, no_color_env()) {
+                (true, false) => style('*').red(),
+                (true, true) => style('*'),
+                (false, _) if Capabilities::detect().unicode => style('·'),
+                (false, _) => style('.'),
+            };
+
+            if (x, y) == pos {
+                print!("{}", tile.reverse());
+            } else {
+                print!("{}", tile);
+            }
+        }
+
+        print!("{}\r\n", border.vertical);
+    }
+
+    print!("{}{}{}\r\n", border.bottom_left, horizontal, border.bottom_right);
+    print!("{} mines. Q/E toggles a mine, ESC saves to {}, CTRL+C cancels", count, path);
+
+    execute!(stdout().lock(), cursor::MoveTo(pos.0 as u16 + 1, pos.1 as u16 + 1));
+}
+
+// How many tiles Shift and an arrow key move the view by
+const PAN_STEP: i64 = 4;
+
+// The minimap is drawn next to the board when the terminal is wide enough, with each character standing for a block
+// of tiles
+const MINIMAP_SIZE: (i64, i64) = (16, 8);
+const MINIMAP_BLOCK: (i64, i64) = (8, 4);
+
+// Remembers what the minimap last showed so only the blocks that changed are drawn again
+struct Minimap {
+    left: u16,
+    blocks: Vec<Option<(char, bool, bool)>>,
+}
+
+impl Minimap {
+    fn new() -> Minimap {
+        Minimap {
+            left: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn draw(&mut self, field: &infinite::Field, theme: &Theme, view: (i64, i64), area: (i64, i64)) {
+        let border = &theme.border;
+        let left = area.0 as u16 + 3;
+        let size = (MINIMAP_SIZE.0, cmp::min(MINIMAP_SIZE.1, area.1));
+        let mut out = stdout();
+
+        if self.left != left || self.blocks.len() != (size.0 * size.1) as usize {
+            let horizontal = border.horizontal.to_string().repeat(size.0 as usize);
+
+            queue!(out, cursor::MoveTo(left, 1));
+            print!("{}{}{}", border.top_left, horizontal, border.top_right);
+
+            for y in 0..size.1 {
+                queue!(out, cursor::MoveTo(left, y as u16 + 2));
+                print!("{}{}{}", border.vertical, " ".repeat(size.0 as usize), border.vertical);
+            }
+
+            queue!(out, cursor::MoveTo(left, size.1 as u16 + 2));
+            print!("{}{}{}", border.bottom_left, horizontal, border.bottom_right);
+
+            self.left = left;
+            self.blocks = vec![None; (size.0 * size.1) as usize];
+        }
+
+        let center = ((view.0 + area.0 / 2).div_euclid(MINIMAP_BLOCK.0), (view.1 + area.1 / 2).div_euclid(MINIMAP_BLOCK.1));
+        let origin = (center.0 - size.0 / 2, center.1 - size.1 / 2);
+
+        for (i, (y, x)) in (0..size.1).cartesian_product(0..size.0).enumerate() {
+            let tile = ((origin.0 + x) * MINIMAP_BLOCK.0, (origin.1 + y) * MINIMAP_BLOCK.1);
+            let (generated, opened, flagged) = field.block(tile.0, tile.1, MINIMAP_BLOCK.0, MINIMAP_BLOCK.1);
+
+            let symbol = match (generated, opened * 4 / cmp::max(generated, 1)) {
+                (0, _) => ' ',
+                (_, 0) if opened == 0 => '·',
+                (_, 0) => '░',
+                (_, 1) => '▒',
+                (_, 2) | (_, 3) => '▓',
+                _ => '█',
+            };
+
+            let seen = tile.0 < view.0 + area.0 && tile.0 + MINIMAP_BLOCK.0 > view.0
+                && tile.1 < view.1 + area.1 && tile.1 + MINIMAP_BLOCK.1 > view.1;
+            let block = Some((symbol, flagged > 0, seen));
+
+            if self.blocks[i] != block {
+                let symbol = match symbol {
+                    _ if theme.capabilities.unicode => symbol,
+                    '·' => '.',
+                    '░' => ':',
+                    '▒' => '+',
+                    '▓' => '*',
+                    '█' => '#',
+                    _ => symbol,
+                };
+                let content = if flagged > 0 && theme.colored() { style(symbol).green() } else { style(symbol) };
+
+                queue!(out, cursor::MoveTo(left + x as u16 + 1, y as u16 + 2));
+                print!("{}", if seen { content.reverse() } else { content });
+
+                self.blocks[i] = block;
+            }
+        }
+    }
+}
+
+// Plays on a field without edges until the player hits a mine or quits. The view scrolls to follow the cursor, and
+// new chunks are generated as they come into view. Shift and the arrow keys pan the view away from the cursor, and
+// any other key brings it back
+fn play_infinite(seed: u64, density: f64, theme: Theme, mut keymap: Keymap) {
+    let mut field = infinite::Field::new(seed, density);
+    let mut stdout = stdout();
+    let mut pos = (0, 0);
+    let mut view = (0, 0);
+    let mut follow = true;
+    let mut status = String::from("The tiles around (0, 0) are always safe");
+    let mut minimap = Minimap::new();
+    let mut last_size = (0, 0);
+
+    enable_raw_mode().unwrap();
+    execute!(stdout, cursor::DisableBlinking, DisableLineWrap, Clear(ClearType::All));
+
+    loop {
+        let (columns, rows) = size().unwrap();
+        let mapped = columns as i64 >= MINIMAP_SIZE.0 * 4;
+        let reserved = if mapped { MINIMAP_SIZE.0 + 3 } else { 0 };
+        let area = (cmp::max(columns as i64 - 2 - reserved, 1), cmp::max(rows as i64 - 4, 1));
+
+        if (columns, rows) != last_size {
+            execute!(stdout, Clear(ClearType::All));
+            minimap = Minimap::new();
+            last_size = (columns, rows);
+        }
+
+        if follow {
+            view.0 = cmp::min(cmp::max(view.0, pos.0 - area.0 + 1), pos.0);
+            view.1 = cmp::min(cmp::max(view.1, pos.1 - area.1 + 1), pos.1);
+        }
+
+        draw_infinite(&mut field, &theme, pos, view, area, &status, if mapped { Some(&mut minimap) } else { None });
+
+        let key = match read().unwrap() {
+            Event::Key(i) => i,
+            _ => continue,
+        };
+
+        if field.lost.is_some() {
+            break;
+        }
+
+        if key.modifiers.contains(KeyModifiers::SHIFT) {
+            let pan = match key.code {
+                KeyCode::Left => Some((-PAN_STEP, 0)),
+                KeyCode::Right => Some((PAN_STEP, 0)),
+                KeyCode::Up => Some((0, -PAN_STEP)),
+                KeyCode::Down => Some((0, PAN_STEP)),
+                _ => None,
+            };
+
+            if let Some(i) = pan {
+                view = (view.0 + i.0, view.1 + i.1);
+                follow = false;
+                continue;
+            }
+        }
+
+        follow = true;
+
+        match keymap.feed(key) {
+            Some(Action::Move(motion)) => pos = match motion {
+                Motion::Left => (pos.0 - 1, pos.1),
+                Motion::Right => (pos.0 + 1, pos.1),
+                Motion::Up => (pos.0, pos.1 - 1),
+                Motion::Down => (pos.0, pos.1 + 1),
+                Motion::Top => (pos.0, view.1),
+                Motion::Bottom => (pos.0, view.1 + area.1 - 1),
+                Motion::RowStart => (view.0, pos.1),
+                Motion::RowEnd => (view.0 + area.0 - 1, pos.1),
+                _ => pos,
+            },
+            Some(Action::Uncover) => field.uncover(pos.0, pos.1),
+            Some(Action::Flag) => field.flag(pos.0, pos.1),
+            Some(Action::Quit) => break,
+            _ => (),
+        }
+
+        if field.lost.is_some() {
+            status = format!("You hit a mine after opening {} tiles! Press any key to exit", field.opened);
+        }
+    }
+
+    execute!(stdout, cursor::MoveTo(0, 0), Clear(ClearType::All), EnableLineWrap);
+    disable_raw_mode().unwrap();
+
+    println!("Opened {} tiles across {} chunks of the infinite board with seed {}", field.opened, field.chunk_count(), field.seed);
+}
+
+fn draw_infinite(field: &mut infinite::Field, theme: &Theme, pos: (i64, i64), view: (i64, i64), area: (i64, i64), status: &str, minimap: Option<&mut Minimap>) {
+    let border = &theme.border;
+    let horizontal = border.horizontal.to_string().repeat(area.0 as usize);
+    let density = field.density(pos.0, pos.1) * 100.0;
+    let mut out = stdout();
+
+    queue!(out, cursor::MoveTo(0, 0), Clear(ClearType::CurrentLine));
+    print!("Opened {}  Flags {}  ({}, {})  View ({}, {})  {:.0}% mines here", field.opened, field.flags, pos.0, pos.1, view.0, view.1, density);
+    queue!(out, cursor::MoveTo(0, 1));
+    print!("{}{}{}", border.top_left, horizontal, border.top_right);
+
+    for y in 0..area.1 {
+        queue!(out, cursor::MoveTo(0, y as u16 + 2));
+        print!("{}", border.vertical);
+
+        for x in 0..area.0 {
+            let tile = match field.cell(view.0 + x, view.1 + y) {
+                Cell::Covered => theme.covered.styled(),
+                Cell::Flagged => theme.flag.styled(),
+                Cell::Open(0) => theme.empty.styled(),
+                Cell::Open(i) => theme.number(i),
+                Cell::Negative(i) => theme.net(-(i as i64)),
+                Cell::Mine => theme.mine.styled(),
+                Cell::WrongFlag => theme.wrong_flag.styled(),
+                Cell::Exploded => theme.exploded.styled(),
+            };
+
+            if (view.0 + x, view.1 + y) == pos {
+                print!("{}", tile.reverse());
+            } else {
+                print!("{}", tile);
+            }
+        }
+
+        print!("{}", border.vertical);
+    }
+
+    queue!(out, cursor::MoveTo(0, area.1 as u16 + 2));
+    print!("{}{}{}", border.bottom_left, horizontal, border.bottom_right);
+    queue!(out, cursor::MoveTo(0, area.1 as u16 + 3), Clear(ClearType::CurrentLine));
+    print!("{}", status);
+
+    if let Some(i) = minimap {
+        i.draw(field, theme, view, area);
+    }
+
+    if (view.0..view.0 + area.0).contains(&pos.0) && (view.1..view.1 + area.1).contains(&pos.1) {
+        execute!(out, cursor::MoveTo((pos.0 - view.0) as u16 + 1, (pos.1 - view.1) as u16 + 2));
+    } else {
+        execute!(out, cursor::MoveTo(status.chars().count() as u16, area.1 as u16 + 3));
+    }
+}
+
+fn simulate(matches: &ArgMatches) {
+    let games = value_t!(matches, "games", u64).unwrap_or(1000);
+    let strategy = value_t!(matches, "strategy", Strategy).unwrap_or(Strategy::SinglePoint);
+    let difficulty = value_t!(matches, "difficulty", Difficulty).unwrap_or(Difficulty::Intermediate);
+    let seed = value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random());
+    let (width, height, mine_num) = difficulty.size();
+
+    let (wins, guesses, elapsed) = play_games(strategy, &difficulty, games, seed);
+    let per_game = cmp::max(games, 1) as f64;
+
+    println!("strategy: {}", strategy);
+    println!("games: {} on {} ({}x{}, {} mines), starting at seed {}", games, difficulty, width, height, mine_num, seed);
+    println!("wins: {} ({:.1}%)", wins, wins as f64 * 100.0 / per_game);
+    println!("average guesses: {:.2}", guesses as f64 / per_game);
+    println!("average time: {:.3}ms", elapsed.as_secs_f64() * 1000.0 / per_game);
+}
+
+// Plays games on consecutive seeds, returning the wins, the guesses and the time they took altogether
+fn play_games(strategy: Strategy, difficulty: &Difficulty, games: u64, seed: u64) -> (u64, u64, Duration) {
+    let (width, height, mine_num) = difficulty.size();
+
+    let mut wins = 0;
+    let mut guesses = 0;
+    let start = Instant::now();
+
+    for i in 0..games {
+        let game_seed = seed.wrapping_add(i);
+        let mut board = Board::with_seed(width as usize, height as usize, mine_num as usize, game_seed).unwrap();
+        let outcome = solver::play(&mut board, strategy, &mut StdRng::seed_from_u64(game_seed));
+
+        wins += outcome.won as u64;
+        guesses += outcome.guesses as u64;
+    }
+
+    (wins, guesses, start.elapsed())
+}
+
+// Every strategy plays the same seeds, and guesses with the same random numbers, so the only difference between
+// them is how much they work out before guessing
+fn compare(matches: &ArgMatches) {
+    let games = cmp::max(value_t!(matches, "games", u64).unwrap_or(1000), 1);
+    let seed = value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random());
+
+    let strategies: Vec<Strategy> = match matches.values_of("strategies") {
+        Some(i) => i.filter_map(|i| i.parse().ok()).collect(),
+        None => Strategy::variants().iter().filter_map(|i| i.parse().ok()).collect(),
+    };
+
+    let difficulties: Vec<Difficulty> = match value_t!(matches, "difficulty", Difficulty) {
+        Ok(i) => vec![i],
+        Err(_) => Difficulty::variants().iter().filter_map(|i| i.parse().ok()).collect(),
+    };
+
+    println!("{} games per strategy and difficulty, starting at seed {}", games, seed);
+    println!("{:<14}{:<14}{:>8}{:>10}{:>12}", "difficulty", "strategy", "wins", "guesses", "time");
+
+    for difficulty in difficulties {
+        for strategy in &strategies {
+            let (wins, guesses, elapsed) = play_games(*strategy, &difficulty, games, seed);
+
+            println!(
+                "{:<14}{:<14}{:>7.1}%{:>10.2}{:>10.3}ms",
+                difficulty.to_string(),
+                strategy.to_string(),
+                wins as f64 * 100.0 / games as f64,
+                guesses as f64 / games as f64,
+                elapsed.as_secs_f64() * 1000.0 / games as f64,
+            );
+        }
+    }
+}
+
+fn bench(matches: &ArgMatches) {
+    let boards = cmp::max(value_t!(matches, "boards", u64).unwrap_or(500), 1);
+    let strategy = value_t!(matches, "strategy", Strategy).unwrap_or(Strategy::Csp);
+    let seed = value_t!(matches, "seed", u64).unwrap_or_else(|_| rand::random());
+
+    println!("{} boards per difficulty with the {} strategy, starting at seed {}", boards, strategy, seed);
+    println!("{:<14}{:>10}{:>12}{:>12}{:>12}  {}", "difficulty", "boards/s", "generate", "solve", "worst", "worst seed");
+
+    for difficulty in Difficulty::variants().iter().filter_map(|i| i.parse::<Difficulty>().ok()) {
+        let (width, height, mine_num) = difficulty.size();
+        let mut generate = Duration::from_secs(0);
+        let mut solve = Duration::from_secs(0);
+        let mut worst = (Duration::from_secs(0), seed);
+
+        for i in 0..boards {
+            let board_seed = seed.wrapping_add(i);
+
+            let start = Instant::now();
+            let mut board = Board::with_seed(width as usize, height as usize, mine_num as usize, board_seed).unwrap();
+            generate += start.elapsed();
+
+            let start = Instant::now();
+            solver::play(&mut board, strategy, &mut StdRng::seed_from_u64(board_seed));
+            let time = start.elapsed();
+
+            solve += time;
+
+            if time > worst.0 {
+                worst = (time, board_seed);
+            }
+        }
+
+        let total = (generate + solve).as_secs_f64();
+        let ms = |i: Duration| format!("{:.3}ms", i.as_secs_f64() * 1000.0);
+
+        println!(
+            "{:<14}{:>10.1}{:>12}{:>12}{:>12}  {}",
+            difficulty.to_string(),
+            boards as f64 / total,
+            ms(generate / boards as u32),
+            ms(solve / boards as u32),
+            ms(worst.0),
+            worst.1,
+        );
+    }
+
+    let start = Instant::now();
+    Board::with_seed(1000, 1000, 200_000, seed).unwrap();
+
+    println!("generating a 1000x1000 board: {:.3}ms", start.elapsed().as_secs_f64() * 1000.0);
+
+    // A board without mines opens completely on the first uncover, which is the largest flood fill there can be
+    let mut board = Board::from_layout(1000, &vec![false; 1000 * 1000]).unwrap();
+    let start = Instant::now();
+    board.push_state(0, 0, PushState::Uncover);
+
+    println!("opening a 1000x1000 board: {:.3}ms", start.elapsed().as_secs_f64() * 1000.0);
+}
+
+enum Message {
+    Input(Event),
+    Tick,
+    #[cfg(feature = "gamepad")]
+    Pad(Action),
+}
+
+// Input and clock ticks arrive on one channel so only the main loop ever draws
+struct Events {
+    messages: mpsc::Receiver<Message>,
+    stop: Arc<AtomicBool>,
+    input: Option<thread::JoinHandle<()>>,
+}
+
+// The input thread is waited for, so once a game is over nothing is left reading keys meant for whatever comes next,
+// like the prompts between campaign levels
+impl Drop for Events {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(i) = self.input.take() {
+            let _ = i.join();
+        }
+    }
+}
+
+fn launch_events() -> Events {
+    let (tx, rx) = mpsc::channel();
+    let input_tx = tx.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+
+    #[cfg(feature = "gamepad")]
+    gamepad::launch(tx.clone());
+
+    // Waiting for input a little at a time, instead of blocking until the next key, lets the thread see it should stop
+    let input = thread::spawn(move || {
+        while !stopped.load(Ordering::Relaxed) {
+            match poll(Duration::from_millis(50)) {
+                Ok(true) => match read() {
+                    Ok(i) => if input_tx.send(Message::Input(i)).is_err() {
+                        return;
+                    },
+                    Err(_) => return,
+                },
+                Ok(false) => {},
+                Err(_) => return,
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_millis(100));
+
+            if tx.send(Message::Tick).is_err() {
+                return;
+            }
+        }
+    });
+
+    Events {
+        messages: rx,
+        stop: stop,
+        input: Some(input),
+    }
+}
+
+struct Settings {
+    highlight_neighbors: bool,
+    show_deductions: bool,
+    hint_penalty: u64,
+    show_score: bool,
+    casual: bool,
+    show_history: bool,
+    hotseat: bool,
+    coop: bool,
+    cell_size: u16,
+    // Tiles two characters wide, with covered ones drawn as one glyph and a space like the rest
+    square: bool,
+    max_fps: u32,
+    theme: Theme,
+}
+
+impl Settings {
+    // The settings of a plain game, for the ones not started from the play options like replays and campaign levels
+    fn new(theme: Theme) -> Settings {
+        Settings {
+            highlight_neighbors: false,
+            show_deductions: false,
+            hint_penalty: 10,
+            show_score: false,
+            casual: false,
+            show_history: false,
+            hotseat: false,
+            coop: false,
+            cell_size: 1,
+            square: false,
+            max_fps: 30,
+            theme: theme,
+        }
+    }
+}
+
+// Boards too narrow to fit a middle section of at least MIN_MIDDLE between the counters
+// get a taller header with the counters on one row and the middle section below them
+#[derive(Clone, Copy)]
+struct Layout {
+    width: u16,
+    height: u16,
+    // How many characters wide and rows tall each tile is drawn
+    cell: (u16, u16),
+    compact: bool,
+    board_top: u16,
+    status: u16,
+    counter_width: u16,
+    timer_width: u16,
+    counter: (u16, u16),
+    timer: (u16, u16),
+    flag_mode: (u16, u16),
+    middle: (u16, u16, u16),
+    // Columns to the right of the board and rows under it, for the mines left in every row and column
+    gutters: (u16, u16),
+}
+
+impl Layout {
+    // Width and height are in tiles, and everything else in characters
+    fn new(width: u16, height: u16, mine_num: u16, cell_size: u16) -> Layout {
+        let counter_width = cmp::max(mine_num.to_string().len() as u16, 3);
+        let timer_width = 3;
+        let cell = cell_dimensions(cell_size);
+        let (width, height) = (width * cell.0, height * cell.1);
+
+        let compact = width < counter_width + timer_width + MIN_MIDDLE + 6;
+        Layout::arrange(width, height, cell, compact, counter_width, timer_width)
+    }
+
+    fn arrange(width: u16, height: u16, cell: (u16, u16), compact: bool, counter_width: u16, timer_width: u16) -> Layout {
+        let board_top = if compact { 4 } else { 3 };
+        let flag_mode = if !compact {
+            (counter_width + 5, 1)
+        } else if width >= counter_width + timer_width + 2 {
+            (counter_width + 2, 1)
+        } else {
+            (1, 2)
+        };
+
+        Layout {
+            width: width,
+            height: height,
+            cell: cell,
+            compact: compact,
+            board_top: board_top,
+            status: board_top + height + 1,
+            counter_width: counter_width,
+            timer_width: timer_width,
+            counter: if compact { (1, 1) } else { (2, 1) },
+            timer: if compact { ((width + 1).saturating_sub(timer_width), 1) } else { (width.saturating_sub(timer_width), 1) },
+            flag_mode: flag_mode,
+            gutters: (0, 0),
+            middle: if compact { (1, 2, width) } else { (counter_width + 4, 1, width.saturating_sub(counter_width + timer_width + 6)) },
+        }
+    }
+
+    // Makes room for the line hints of a board that shows them, with a column of digits under every column. Flags
+    // only ever bring the counts down, so the widest ones are there from the start
+    fn with_gutters(mut self, board: &Board) -> Layout {
+        let digits = |counts: Vec<i64>| counts.into_iter().max().unwrap_or(0).to_string().len() as u16;
+
+        let gutters = if board.line_hints {
+            let (rows, columns) = board.line_counts();
+            (digits(rows) + 1, digits(columns))
+        } else {
+            (0, 0)
+        };
+
+        self.status = self.status - self.gutters.1 + gutters.1;
+        self.gutters = gutters;
+        self
+    }
+
+    // Widens the timer once the time outgrows it, as long as the header still has room
+    fn fit_timer(&self, seconds: u64) -> Layout {
+        let timer_width = format_time(seconds).len() as u16;
+
+        if timer_width <= self.timer_width {
+            return *self;
+        }
+
+        let mut grown = Layout::arrange(self.width, self.height, self.cell, self.compact, self.counter_width, timer_width);
+        grown.status = self.status;
+        grown.gutters = self.gutters;
+        let fits = if self.compact {
+            self.counter_width + timer_width + 1 <= self.width
+        } else {
+            grown.middle.2 >= "YOU LOST".len() as u16
+        };
+
+        if fits { grown } else { *self }
+    }
+
+    fn timer_text(&self, seconds: u64) -> String {
+        let text = format_time(seconds);
+
+        if text.len() as u16 <= self.timer_width {
+            text
+        } else if self.timer_width < 5 {
+            format_time(999)
+        } else {
+            format_time(10u64.pow(self.timer_width as u32 - 3) * 60 - 1)
+        }
+    }
+
+    // Goes negative when there are more flags than mines, stopping at the lowest number that fits
+    fn counter_text(&self, working_board: &Board) -> String {
+        let lowest = 1 - 10i64.pow(self.counter_width as u32 - 1);
+        format!("{:01$}", cmp::max(working_board.mines_left(), lowest), self.counter_width as usize)
+    }
+
+    // Where the middle row of a tile starts, which is where its symbol is drawn
+    fn tile_at(&self, pos: &(u16, u16)) -> (u16, u16) {
+        self.row_at(pos, self.cell.1 / 2)
+    }
+
+    fn row_at(&self, pos: &(u16, u16), row: u16) -> (u16, u16) {
+        (pos.0 * self.cell.0 + 1, pos.1 * self.cell.1 + row + self.board_top)
+    }
+}
+
+// Follows https://no-color.org, where any value but an empty one turns colors off
+fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").map_or(false, |i| !i.is_empty())
+}
+
+// Falls back to whatever the terminal can draw
+fn fit_theme(theme: Theme, no_color: bool) -> Theme {
+    let theme = theme.fit(Capabilities::detect());
+
+    if no_color || no_color_env() {
+        theme.plain()
+    } else {
+        theme
+    }
+}
+
+// Tiles get half as many rows as characters, which looks about square in most terminal fonts
+fn cell_dimensions(cell_size: u16) -> (u16, u16) {
+    (cell_size, cmp::max(cell_size / 2, 1))
+}
+
+fn format_time(seconds: u64) -> String {
+    if seconds < 1000 {
+        format!("{:03}", seconds)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+struct Clock {
+    elapsed: Duration,
+    since: Option<Instant>,
+    stopped: bool,
+    penalty: u64,
+    bonus: u64,
+}
+
+impl Clock {
+    fn new() -> Clock {
+        Clock {
+            elapsed: Duration::from_secs(0),
+            since: None,
+            stopped: false,
+            penalty: 0,
+            bonus: 0,
+        }
+    }
+
+    fn penalize(&mut self, seconds: u64) {
+        self.penalty += seconds;
+    }
+
+    fn reward(&mut self, seconds: u64) {
+        self.bonus += seconds;
+    }
+
+    fn start(&mut self) {
+        if !self.stopped && self.since.is_none() {
+            self.since = Some(Instant::now());
+        }
+    }
+
+    fn pause(&mut self) {
+        if let Some(i) = self.since.take() {
+            self.elapsed += Instant::now().saturating_duration_since(i);
+        }
+    }
+
+    // Keeps the clock from counting for a while by starting it again in the future
+    fn freeze(&mut self, duration: Duration) {
+        if !self.stopped {
+            self.pause();
+            self.since = Some(Instant::now() + duration);
+        }
+    }
+
+    fn frozen(&self) -> bool {
+        self.since.map_or(false, |i| i > Instant::now())
+    }
+
+    fn stop(&mut self) {
+        self.pause();
+        self.stopped = true;
+    }
+
+    fn running(&self) -> bool {
+        self.since.is_some()
+    }
+
+    fn seconds(&self) -> u64 {
+        let running = self.since.map_or(Duration::from_secs(0), |i| Instant::now().saturating_duration_since(i));
+        ((self.elapsed + running).as_secs() + self.penalty).saturating_sub(self.bonus)
+    }
+}
+
+fn check_fit(width: u16, height: u16, mine_num: u16, cell_size: u16, terminal: (u16, u16)) -> Result<(), String> {
+    let layout = Layout::new(width, height, mine_num, cell_size);
+    let (cell_width, cell_height) = layout.cell;
+    let scaled = |text: String, cells: u16| if cells == 1 { text } else { format!("({}) / {}", text, cells) };
+
+    if width < MIN_WIDTH {
+        return Err(format!("width cannot be smaller then {}", MIN_WIDTH));
+    }
+
+    if layout.width < layout.counter_width + layout.timer_width + 2 {
+        let least = (layout.counter_width + layout.timer_width + 2 + cell_width - 1) / cell_width;
+        return Err(format!("width cannot be smaller then {} with {} mines", least, mine_num));
+    }
+
+    if layout.width > terminal.0 - 2 {
+        return Err(format!("width cannot be larger then {}", scaled(String::from("the terminal width - 2"), cell_width)));
+    }
+
+    if layout.status >= terminal.1 {
+        return Err(format!("height cannot be larger then {}", scaled(format!("the terminal height - {}", layout.board_top + 2), cell_height)));
+    }
+
+    Ok(())
+}
+
+mod game {
+    use super::*;
+    use crossterm::event::KeyEvent;
+    use rand::Rng;
+    use render::Renderer;
+    use replay::{Replay, Step};
+    use solver::{Deductions, Probabilities};
+
+    // Two players taking turns on one board. Each uncover that opens something scores a point per tile and passes the
+    // turn, flags are free, and whoever hits a mine loses
+    #[derive(Default, Debug, Clone, PartialEq)]
+    pub struct Hotseat {
+        pub scores: [usize; 2],
+        pub current: usize,
+        pub loser: Option<usize>,
+    }
+
+    impl Hotseat {
+        pub fn winner(&self) -> Option<usize> {
+            match self.loser {
+                Some(i) => Some(1 - i),
+                None if self.scores[0] == self.scores[1] => None,
+                None => Some((self.scores[1] > self.scores[0]) as usize),
+            }
+        }
+
+        pub fn result(&self) -> String {
+            match (self.loser, self.winner()) {
+                (Some(i), _) => format!("Player {} hit a mine, so player {} wins", i + 1, 2 - i),
+                (None, Some(i)) => format!("Player {} wins, {} tiles to {}", i + 1, self.scores[i], self.scores[1 - i]),
+                (None, None) => format!("It's a draw at {} tiles each", self.scores[0]),
+            }
+        }
+    }
+
+    // Seconds taken off the clock for every treasure found
+    pub const TREASURE_BONUS: u64 = 5;
+
+    // Presses of the same move key closer together than this come from holding it down. Terminals repeat keys every
+    // 30 to 50ms, while pressing a key over and over takes at least twice that
+    const KEY_REPEAT: Duration = Duration::from_millis(70);
+
+    // How often something happens, by the clock or by the moves made
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum Interval {
+        Seconds(u64),
+        Moves(usize),
+    }
+
+    // Numbers fade back to blank a while after they are opened, so they have to be remembered
+    pub struct Blind {
+        fade: Interval,
+        // When each number was opened, and how many moves had been made by then
+        opened: Vec<Option<(Instant, usize)>>,
+        pub faded: Vec<bool>,
+    }
+
+    impl Blind {
+        pub fn new(fade: Interval, tiles: usize) -> Blind {
+            Blind {
+                fade: fade,
+                opened: vec![None; tiles],
+                faded: vec![false; tiles],
+            }
+        }
+
+        // Starts the timers of newly opened numbers and forgets tiles that were covered again by a rewind. Returns
+        // whether any number faded
+        fn update(&mut self, board: &Board, moves: usize) -> bool {
+            let mut changed = false;
+
+            for i in 0..self.opened.len() {
+                let (x, y) = (i % board.width(), i / board.width());
+
+                let number = match board.cell(x, y) {
+                    Cell::Open(n) => Some(n > 0),
+                    Cell::Negative(_) => Some(true),
+                    _ => None,
+                };
+
+                match (number, self.opened[i]) {
+                    (Some(true), None) => self.opened[i] = Some((Instant::now(), moves)),
+                    (Some(true), Some((time, made))) if !self.faded[i] => {
+                        self.faded[i] = match self.fade {
+                            Interval::Seconds(s) => time.elapsed() >= Duration::from_secs(s),
+                            Interval::Moves(m) => moves >= made + m,
+                        };
+                        changed |= self.faded[i];
+                    },
+                    (Some(_), _) => {},
+                    (None, _) => {
+                        self.opened[i] = None;
+                        self.faded[i] = false;
+                    },
+                }
+            }
+
+            changed
+        }
+    }
+
+    // Lives at the start of a rush game. Each mine that scrolls off the top without a flag takes one
+    pub const RUSH_LIVES: usize = 3;
+
+    // The board scrolling up a row every so often once the player has started, with new rows coming in at the bottom
+    pub struct Rush {
+        pub lives: usize,
+        pub rows: usize,
+        pace: Duration,
+        scrolled: Instant,
+    }
+
+    impl Rush {
+        pub fn new(pace: Duration) -> Rush {
+            Rush {
+                lives: RUSH_LIVES,
+                rows: 0,
+                pace: pace,
+                scrolled: Instant::now(),
+            }
+        }
+
+        pub fn rows_text(&self) -> String {
+            if self.rows == 1 { String::from("1 row") } else { format!("{} rows", self.rows) }
+        }
+    }
+
+    // Writes the game to the autosave every so often while it is played, so a crash or a dropped connection loses
+    // no more than that
+    pub struct Autosave {
+        path: PathBuf,
+        every: Interval,
+        saved: Instant,
+        // How many moves had been made at the last save
+        moves: usize,
+    }
+
+    impl Autosave {
+        pub fn new(path: PathBuf, every: Interval) -> Autosave {
+            Autosave {
+                path: path,
+                every: every,
+                saved: Instant::now(),
+                moves: 0,
+            }
+        }
+    }
+
+    // Opening this many tiles with one uncover earns a power-up in arcade mode
+    pub const ARCADE_OPENING: usize = 20;
+    const FREEZE: Duration = Duration::from_secs(10);
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum PowerUp {
+        Reveal,
+        Freeze,
+        Defuse,
+    }
+
+    impl PowerUp {
+        // In the order of the number keys that use them
+        pub const ALL: [PowerUp; 3] = [PowerUp::Reveal, PowerUp::Freeze, PowerUp::Defuse];
+
+        pub fn name(self) -> &'static str {
+            match self {
+                PowerUp::Reveal => "Reveal",
+                PowerUp::Freeze => "Freeze",
+                PowerUp::Defuse => "Defuse",
+            }
+        }
+    }
+
+    pub struct Arcade {
+        pub inventory: [usize; 3],
+        rng: StdRng,
+        // Whether the inventory was last drawn with the timer frozen
+        frozen: bool,
+    }
+
+    impl Arcade {
+        pub fn new(seed: u64) -> Arcade {
+            Arcade {
+                inventory: [0; 3],
+                rng: StdRng::seed_from_u64(seed),
+                frozen: false,
+            }
+        }
+
+        fn earn(&mut self) -> usize {
+            let n = self.rng.gen_range(0..PowerUp::ALL.len());
+            self.inventory[n] += 1;
+            n
+        }
+    }
+
+    // The solver racing the player on a copy of the board, making a move every so often once the player has started
+    pub struct Bot {
+        pub board: Board,
+        strategy: Strategy,
+        rng: StdRng,
+        pace: Duration,
+        moved: Instant,
+        // When the bot's game ended, on the player's clock
+        pub finished: Option<u64>,
+    }
+
+    impl Bot {
+        pub fn new(board: Board, strategy: Strategy, pace: Duration) -> Bot {
+            Bot {
+                board: board,
+                strategy: strategy,
+                rng: StdRng::seed_from_u64(rand::random()),
+                pace: pace,
+                moved: Instant::now(),
+                finished: None,
+            }
+        }
+
+        // Returns whether the bot moved
+        fn tick(&mut self, seconds: u64) -> bool {
+            if self.board.won.is_some() || self.moved.elapsed() < self.pace {
+                return false;
+            }
+
+            solver::step(&mut self.board, self.strategy, &mut self.rng);
+            self.moved = Instant::now();
+
+            if self.board.won.is_some() {
+                self.finished = Some(seconds);
+            }
+
+            true
+        }
+
+        pub fn status(&self) -> String {
+            match (self.board.won, self.finished) {
+                (Some(true), Some(i)) => format!("Bot cleared the board in {}s", i),
+                (Some(false), _) => format!("Bot hit a mine at {}%", self.board.progress()),
+                _ => format!("Bot {:>3}%", self.board.progress()),
+            }
+        }
+
+        pub fn race(&self, won: bool, seconds: u64) -> String {
+            match (won, self.board.won, self.finished) {
+                (true, Some(true), Some(i)) if i < seconds => format!("The bot won the race by {} seconds", seconds - i),
+                (true, _, _) => String::from("You won the race against the bot"),
+                (false, Some(false), _) => String::from("You and the bot both hit a mine"),
+                (false, _, _) => String::from("The bot won the race"),
+            }
+        }
+    }
+
+    pub struct Game {
+        pub board: Board,
+        pub settings: Settings,
+        pub layout: Layout,
+        pub pos: (u16, u16),
+        pub flag_mode: bool,
+        // The board zoomed out to a braille character for every 2 by 4 tiles
+        pub overview: bool,
+        pub idle: bool,
+        pub clock: Clock,
+        pub palette: Option<String>,
+        pub steps: Vec<Step>,
+        // How many tiles each step opened, for the move history
+        pub opened: Vec<usize>,
+        pub deductions: Deductions,
+        // Shown with the deductions once none of them is safe
+        pub chances: Option<Probabilities>,
+        pub deaths: usize,
+        pub hotseat: Option<Hotseat>,
+        // The second player's cursor in co-op, moved with the arrow keys
+        pub partner: Option<(u16, u16)>,
+        partner_acting: bool,
+        pub bot: Option<Bot>,
+        pub arcade: Option<Arcade>,
+        pub blind: Option<Blind>,
+        pub rush: Option<Rush>,
+        pub autosave: Option<Autosave>,
+        pub spectators: Option<spectate::Server>,
+        // Which of board::TAGS the tag key paints with
+        pub brush: usize,
+        // How the games before the latest restart ended and their times, for the summary at exit
+        pub session: Vec<(Option<bool>, u64)>,
+        pub daily: Option<daily::Daily>,
+        pub acceleration: Vec<u16>,
+        // The last arrow move from the keyboard, when it came and how many repeats of it came right before it
+        held: Option<(Motion, Instant, usize)>,
+        // A failure shown over the board, dismissed by the next key
+        error: Option<String>,
+        rewind: Option<Board>,
+        keymap: Keymap,
+        idle_after: Option<Duration>,
+        last_input: Instant,
+        shown_time: u64,
+        started: Instant,
+    }
+
+    impl Game {
+        pub fn new(board: Board, settings: Settings, keymap: Keymap, idle_after: Option<Duration>) -> Game {
+            let layout = Layout::new(board.width() as u16, board.layer_height() as u16, board.mines() as u16, settings.cell_size).with_gutters(&board);
+
+            let mut game = Game {
+                board: board,
+                settings: settings,
+                layout: layout,
+                pos: (0, 0),
+                flag_mode: false,
+                overview: false,
+                brush: 0,
+                session: vec![],
+                daily: None,
+                acceleration: vec![1],
+                held: None,
+                idle: false,
+                clock: Clock::new(),
+                palette: None,
+                steps: vec![],
+                opened: vec![],
+                deductions: Deductions::default(),
+                chances: None,
+                deaths: 0,
+                hotseat: None,
+                partner: None,
+                partner_acting: false,
+                bot: None,
+                arcade: None,
+                blind: None,
+                rush: None,
+                autosave: None,
+                spectators: None,
+                error: None,
+                rewind: None,
+                keymap: keymap,
+                idle_after: idle_after,
+                last_input: Instant::now(),
+                shown_time: 0,
+                started: Instant::now(),
+            };
+
+            // A loaded save carries on the clock from where it was saved
+            game.clock.elapsed = Duration::from_secs(game.board.elapsed);
+
+            if game.settings.show_deductions {
+                game.assist(1);
+            }
+
+            if game.settings.hotseat {
+                game.hotseat = Some(Hotseat::default());
+            }
+
+            if game.settings.coop {
+                game.partner = Some((game.board.width() as u16 - 1, game.board.height() as u16 - 1));
+            }
+
+            game.analyse();
+            game
+        }
+
+        // Every use of a hint or another assisted feature marks the game and adds to its time
+        fn assist(&mut self, uses: usize) {
+            if uses > 0 {
+                self.board.assisted = true;
+                self.clock.penalize(uses as u64 * self.settings.hint_penalty);
+            }
+        }
+
+        fn analyse(&mut self) {
+            self.deductions = if self.settings.show_deductions && self.board.won.is_none() {
+                solver::csp(&self.board.visible_state())
+            } else {
+                Deductions::default()
+            };
+
+            self.chances = if self.settings.show_deductions && self.deductions.safe.is_empty() {
+                solver::probabilities(&self.board.visible_state())
+            } else {
+                None
+            };
+        }
+
+        pub fn replay(&self) -> Replay {
+            Replay {
+                width: self.board.width(),
+                height: self.board.height(),
+                mines: self.board.mine_total,
+                seed: self.board.seed,
+                no_flag: self.board.no_flag,
+                auto_flag: self.board.auto_flag,
+                hardcore: self.board.hardcore,
+                strict_flags: self.board.strict_flags,
+                treasure: self.board.treasure().1,
+                lie_chance: self.board.lie_chance,
+                anti_mines: self.board.anti_total,
+                stacked: self.board.stacked,
+                line_hints: self.board.line_hints,
+                generation: self.board.generation,
+                steps: self.steps.clone(),
+            }
+        }
+
+        // The layer the cursor is on, which is the one being shown
+        pub fn layer(&self) -> usize {
+            self.pos.1 as usize / self.board.layer_height()
+        }
+
+        // Where a tile is drawn, if it is on the layer being shown
+        pub fn on_screen(&self, tile: (u16, u16)) -> Option<(u16, u16)> {
+            let rows = self.board.layer_height() as u16;
+
+            if (tile.1 / rows) as usize == self.layer() {
+                Some((tile.0, tile.1 % rows))
+            } else {
+                None
+            }
+        }
+
+        // Returns false once the game is over
+        pub fn tick<R: Renderer>(&mut self, renderer: &mut R) -> bool {
+            renderer.expire_message(self);
+
+            if let Some(i) = self.idle_after {
+                if self.clock.running() && self.last_input.elapsed() >= i {
+                    self.idle = true;
+                    self.clock.pause();
+                    renderer.draw_header(self);
+                }
+            }
+
+            let moves = self.steps.len();
+
+            if let (Some(blind), true) = (self.blind.as_mut(), self.board.won.is_none()) {
+                if blind.update(&self.board, moves) {
+                    renderer.draw_board(self);
+                }
+            }
+
+            if !self.rush_tick(renderer) {
+                return false;
+            }
+
+            self.autosave_tick(renderer);
+
+            let frozen = self.clock.frozen();
+
+            if let Some(arcade) = self.arcade.as_mut().filter(|i| i.frozen != frozen) {
+                arcade.frozen = frozen;
+                renderer.draw_inventory(self);
+            }
+
+            let time = self.clock.seconds();
+
+            if let (Some(bot), true) = (self.bot.as_mut(), self.clock.running()) {
+                if bot.tick(time) {
+                    renderer.draw_bot(self);
+                }
+            }
+
+            if time != self.shown_time {
+                self.shown_time = time;
+                let grown = self.layout.fit_timer(time);
+
+                if grown.timer_width != self.layout.timer_width {
+                    self.layout = grown;
+                    renderer.draw_frame(self);
+                } else {
+                    renderer.draw_header(self);
+                }
+            }
+
+            true
+        }
+
+        // Only writes when there were moves since the last save, so a game left alone isn't saved over and over
+        fn autosave_tick<R: Renderer>(&mut self, renderer: &mut R) {
+            let moves = self.steps.len();
+
+            let autosave = match self.autosave.as_mut() {
+                Some(i) if self.board.won.is_none() && moves > i.moves => i,
+                _ => return,
+            };
+
+            let due = match autosave.every {
+                Interval::Seconds(s) => autosave.saved.elapsed() >= Duration::from_secs(s),
+                Interval::Moves(m) => moves >= autosave.moves + m,
+            };
+
+            if !due {
+                return;
+            }
+
+            autosave.saved = Instant::now();
+            autosave.moves = moves;
+
+            let mut board = self.board.clone();
+            board.elapsed = self.clock.seconds();
+
+            if let Err(e) = saves::write_autosave(&autosave.path, &board) {
+                let message = format!("Could not autosave to {}: {}", autosave.path.display(), e);
+                renderer.flash_message(self, &message);
+            }
+        }
+
+        // Scrolls the board once its time is up, taking a life for every mine that went off the top unflagged. The
+        // time only counts while the clock runs, so the board waits for the first move and for a pause
+        fn rush_tick<R: Renderer>(&mut self, renderer: &mut R) -> bool {
+            let rush = match self.rush.as_mut() {
+                Some(i) if self.board.won.is_none() => i,
+                _ => return true,
+            };
+
+            if !self.clock.running() {
+                rush.scrolled = Instant::now();
+                return true;
+            }
+
+            if rush.scrolled.elapsed() < rush.pace {
+                return true;
+            }
+
+            let lost = self.board.scroll();
+            rush.scrolled = Instant::now();
+            rush.rows += 1;
+            rush.lives = rush.lives.saturating_sub(lost);
+
+            let lives = rush.lives;
+            self.pos.1 = self.pos.1.saturating_sub(1);
+
+            if lives == 0 {
+                self.board.lose();
+                self.clock.stop();
+            }
+
+            self.analyse();
+
+            renderer.draw_board(self);
+            renderer.draw_header(self);
+            renderer.draw_inventory(self);
+
+            if lives == 0 {
+                renderer.finish(self);
+                return false;
+            }
+
+            if lost > 0 {
+                let mines = if lost == 1 { String::from("A mine") } else { format!("{} mines", lost) };
+                renderer.flash_message(self, &format!("{} went off the top unflagged, {} left", mines, campaign::lives_text(lives)));
+            }
+
+            true
+        }
+
+        // Returns false once the game is over or the player quits
+        fn use_power_up<R: Renderer>(&mut self, n: usize, renderer: &mut R) -> bool {
+            let arcade = match self.arcade.as_mut() {
+                Some(i) => i,
+                None => return true,
+            };
+
+            let power_up = PowerUp::ALL[n];
+            let (x, y) = (self.pos.0 as usize, self.pos.1 as usize);
+
+            if arcade.inventory[n] == 0 {
+                renderer.flash_message(self, &format!("No {} power-ups yet, they are earned by opening {} tiles at once", power_up.name(), ARCADE_OPENING));
+                return true;
+            }
+
+            if power_up == PowerUp::Defuse && self.board.cell(x, y) != Cell::Flagged {
+                renderer.flash_message(self, "Put the cursor on a flag to defuse the mine under it");
+                return true;
+            }
+
+            arcade.inventory[n] -= 1;
+
+            let message = match power_up {
+                PowerUp::Reveal => match self.board.random_safe() {
+                    Some(tile) => {
+                        self.board.push_state(tile.0, tile.1, PushState::Uncover);
+                        self.pos = (tile.0 as u16, tile.1 as u16);
+                        format!("Revealed ({}, {})", tile.0 + 1, tile.1 + 1)
+                    },
+                    None => String::new(),
+                },
+                PowerUp::Freeze => {
+                    self.clock.freeze(FREEZE);
+                    format!("The timer is frozen for {} seconds", FREEZE.as_secs())
+                },
+                PowerUp::Defuse if self.board.defuse(x, y) => format!("Defused the mine at ({}, {})", x + 1, y + 1),
+                PowerUp::Defuse => String::from("There was no mine under that flag"),
+            };
+
+            if self.board.won.is_some() {
+                self.clock.stop();
+            }
+
+            self.analyse();
+
+            renderer.draw_board(self);
+            renderer.draw_header(self);
+            renderer.draw_inventory(self);
+
+            if self.board.won.is_some() {
+                renderer.finish(self);
+                return false;
+            }
+
+            renderer.flash_message(self, &message);
+            true
+        }
+
+        // For things that went wrong outside the game, like a file that couldn't be written. The game carries on
+        // once the error is dismissed
+        fn fail<R: Renderer>(&mut self, renderer: &mut R, message: String) {
+            renderer.show_error(self, &message);
+            self.error = Some(message);
+        }
+
+        pub fn input<R: Renderer>(&mut self, event: Event, renderer: &mut R) -> bool {
+            self.last_input = Instant::now();
+
+            let key = match event {
+                Event::Key(i) => i,
+                _ => return true,
+            };
+
+            if self.wake(renderer) {
+                return true;
+            }
+
+            if self.rewind.is_some() {
+                let action = match key.code {
+                    KeyCode::Char('r') | KeyCode::Char('R') => Action::Rewind,
+                    _ => Action::Quit,
+                };
+
+                return self.handle(action, renderer);
+            }
+
+            let palette = self.palette.is_some();
+            let action = self.palette_key(key, renderer);
+
+            if palette {
+                self.keymap.record(key);
+            }
+
+            if let (None, Some(i)) = (&action, self.partner_key(key)) {
+                return self.partner_handle(i, renderer);
+            }
+
+            match action {
+                Some(Some(i)) => self.handle(i, renderer),
+                Some(None) => true,
+                None => match self.keymap.feed(key) {
+                    Some(Action::Move(i)) if matches!(i, Motion::Left | Motion::Right | Motion::Up | Motion::Down) => {
+                        self.held_move(i, renderer)
+                    },
+                    Some(i) => self.handle(i, renderer),
+                    None => true,
+                },
+            }
+        }
+
+        // Holding a move key down sends repeats faster than anyone presses keys, and the longer they keep coming the
+        // further each one goes
+        fn held_move<R: Renderer>(&mut self, motion: Motion, renderer: &mut R) -> bool {
+            let now = Instant::now();
+            let repeats = match self.held {
+                Some((i, at, repeats)) if i == motion && now - at < KEY_REPEAT => repeats + 1,
+                _ => 0,
+            };
+
+            self.held = Some((motion, now, repeats));
+
+            let steps = self.acceleration.get(repeats).or_else(|| self.acceleration.last()).cloned().unwrap_or(1);
+            (0..steps).all(|_| self.handle(Action::Move(motion), renderer))
+        }
+
+        // Controller buttons skip the keymap and the palette. After hitting a mine in casual mode, uncovering again
+        // takes the rewind
+        #[cfg(feature = "gamepad")]
+        pub fn pad<R: Renderer>(&mut self, action: Action, renderer: &mut R) -> bool {
+            self.last_input = Instant::now();
+
+            if self.wake(renderer) {
+                return true;
+            }
+
+            if self.rewind.is_some() && action == Action::Uncover {
+                self.handle(Action::Rewind, renderer)
+            } else {
+                self.handle(action, renderer)
+            }
+        }
+
+        // The first input after being idle or after an error box only starts the clock or clears the box
+        fn wake<R: Renderer>(&mut self, renderer: &mut R) -> bool {
+            if self.idle {
+                self.idle = false;
+                self.clock.start();
+                renderer.draw_header(self);
+                return true;
+            }
+
+            if self.error.take().is_some() {
+                renderer.draw_frame(self);
+                return true;
+            }
+
+            false
+        }
+
+        // In co-op the arrow keys, comma and period belong to the second player
+        fn partner_key(&self, key: KeyEvent) -> Option<Action> {
+            self.partner?;
+
+            match key.code {
+                KeyCode::Left => Some(Action::Move(Motion::Left)),
+                KeyCode::Right => Some(Action::Move(Motion::Right)),
+                KeyCode::Up => Some(Action::Move(Motion::Up)),
+                KeyCode::Down => Some(Action::Move(Motion::Down)),
+                KeyCode::Char(',') => Some(Action::Uncover),
+                KeyCode::Char('.') => Some(Action::Flag),
+                _ => None,
+            }
+        }
+
+        // Runs an action at the second player's cursor by swapping the two cursors around it
+        fn partner_handle<R: Renderer>(&mut self, action: Action, renderer: &mut R) -> bool {
+            let partner = self.partner.unwrap();
+
+            self.partner = Some(self.pos);
+            self.pos = partner;
+            self.partner_acting = true;
+
+            let running = self.handle(action, renderer);
+            let moved = self.pos;
+
+            self.pos = self.partner.unwrap();
+            self.partner = Some(moved);
+            self.partner_acting = false;
+
+            if running {
+                renderer.move_cursor(self);
+            }
+
+            running
+        }
+
+        // The first and second player's cursors, even while the second player's move is being handled
+        pub fn cursors(&self) -> ((u16, u16), Option<(u16, u16)>) {
+            match (self.partner, self.partner_acting) {
+                (Some(i), true) => (i, Some(self.pos)),
+                _ => (self.pos, self.partner),
+            }
+        }
+
+        // Returns None when the palette is closed and the key should go to the keymap
+        fn palette_key<R: Renderer>(&mut self, key: KeyEvent, renderer: &mut R) -> Option<Option<Action>> {
+            let mut line = self.palette.take()?;
+
+            let action = match key.code {
+                KeyCode::Enter => match command::parse(&line) {
+                    Ok(i) => Some(i),
+                    Err(e) => {
+                        renderer.flash_message(self, &e);
+                        None
+                    }
+                },
+                KeyCode::Esc => {
+                    renderer.flash_message(self, "");
+                    None
+                },
+                KeyCode::Backspace => {
+                    line.pop();
+                    renderer.draw_palette(self, &line);
+                    self.palette = Some(line);
+                    None
+                },
+                KeyCode::Char(c) => {
+                    line.push(c);
+                    renderer.draw_palette(self, &line);
+                    self.palette = Some(line);
+                    None
+                },
+                _ => {
+                    self.palette = Some(line);
+                    None
+                },
+            };
+
+            Some(action)
+        }
+
+        pub fn handle<R: Renderer>(&mut self, action: Action, renderer: &mut R) -> bool {
+            let width = self.board.width() as u16;
+            let height = self.board.height() as u16;
+
+            // After hitting a mine in casual mode, anything but a rewind gives up
+            if self.rewind.is_some() && action != Action::Rewind {
+                renderer.finish(self);
+                return false;
+            }
+
+            match action {
+                Action::Quit => {
+                    renderer.finish(self);
+                    return false;
+                },
+                Action::OpenPalette => {
+                    self.palette = Some(String::new());
+                    renderer.draw_palette(self, "");
+                },
+                Action::Restart(seed) => {
+                    let seed = seed.unwrap_or_else(rand::random);
+
+                    // Rules like --min-opening can't be met on every seed, and the current game carries on if so
+                    let board = match self.board.regenerate(seed) {
+                        Ok(i) => i,
+                        Err(e) => {
+                            self.fail(renderer, e);
+                            return true;
+                        },
+                    };
+
+                    if !self.steps.is_empty() {
+                        self.session.push((self.board.won, self.clock.seconds()));
+                    }
+
+                    self.board = board;
+                    self.flag_mode = false;
+
+                    // Any other board leaves the daily puzzle
+                    if self.daily.as_ref().map_or(false, |i| daily::seed(i.day) != seed) {
+                        self.daily = None;
+                    }
+
+                    self.layout = Layout::new(width, self.board.layer_height() as u16, self.board.mines() as u16, self.settings.cell_size).with_gutters(&self.board);
+                    self.pos = (0, 0);
+                    self.idle = false;
+                    self.clock = Clock::new();
+                    self.shown_time = 0;
+                    self.steps.clear();
+
+                    // The new game is saved over the old one from its first moves
+                    if let Some(autosave) = self.autosave.as_mut() {
+                        autosave.moves = 0;
+                        autosave.saved = Instant::now();
+                    }
+                    self.opened.clear();
+                    self.started = Instant::now();
+                    self.deaths = 0;
+
+                    if let Some(bot) = self.bot.take() {
+                        self.bot = Some(Bot::new(self.board.clone(), bot.strategy, bot.pace));
+                    }
+
+                    if self.arcade.is_some() {
+                        self.arcade = Some(Arcade::new(seed));
+                    }
+
+                    if let Some(rush) = self.rush.take() {
+                        self.rush = Some(Rush::new(rush.pace));
+                    }
+
+                    if self.settings.show_deductions {
+                        self.assist(1);
+                    }
+
+                    self.analyse();
+
+                    renderer.draw_frame(self);
+                    renderer.flash_message(self, &format!("Started a new game with seed {}", seed));
+                },
+                Action::ShowSeed => {
+                    renderer.flash_message(self, &format!("Seed: {}", self.board.seed));
+                },
+                Action::UsePowerUp(n) => return self.use_power_up(n, renderer),
+                Action::Save(_) if self.rush.is_some() => {
+                    renderer.flash_message(self, "Rush games can't be saved");
+                },
+                Action::Save(name) => {
+                    let path = match Path::new(&name).extension() {
+                        Some(_) => PathBuf::from(name),
+                        None => PathBuf::from(format!("{}.save", name)),
+                    };
+
+                    self.board.elapsed = self.clock.seconds();
+
+                    match fs::write(&path, seal::sign(&self.board.to_save())) {
+                        Ok(_) => renderer.flash_message(self, &format!("Saved to {}", path.display())),
+                        Err(e) => self.fail(renderer, format!("Could not save to {}: {}", path.display(), e)),
+                    }
+                },
+                Action::Theme(name) => {
+                    self.settings.theme = Theme::builtin(name).fit(self.settings.theme.capabilities);
+                    renderer.draw_frame(self);
+                },
+                Action::Move(motion) => {
+                    let pos = self.pos;
+
+                    // Moving up and down stays on the layer being shown
+                    let rows = self.board.layer_height() as u16;
+                    let top = pos.1 / rows * rows;
+                    let bottom = top + rows - 1;
+
+                    self.pos = match motion {
+                        Motion::Left => (pos.0.saturating_sub(1), pos.1),
+                        Motion::Right => (cmp::min(pos.0 + 1, width - 1), pos.1),
+                        Motion::Up => (pos.0, cmp::max(pos.1.saturating_sub(1), top)),
+                        Motion::Down => (pos.0, cmp::min(pos.1 + 1, bottom)),
+                        Motion::Top => (pos.0, top),
+                        Motion::Bottom => (pos.0, bottom),
+                        Motion::RowStart => (0, pos.1),
+                        Motion::RowEnd => (width - 1, pos.1),
+                        Motion::To(x, y) => (cmp::min(x, width - 1), top + cmp::min(y, rows - 1)),
+                        Motion::LayerUp if pos.1 >= rows => (pos.0, pos.1 - rows),
+                        Motion::LayerDown if pos.1 + rows < height => (pos.0, pos.1 + rows),
+                        Motion::LayerUp | Motion::LayerDown => pos,
+                        Motion::NextCovered => match self.board.nearest_covered(pos.0 as usize, pos.1 as usize) {
+                            Some((x, y)) => (x as u16, y as u16),
+                            None => pos,
+                        },
+                    };
+
+                    if pos.1 / rows != self.pos.1 / rows {
+                        renderer.draw_board(self);
+                        renderer.draw_header(self);
+                    } else if self.settings.highlight_neighbors {
+                        renderer.draw_board(self);
+                    } else {
+                        renderer.draw_tiles(self, &[pos, self.pos]);
+                    }
+                },
+                Action::ToggleFlagMode => {
+                    self.flag_mode = !self.flag_mode;
+                    renderer.draw_header(self);
+                },
+                Action::Recording(register) => {
+                    let message = register.map_or(String::new(), |i| format!("recording @{}", i));
+                    renderer.flash_message(self, &message);
+                },
+                Action::PlayMacro(register) => {
+                    // Macros can't play other macros, so they can't play themselves forever
+                    if self.keymap.replaying {
+                        return true;
+                    }
+
+                    let keys = match self.keymap.macro_keys(register) {
+                        Some(i) => i,
+                        None => {
+                            renderer.flash_message(self, &format!("Nothing is recorded in @{}", register));
+                            return true;
+                        }
+                    };
+
+                    self.keymap.replaying = true;
+                    let running = keys.into_iter().all(|i| self.input(Event::Key(i), renderer));
+                    self.keymap.replaying = false;
+
+                    return running;
+                },
+                Action::ToggleHistory => {
+                    self.settings.show_history = !self.settings.show_history;
+                    renderer.draw_frame(self);
+                },
+                Action::ToggleOverview => {
+                    self.overview = !self.overview;
+                    renderer.draw_board(self);
+                },
+                Action::Pencil => {
+                    if self.board.cycle_pencil(self.pos.0 as usize, self.pos.1 as usize) {
+                        renderer.draw_tiles(self, &[self.pos]);
+                    }
+                },
+                Action::Tag => {
+                    if self.board.toggle_tag(self.pos.0 as usize, self.pos.1 as usize, self.brush) {
+                        renderer.draw_tiles(self, &[self.pos]);
+                    }
+                },
+                Action::NextTag => {
+                    self.brush = (self.brush + 1) % board::TAGS.len();
+                    renderer.flash_message(self, &format!("Tagging in {}", board::TAGS[self.brush].1));
+                },
+                Action::ClearTags => {
+                    self.board.clear_tags();
+                    renderer.draw_board(self);
+                },
+                Action::Hint => {
+                    let found = solver::csp(&self.board.visible_state());
+                    let pos = (self.pos.0 as usize, self.pos.1 as usize);
+                    let distance = |i: &&(usize, usize)| (i.0 as i64 - pos.0 as i64).abs() + (i.1 as i64 - pos.1 as i64).abs();
+
+                    let hint = match found.safe.iter().min_by_key(distance) {
+                        Some(i) => Some((*i, "safe")),
+                        None => found.mines.iter().min_by_key(distance).map(|i| (*i, "a mine")),
+                    };
+
+                    // With nothing left to deduce, the tile least likely to be a mine is the best guess
+                    let (cell, message) = match (hint, solver::probabilities(&self.board.visible_state()).and_then(|i| i.best())) {
+                        (Some((cell, what)), _) => (cell, format!("({}, {}) is {}: {}", cell.0 + 1, cell.1 + 1, what, found.reason(cell).unwrap())),
+                        (None, Some((cell, p))) if p == 0.0 => {
+                            (cell, format!("({}, {}) is safe: none of the ways the mines left can lie puts one there", cell.0 + 1, cell.1 + 1))
+                        },
+                        (None, Some((cell, p))) => {
+                            (cell, format!("({}, {}) is the best guess, with a {:.0}% chance of a mine", cell.0 + 1, cell.1 + 1, p * 100.0))
+                        },
+                        (None, None) => {
+                            renderer.flash_message(self, "Nothing can be worked out from here, you will have to guess");
+                            return true;
+                        }
+                    };
+
+                    self.assist(1);
+                    self.pos = (cell.0 as u16, cell.1 as u16);
+                    renderer.draw_tiles(self, &[(pos.0 as u16, pos.1 as u16), self.pos]);
+                    renderer.flash_message(self, &message);
+                },
+                Action::Rewind => {
+                    let board = match self.rewind.take() {
+                        Some(i) => i,
+                        None => return true,
+                    };
+
+                    self.board = board;
+                    self.deaths += 1;
+                    self.steps.pop();
+                    self.opened.pop();
+                    self.assist(1);
+                    self.clock.start();
+                    self.analyse();
+
+                    renderer.draw_frame(self);
+                    renderer.flash_message(self, &format!("Rewound, {} so far", if self.deaths == 1 { String::from("1 death") } else { format!("{} deaths", self.deaths) }));
+                },
+                Action::ToggleDeductions => {
+                    self.settings.show_deductions = !self.settings.show_deductions;
+
+                    if self.settings.show_deductions {
+                        self.assist(1);
+                    }
+
+                    self.analyse();
+
+                    renderer.draw_board(self);
+                    renderer.flash_message(self, if self.settings.show_deductions { "Showing deductions" } else { "Hiding deductions" });
+                },
+                Action::Uncover | Action::Flag => {
+                    let update = match (action, self.flag_mode) {
+                        (Action::Uncover, false) | (Action::Flag, true) => PushState::Uncover,
+                        _ => PushState::Flag,
+                    };
+
+                    if self.board.no_flag && matches!(update, PushState::Flag) {
+                        renderer.flash_message(self, "Flags are disabled in no-flag mode");
+                        return true;
+                    }
+
+                    let cell = self.board.cell(self.pos.0 as usize, self.pos.1 as usize);
+
+                    if self.board.hardcore && matches!(update, PushState::Flag) && cell == Cell::Flagged {
+                        renderer.flash_message(self, "Flags are permanent in hardcore mode");
+                        return true;
+                    }
+
+                    if matches!(update, PushState::Flag) && cell == Cell::Covered && !self.board.can_flag() {
+                        renderer.flash_message(self, "Flag limit reached");
+                        return true;
+                    }
+
+                    renderer.flash_message(self, "");
+
+                    let auto_flagged = self.board.auto_flagged;
+                    let before = if self.settings.casual { Some(self.board.clone()) } else { None };
+                    let opened = self.board.opened();
+                    let treasure = self.board.treasure().0;
+
+                    self.board.push_state(self.pos.0 as usize, self.pos.1 as usize, update);
+                    self.assist(self.board.auto_flagged - auto_flagged);
+
+                    let found = self.board.treasure().0 - treasure;
+                    self.clock.reward(found as u64 * TREASURE_BONUS);
+
+                    if let Some(blind) = self.blind.as_mut() {
+                        blind.update(&self.board, self.steps.len() + 1);
+                    }
+
+                    self.opened.push(match self.board.won {
+                        Some(false) => 0,
+                        _ => self.board.opened() - opened,
+                    });
+
+                    self.steps.push(Step {
+                        time: self.started.elapsed().as_millis() as u64,
+                        update: update,
+                        x: self.pos.0 as usize,
+                        y: self.pos.1 as usize,
+                    });
+
+                    let opened = *self.opened.last().unwrap();
+
+                    #[cfg(feature = "log")]
+                    log::event("move", serde_json::json!({
+                        "action": format!("{:?}", update),
+                        "x": self.pos.0,
+                        "y": self.pos.1,
+                        "opened": opened,
+                        "won": self.board.won,
+                    }));
+
+                    if let (Some(turns), PushState::Uncover) = (self.hotseat.as_mut(), update) {
+                        if self.board.won == Some(false) {
+                            turns.loser = Some(turns.current);
+                        } else if opened > 0 {
+                            turns.scores[turns.current] += opened;
+                            turns.current = 1 - turns.current;
+                        }
+                    }
+
+                    if let (Some(board), Some(false)) = (before, self.board.won) {
+                        self.rewind = Some(board);
+                        self.clock.pause();
+
+                        renderer.draw_board(self);
+                        renderer.draw_header(self);
+                        renderer.show_prompt(self, "You hit a mine! Press R to rewind to before that move, or any other key to give up");
+                        return true;
+                    }
+
+                    match self.board.won {
+                        Some(_) => self.clock.stop(),
+                        None => self.clock.start(),
+                    }
+
+                    self.analyse();
+
+                    renderer.draw_board(self);
+                    renderer.draw_header(self);
+
+                    if self.board.won.is_some() {
+                        renderer.finish(self);
+                        return false;
+                    }
+
+                    if let (Some(turns), true) = (&self.hotseat, opened > 0) {
+                        renderer.flash_message(self, &format!("Player {}'s turn", turns.current + 1));
+                    }
+
+                    if found > 0 {
+                        renderer.draw_header(self);
+                        renderer.flash_message(self, &format!("Found treasure! {} seconds off the clock", found as u64 * TREASURE_BONUS));
+                    }
+
+                    if let (Some(arcade), true) = (self.arcade.as_mut(), opened >= ARCADE_OPENING) {
+                        let n = arcade.earn();
+
+                        renderer.draw_inventory(self);
+                        renderer.flash_message(self, &format!("Earned a {}! Press {} to use it", PowerUp::ALL[n].name(), n + 1));
+                    }
+                },
+            }
+
+            true
+        }
+    }
+
+    pub mod tests {
+        use super::*;
+        use theme::ThemeName;
+
+        #[derive(Default)]
+        struct Recorder {
+            calls: Vec<String>,
+        }
+
+        impl Renderer for Recorder {
+            fn draw_frame(&mut self, _: &Game) { self.calls.push(String::from("frame")); }
+            fn draw_header(&mut self, _: &Game) { self.calls.push(String::from("header")); }
+            fn draw_board(&mut self, _: &Game) { self.calls.push(String::from("board")); }
+            fn draw_tiles(&mut self, _: &Game, tiles: &[(u16, u16)]) { self.calls.push(format!("tiles {:?}", tiles)); }
+            fn flash_message(&mut self, _: &Game, message: &str) { self.calls.push(format!("message {}", message)); }
+            fn show_prompt(&mut self, _: &Game, message: &str) { self.calls.push(format!("prompt {}", message)); }
+            fn expire_message(&mut self, _: &Game) {}
+            fn show_error(&mut self, _: &Game, message: &str) { self.calls.push(format!("error {}", message)); }
+            fn draw_palette(&mut self, _: &Game, line: &str) { self.calls.push(format!("palette {}", line)); }
+            fn move_cursor(&mut self, _: &Game) { self.calls.push(String::from("cursor")); }
+            fn draw_bot(&mut self, _: &Game) { self.calls.push(String::from("bot")); }
+            fn draw_inventory(&mut self, _: &Game) { self.calls.push(String::from("inventory")); }
+            fn finish(&mut self, _: &Game) { self.calls.push(String::from("finish")); }
+        }
+
+        // Also used by the tests of the other ways a game is shown
+        pub fn game(no_flag: bool) -> Game {
+            let mut board = Board::with_seed(22, 5, 5, 1).unwrap();
+            board.no_flag = no_flag;
+
+            let settings = Settings::new(Theme::builtin(ThemeName::Default));
+
+            Game::new(board, settings, Keymap::new(KeyPreset::Default), None)
+        }
+
+        fn press(game: &mut Game, renderer: &mut Recorder, keys: &str) -> bool {
+            keys.chars().all(|i| game.input(Event::Key(KeyEvent::from(KeyCode::Char(i))), renderer))
+        }
+
+        #[test]
+        fn game_loop() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            assert!(press(&mut test_game, &mut renderer, "dds"));
+            assert_eq!(test_game.pos, (2, 1));
+            assert_eq!(renderer.calls.last().unwrap(), "tiles [(2, 0), (2, 1)]");
+
+            assert!(press(&mut test_game, &mut renderer, ":goto 5 3"));
+            assert!(test_game.input(Event::Key(KeyEvent::from(KeyCode::Enter)), &mut renderer));
+            assert_eq!(test_game.pos, (4, 2));
+            assert!(test_game.palette.is_none());
+
+            assert!(press(&mut test_game, &mut renderer, "e"));
+            assert_eq!(test_game.board.flag_total, 1);
+            assert_eq!(renderer.calls[renderer.calls.len() - 2..], ["board", "header"]);
+
+            assert!(!test_game.input(Event::Key(KeyEvent::from(KeyCode::Esc)), &mut renderer));
+            assert_eq!(renderer.calls.last().unwrap(), "finish");
+        }
+
+        #[test]
+        fn game_no_flag() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(true);
+
+            assert!(press(&mut test_game, &mut renderer, "e"));
+            assert_eq!(test_game.board.flag_total, 0);
+            assert_eq!(renderer.calls, ["message Flags are disabled in no-flag mode"]);
+        }
+
+        #[test]
+        fn game_flag_limit() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+            test_game.board.strict_flags = true;
+
+            assert!(press(&mut test_game, &mut renderer, "ededededede"));
+            assert_eq!(test_game.board.flag_total, 5);
+            assert_eq!(renderer.calls.last().unwrap(), "message Flag limit reached");
+        }
+
+        #[test]
+        fn game_arcade() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+            test_game.board = Board::from_save("rs-minesweeper save\nwidth 7\nfirst-uncover false\n\n.......\n.......\n.....**\n.....*.\n").unwrap();
+            test_game.arcade = Some(game::Arcade::new(1));
+
+            assert!(press(&mut test_game, &mut renderer, "q"));
+            assert_eq!(test_game.arcade.as_ref().unwrap().inventory.iter().sum::<usize>(), 1);
+            assert!(renderer.calls.last().unwrap().starts_with("message Earned a "));
+
+            test_game.arcade.as_mut().unwrap().inventory = [1, 1, 1];
+            assert!(press(&mut test_game, &mut renderer, "3"));
+            assert_eq!(renderer.calls.last().unwrap(), "message Put the cursor on a flag to defuse the mine under it");
+
+            test_game.pos = (5, 2);
+            assert!(press(&mut test_game, &mut renderer, "e3"));
+            assert_eq!(test_game.board.mine_total, 2);
+            assert_eq!(test_game.board.cell(5, 2), Cell::Open(2));
+
+            assert!(press(&mut test_game, &mut renderer, "2"));
+            assert!(test_game.clock.frozen());
+            assert_eq!(test_game.arcade.as_ref().unwrap().inventory, [1, 0, 0]);
+
+            assert!(!press(&mut test_game, &mut renderer, "1"));
+            assert_eq!(test_game.board.won, Some(true));
+        }
+
+        #[test]
+        fn game_blind() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+            test_game.board = Board::from_save("rs-minesweeper save\nwidth 4\nfirst-uncover false\n\n....\n....\n..**\n..*.\n").unwrap();
+            test_game.blind = Some(game::Blind::new(game::Interval::Moves(1), 16));
+
+            assert!(press(&mut test_game, &mut renderer, "q"));
+            assert_eq!(test_game.board.cell(1, 1), Cell::Open(1));
+            assert!(test_game.blind.as_ref().unwrap().faded.iter().all(|i| !i));
+
+            test_game.pos = (3, 3);
+            assert!(press(&mut test_game, &mut renderer, "e"));
+            let faded = &test_game.blind.as_ref().unwrap().faded;
+            assert!(faded[5] && faded[6] && !faded[0]);
+            assert!(!faded[15]);
+        }
+
+        #[test]
+        fn game_autosave() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+            let path = std::env::temp_dir().join(format!("rs-minesweeper-autosave-{}", std::process::id()));
+            test_game.autosave = Some(game::Autosave::new(path.clone(), game::Interval::Moves(2)));
+
+            assert!(press(&mut test_game, &mut renderer, "dq"));
+            assert!(test_game.tick(&mut renderer));
+            assert!(!path.exists());
+
+            assert!(press(&mut test_game, &mut renderer, "de"));
+            assert!(test_game.tick(&mut renderer));
+            let saved = Board::from_save(&fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(saved.tiles, test_game.board.tiles);
+
+            // A restarted game is saved after its own moves, not once it catches up with the old one
+            assert!(test_game.handle(Action::Restart(Some(1)), &mut renderer));
+            assert!(press(&mut test_game, &mut renderer, "edq"));
+            assert!(test_game.tick(&mut renderer));
+            let saved = Board::from_save(&fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(saved.tiles, test_game.board.tiles);
+
+            fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn game_error() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            assert!(test_game.handle(Action::Save(String::from("/nonexistent/board")), &mut renderer));
+            assert!(renderer.calls.last().unwrap().starts_with("error Could not save to /nonexistent/board.save"));
+
+            assert!(press(&mut test_game, &mut renderer, "d"));
+            assert_eq!(renderer.calls.last().unwrap(), "frame");
+            assert_eq!(test_game.pos, (0, 0));
+
+            // A seed that can't give a board with the same rules keeps the current game going
+            let generation = Generation { min_opening: 12, ..Generation::default() };
+            let (good, bad): (Vec<u64>, Vec<u64>) = (0..10).partition(|i| Board::generate(9, 9, 30, *i, generation).is_ok());
+            test_game.board = Board::generate(9, 9, 30, good[0], generation).unwrap();
+
+            assert!(test_game.handle(Action::Restart(Some(bad[0])), &mut renderer));
+            assert!(renderer.calls.last().unwrap().starts_with("error No board with an opening of 12 tiles"));
+            assert_eq!(test_game.board.seed, good[0]);
+        }
+
+        #[test]
+        fn game_hotseat() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            test_game.board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n..*\n").unwrap();
+            test_game.hotseat = Some(Hotseat::default());
+
+            assert!(press(&mut test_game, &mut renderer, "dqq"));
+            assert_eq!(test_game.hotseat, Some(Hotseat { scores: [1, 0], current: 1, loser: None }));
+            assert!(renderer.calls.contains(&String::from("message Player 2's turn")));
+
+            assert!(!press(&mut test_game, &mut renderer, "aq"));
+            let turns = test_game.hotseat.unwrap();
+            assert_eq!((turns.loser, turns.winner()), (Some(1), Some(0)));
+            assert_eq!(turns.result(), "Player 2 hit a mine, so player 1 wins");
+        }
+
+        #[test]
+        fn game_key_repeat() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            // Moves that come right after each other speed up, as if the key were held
+            test_game.acceleration = vec![1, 2, 4];
+            assert!(press(&mut test_game, &mut renderer, "dddd"));
+            assert_eq!(test_game.pos, (11, 0));
+
+            assert!(press(&mut test_game, &mut renderer, "sa"));
+            assert_eq!(test_game.pos, (10, 1));
+        }
+
+        #[test]
+        fn game_coop() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            test_game.board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n..*\n").unwrap();
+            test_game.partner = Some((2, 1));
+
+            assert!(test_game.input(Event::Key(KeyEvent::from(KeyCode::Up)), &mut renderer));
+            assert!(press(&mut test_game, &mut renderer, "s,"));
+            assert_eq!(test_game.cursors(), ((0, 1), Some((2, 0))));
+            assert_eq!(test_game.board.cell(2, 0), Cell::Open(0));
+            assert_eq!(test_game.board.cell(0, 1), Cell::Covered);
+        }
+
+        #[test]
+        fn game_casual() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            test_game.board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n..*\n").unwrap();
+            test_game.settings.casual = true;
+
+            assert!(press(&mut test_game, &mut renderer, "dq"));
+            assert!(press(&mut test_game, &mut renderer, "aq"));
+            assert_eq!(test_game.board.won, Some(false));
+
+            assert!(press(&mut test_game, &mut renderer, "r"));
+            assert_eq!(test_game.board.won, None);
+            assert_eq!(test_game.board.cell(0, 0), Cell::Covered);
+            assert_eq!(test_game.board.cell(1, 0), Cell::Open(1));
+            assert_eq!(test_game.deaths, 1);
+            assert_eq!(test_game.steps.len(), 1);
+            assert_eq!(test_game.opened, [1]);
+            assert!(test_game.board.assisted);
+
+            assert!(press(&mut test_game, &mut renderer, "q"));
+            assert!(!press(&mut test_game, &mut renderer, "e"));
+            assert_eq!(test_game.board.won, Some(false));
+        }
+
+        #[test]
+        fn game_deductions() {
+            let mut renderer = Recorder::default();
+            let mut test_game = game(false);
+
+            assert!(press(&mut test_game, &mut renderer, "ddddddddddssq"));
+            assert!(test_game.deductions.is_empty());
+            assert!(!test_game.board.assisted);
+
+            assert!(press(&mut test_game, &mut renderer, "i"));
+            assert_eq!(test_game.deductions, solver::csp(&test_game.board.visible_state()));
+            assert!(!test_game.deductions.is_empty());
+            assert!(test_game.board.assisted);
+            assert_eq!(test_game.clock.seconds(), 10);
+            assert_eq!(renderer.calls.last().unwrap(), "message Showing deductions");
+
+            assert!(press(&mut test_game, &mut renderer, "i"));
+            assert!(test_game.deductions.is_empty());
+
+            assert!(press(&mut test_game, &mut renderer, "?"));
+            let pos = (test_game.pos.0 as usize, test_game.pos.1 as usize);
+            let found = solver::csp(&test_game.board.visible_state());
+            assert!(found.safe.contains(&pos));
+            assert_eq!(test_game.clock.seconds(), 20);
+            assert_eq!(renderer.calls.last().unwrap(), &format!("message ({}, {}) is safe: {}", pos.0 + 1, pos.1 + 1, found.reason(pos).unwrap()));
+        }
+    }
+}
+
+// What the terminal should show, kept as a character and a style for every cell. Everything is drawn straight into
+// the cells, and present() only sends the ones that changed since the last frame
+mod screen {
+    use crossterm::cursor;
+    use crossterm::style::{ContentStyle, PrintStyledContent, ResetColor, StyledContent};
+    use crossterm::terminal::{Clear, ClearType, SetTitle};
+    use std::fmt::{self, Display, Write};
+    use unicode_width::UnicodeWidthChar;
+
+    type Cell = (char, ContentStyle);
+
+    // Fills the second cell of a character that is two columns wide
+    const WIDE: char = '\0';
+
+    fn blank() -> Cell {
+        (' ', ContentStyle::default())
+    }
+
+    pub struct Screen {
+        width: usize,
+        height: usize,
+        cells: Vec<Cell>,
+        // What the terminal showed after the last present, or nothing when it has to be drawn from scratch
+        shown: Option<Vec<Cell>>,
+        cursor: Option<(u16, u16)>,
+        shown_cursor: Option<Option<(u16, u16)>>,
+        // Commands that aren't about cells, like the window title, sent as they are with the next frame
+        commands: Vec<u8>,
+        dirty: bool,
+    }
+
+    // Writes formatted text into the cells one character at a time, so nothing has to be collected into a String
+    struct Pen<'a> {
+        screen: &'a mut Screen,
+        x: u16,
+        y: u16,
+        style: ContentStyle,
+    }
+
+    impl<'a> Write for Pen<'a> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for c in s.chars() {
+                self.x = self.screen.put(self.x, self.y, c, self.style);
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Screen {
+        pub fn new(size: (u16, u16)) -> Screen {
+            let (width, height) = (size.0 as usize, size.1 as usize);
+
+            Screen {
+                width: width,
+                height: height,
+                cells: vec![blank(); width * height],
+                shown: None,
+                cursor: None,
+                shown_cursor: None,
+                commands: vec![],
+                dirty: false,
+            }
+        }
+
+        // A new size starts over with a blank screen that is drawn in full
+        pub fn resize(&mut self, size: (u16, u16)) {
+            if (size.0 as usize, size.1 as usize) != (self.width, self.height) {
+                *self = Screen { commands: self.commands.split_off(0), ..Screen::new(size) };
+                self.dirty = true;
+            }
+        }
+
+        // Whether anything was drawn since the last present
+        pub fn dirty(&self) -> bool {
+            self.dirty
+        }
+
+        pub fn height(&self) -> u16 {
+            self.height as u16
+        }
+
+        // Draws one character and gives the column after it. Anything past the edge is lost, since lines aren't
+        // wrapped, and characters with no width of their own are left out
+        pub fn put(&mut self, x: u16, y: u16, c: char, style: ContentStyle) -> u16 {
+            let width = c.width().unwrap_or(0);
+            let (column, row) = (x as usize, y as usize);
+
+            if width > 0 && row < self.height && column + width <= self.width {
+                let i = row * self.width + column;
+
+                for n in i..i + width {
+                    self.split(n);
+                }
+
+                self.cells[i] = (c, style);
+
+                if width == 2 {
+                    self.cells[i + 1] = (WIDE, style);
+                }
+
+                self.dirty = true;
+            }
+
+            x.saturating_add(width as u16)
+        }
+
+        // Drawing over half of a wide character blanks the other half, the same as the terminal does
+        fn split(&mut self, i: usize) {
+            if self.cells[i].0 == WIDE {
+                self.cells[i - 1] = blank();
+            } else if self.cells[i].0.width() == Some(2) {
+                self.cells[i + 1] = blank();
+            }
+        }
+
+        pub fn print(&mut self, x: u16, y: u16, text: impl Display, style: ContentStyle) -> u16 {
+            let mut pen = Pen { screen: self, x: x, y: y, style: style };
+            write!(pen, "{}", text).unwrap();
+            pen.x
+        }
+
+        pub fn print_styled<D: Display>(&mut self, x: u16, y: u16, content: &StyledContent<D>) -> u16 {
+            self.print(x, y, content.content(), *content.style())
+        }
+
+        pub fn clear(&mut self) {
+            self.cells = vec![blank(); self.cells.len()];
+            self.dirty = true;
+        }
+
+        pub fn clear_line(&mut self, y: u16) {
+            let row = y as usize;
+
+            if row < self.height {
+                for i in &mut self.cells[row * self.width..(row + 1) * self.width] {
+                    *i = blank();
+                }
+            }
+
+            self.dirty = true;
+        }
+
+        // Moves every line up, dropping the top ones, like printing past the bottom of the terminal does
+        pub fn scroll(&mut self, lines: u16) {
+            let lines = (lines as usize).min(self.height);
+
+            self.cells.drain(..lines * self.width);
+            self.cells.extend(vec![blank(); lines * self.width]);
+            self.dirty = true;
+        }
+
+        // Where the cursor shows once the frame is written, with nothing hiding it
+        pub fn show_cursor(&mut self, cursor: Option<(u16, u16)>) {
+            self.cursor = cursor;
+            self.dirty = true;
+        }
+
+        pub fn title(&mut self, title: &str) {
+            queue!(self.commands, SetTitle(title)).unwrap();
+            self.dirty = true;
+        }
+
+        // The bytes that take the terminal from the last frame to this one
+        pub fn present(&mut self) -> Vec<u8> {
+            let mut out = self.commands.split_off(0);
+            let shown = match self.shown.take() {
+                Some(i) => i,
+                None => {
+                    queue!(out, ResetColor, Clear(ClearType::All)).unwrap();
+                    vec![blank(); self.cells.len()]
+                },
+            };
+
+            let mut next = None;
+            let mut i = 0;
+
+            while i < self.cells.len() {
+                if self.cells[i] == shown[i] || self.cells[i].0 == WIDE {
+                    i += 1;
+                    continue;
+                }
+
+                if next.is_none() {
+                    queue!(out, cursor::Hide).unwrap();
+                }
+
+                if next != Some(i) {
+                    queue!(out, cursor::MoveTo((i % self.width) as u16, (i / self.width) as u16)).unwrap();
+                }
+
+                // Changed cells next to each other on a line go out together when they have the same style
+                let style = self.cells[i].1;
+                let end = (i / self.width + 1) * self.width;
+                let mut text = String::new();
+
+                while i < end && self.cells[i] != shown[i] && self.cells[i].1 == style {
+                    if self.cells[i].0 != WIDE {
+                        text.push(self.cells[i].0);
+                    }
+
+                    i += 1;
+                }
+
+                queue!(out, PrintStyledContent(StyledContent::new(style, text))).unwrap();
+                next = Some(i);
+            }
+
+            // The cursor goes back to where drawing left it, since the cells were written out of order
+            if next.is_some() || self.shown_cursor != Some(self.cursor) {
+                match self.cursor {
+                    Some((x, y)) => queue!(out, cursor::MoveTo(x.min(self.width.saturating_sub(1) as u16), y), cursor::Show),
+                    None => queue!(out, cursor::Hide),
+                }.unwrap();
+            }
+
+            self.shown = Some(self.cells.clone());
+            self.shown_cursor = Some(self.cursor);
+            self.dirty = false;
+            out
+        }
+    }
+
+    mod tests {
+        use super::*;
+        use crossterm::style::{style, Styler};
+
+        #[test]
+        fn screen_frames() {
+            let mut screen = Screen::new((6, 2));
+            let reverse = *style("").reverse().style();
+
+            screen.print(1, 0, "ab", ContentStyle::default());
+            let x = screen.put(0, 1, 'c', reverse);
+            screen.put(x, 1, 'd', ContentStyle::default());
+            screen.show_cursor(Some((2, 1)));
+            assert_eq!(
+                String::from_utf8(screen.present()).unwrap(),
+                "\x1b[0m\x1b[2J\x1b[?25l\x1b[1;2Hab\x1b[2;1H\x1b[7mc\x1b[0md\x1b[2;3H\x1b[?25h",
+            );
+
+            // Drawing the same thing again sends nothing, and only the cells that changed go out after that
+            screen.print(1, 0, "ab", ContentStyle::default());
+            screen.print_styled(0, 1, &style("c").reverse());
+            assert_eq!(screen.present(), b"");
+
+            // Half of a wide character drawn over blanks the rest of it
+            screen.print(0, 0, "漢b", ContentStyle::default());
+            screen.show_cursor(None);
+            assert_eq!(String::from_utf8(screen.present()).unwrap(), "\x1b[?25l\x1b[1;1H漢\x1b[?25l");
+
+            screen.put(1, 0, 'x', ContentStyle::default());
+            assert_eq!(String::from_utf8(screen.present()).unwrap(), "\x1b[?25l\x1b[1;1H x\x1b[?25l");
+        }
+    }
+}
+
+mod render {
+    use super::*;
+    use game::Game;
+
+    use std::io::Write;
+
+    pub trait Renderer {
+        fn draw_frame(&mut self, game: &Game);
+        fn draw_header(&mut self, game: &Game);
+        fn draw_board(&mut self, game: &Game);
+        fn draw_tiles(&mut self, game: &Game, tiles: &[(u16, u16)]);
+        // Messages on the status line disappear after a few seconds, while prompts stay until something replaces them
+        fn flash_message(&mut self, game: &Game, message: &str);
+        fn show_prompt(&mut self, game: &Game, message: &str);
+        fn expire_message(&mut self, game: &Game);
+        fn show_error(&mut self, game: &Game, message: &str);
+        fn draw_palette(&mut self, game: &Game, line: &str);
+        fn move_cursor(&mut self, game: &Game);
+        fn draw_bot(&mut self, game: &Game);
+        fn draw_inventory(&mut self, game: &Game);
+        fn finish(&mut self, game: &Game);
+    }
+
+    pub struct Terminal {
+        screen: Screen,
+        depth: usize,
+        interval: Duration,
+        flushed: Instant,
+        title: String,
+        message_expires: Option<Instant>,
+        pub cast: Option<cast::Cast>,
+    }
+
+    const HISTORY_WIDTH: u16 = 34;
+    const MESSAGE_TIMEOUT: Duration = Duration::from_secs(4);
+
+    // There is no way to read the window title, so the one from before the game is kept on xterm's title stack,
+    // which most terminals support, and popped back off on exit
+    pub const PUSH_TITLE: &str = "\x1b[22;0t";
+    pub const POP_TITLE: &str = "\x1b[23;0t";
+
+    // Splits text into lines of at most width characters, breaking between words where it can
+    fn wrap(text: &str, width: usize) -> Vec<String> {
+        let mut lines = vec![String::new()];
+
+        for word in text.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let line = lines.last_mut().unwrap();
+            let used = line.chars().count();
+
+            if used > 0 && used + 1 + word.len() <= width {
+                line.push(' ');
+            } else if used > 0 {
+                lines.push(String::new());
+            }
+
+            while word.len() > width {
+                lines.last_mut().unwrap().extend(word.drain(..width));
+                lines.push(String::new());
+            }
+
+            lines.last_mut().unwrap().extend(word);
+        }
+
+        lines
+    }
+
+    // One line of the move history, like "00:42 uncover (5, 3), opened 12"
+    fn history_line(step: &Step, opened: usize, fatal: bool) -> String {
+        let seconds = step.time / 1000;
+        let action = match step.update {
+            PushState::Uncover => "uncover",
+            PushState::Flag => "flag",
+        };
+
+        let mut line = format!("{:02}:{:02} {} ({}, {})", seconds / 60, seconds % 60, action, step.x + 1, step.y + 1);
+
+        if fatal {
+            line.push_str(", hit a mine");
+        } else if opened > 0 {
+            line.push_str(&format!(", opened {}", opened));
+        }
+
+        line
+    }
+
+    fn board_view(game: &Game) -> View {
+        let (pos, partner) = game.cursors();
+        let mut view = game.board.view(&game.settings.theme)
+            .layer(game.layer())
+            .cell(game.layout.cell.0, game.layout.cell.1)
+            .cursor(pos.0 as usize, pos.1 as usize)
+            .mark(&game.deductions.safe, &game.deductions.mines);
+
+        if let Some(i) = &game.chances {
+            view = view.chances(&i.chances);
+        }
+
+        if let Some(i) = partner {
+            view = view.partner(i.0 as usize, i.1 as usize);
+        }
+
+        if game.settings.square {
+            view = view.spaced();
+        }
+
+        if game.overview {
+            view = view.overview();
+        }
+
+        if let Some(blind) = &game.blind {
+            view = view.fade(&blind.faded);
+        }
+
+        if game.settings.highlight_neighbors {
+            view.highlight_neighbors(pos.0 as usize, pos.1 as usize)
+        } else {
+            view
+        }
+    }
+
+    impl Terminal {
+        // Writes at most max_fps frames a second, with 0 writing every update as soon as it is drawn
+        pub fn new(max_fps: u32) -> Terminal {
+            let interval = if max_fps == 0 { Duration::from_secs(0) } else { Duration::from_secs(1) / max_fps };
+
+            Terminal {
+                screen: Screen::new(size().unwrap_or((80, 24))),
+                depth: 0,
+                interval: interval,
+                flushed: Instant::now().checked_sub(interval).unwrap_or_else(Instant::now),
+                title: String::new(),
+                message_expires: None,
+                cast: None,
+            }
+        }
+
+        // Every drawing method runs inside a frame, and only the outermost one writes the frame out, so each update
+        // reaches the terminal in a single flush instead of tearing on slow connections. Updates that come in faster
+        // than the frame rate stay on the screen and go out together once the next frame is due, as only the cells
+        // that differ from what the terminal already shows
+        fn begin(&mut self) {
+            self.depth += 1;
+        }
+
+        fn end(&mut self) {
+            self.depth -= 1;
+
+            if self.depth == 0 && self.flushed.elapsed() >= self.interval {
+                self.flush();
+            }
+        }
+
+        pub fn flush(&mut self) {
+            if !self.screen.dirty() {
+                return;
+            }
+
+            #[cfg(feature = "log")]
+            let started = Instant::now();
+            let frame = self.screen.present();
+            let mut out = stdout();
+            out.write_all(&frame).unwrap();
+            out.flush().unwrap();
+
+            #[cfg(feature = "log")]
+            log::event("render", serde_json::json!({ "bytes": frame.len(), "micros": started.elapsed().as_micros() as u64 }));
+
+            if let Some(i) = self.cast.as_mut() {
+                i.push(&frame);
+            }
+
+            self.flushed = Instant::now();
+        }
+
+        // How long until the buffered updates should be written, if there are any
+        pub fn due(&self) -> Option<Duration> {
+            if !self.screen.dirty() {
+                None
+            } else {
+                Some(self.interval.saturating_sub(self.flushed.elapsed()))
+            }
+        }
+
+        fn history_fits(&self, game: &Game) -> bool {
+            size().map_or(false, |i| i.0 >= game.layout.width + 3 + game.layout.gutters.0 + HISTORY_WIDTH)
+        }
+
+        // Fills the space to the right of the board with the latest moves, newest at the bottom
+        fn draw_history(&mut self, game: &Game) {
+            if !game.settings.show_history || !self.history_fits(game) {
+                return;
+            }
+
+            let x = game.layout.width + 3 + game.layout.gutters.0;
+            let rows = (game.layout.board_top + game.layout.height + 1) as usize;
+            let shown = cmp::min(game.steps.len(), rows - 1);
+            let start = game.steps.len() - shown;
+
+            let mut lines = vec![String::from("Moves")];
+
+            for i in start..game.steps.len() {
+                let fatal = i == game.steps.len() - 1 && game.board.won == Some(false);
+                lines.push(history_line(&game.steps[i], game.opened.get(i).cloned().unwrap_or(0), fatal));
+            }
+
+            for row in 0..rows {
+                let line = lines.get(row).map_or("", |i| i.as_str());
+                self.screen.print(x, row as u16, format_args!("{:<1$}", line, HISTORY_WIDTH as usize), ContentStyle::default());
+            }
+        }
+
+        // The mines left in every row go to the right of the board, and those in every column under it with their
+        // digits going down. Rows and columns with too many flags show 0 in reverse
+        fn draw_line_hints(&mut self, game: &Game) {
+            if !game.board.line_hints {
+                return;
+            }
+
+            let layout = &game.layout;
+            let color = game.settings.theme.counter;
+            let (rows, columns) = game.board.line_counts();
+            let styled = |n: i64, text: String| if n < 0 { paint(text, color).reverse() } else { paint(text, color) };
+
+            for (y, n) in rows.iter().enumerate() {
+                let row = layout.board_top + y as u16 * layout.cell.1 + layout.cell.1 / 2;
+                self.screen.print_styled(layout.width + 2, row, &styled(*n, format!("{:>1$}", cmp::max(*n, 0), layout.gutters.0 as usize)));
+            }
+
+            for (x, n) in columns.iter().enumerate() {
+                let text = format!("{:>1$}", cmp::max(*n, 0), layout.gutters.1 as usize);
+
+                for (row, digit) in text.chars().enumerate() {
+                    let column = 1 + x as u16 * layout.cell.0 + layout.cell.0 / 2;
+                    self.screen.print_styled(column, layout.board_top + layout.height + 1 + row as u16, &styled(*n, digit.to_string()));
+                }
+            }
+        }
+    }
+
+    impl Renderer for Terminal {
+        fn draw_frame(&mut self, game: &Game) {
+            if let Ok(i) = size() {
+                self.screen.resize(i);
+            }
+
+            self.begin();
+
+            let layout = &game.layout;
+            let width = layout.width;
+            let theme = &game.settings.theme;
+            let border = &theme.border;
+            let horizontal = |n: u16| border.horizontal.to_string().repeat(n as usize);
+
+            let header = if layout.compact {
+                vec![
+                    format!("{}{}{}", border.top_left, horizontal(width), border.top_right),
+                    format!("{}{}{}", border.vertical, " ".repeat(width as usize), border.vertical),
+                    format!("{}{}{}", border.vertical, " ".repeat(width as usize), border.vertical),
+                    format!("{}{}{}", border.left_tee, horizontal(width), border.right_tee),
+                ]
+            } else {
+                let counter_box = horizontal(layout.counter_width + 2);
+                let timer_box = horizontal(layout.timer_width + 2);
+                let counter_space = " ".repeat(layout.counter_width as usize + 2);
+                let timer_space = " ".repeat(layout.timer_width as usize + 2);
+
+                vec![
+                    format!("{}{}{}{}{}{}{}", border.top_left, counter_box, border.top_tee, horizontal(layout.middle.2), border.top_tee, timer_box, border.top_right),
+                    format!("{0}{1}{0}{2}{0}{3}{0}", border.vertical, counter_space, " ".repeat(layout.middle.2 as usize), timer_space),
+                    format!("{}{}{}{}{}{}{}", border.left_tee, counter_box, border.bottom_tee, horizontal(layout.middle.2), border.bottom_tee, timer_box, border.right_tee),
+                ]
+            };
+
+            self.screen.clear();
+
+            for (y, line) in header.iter().enumerate() {
+                self.screen.print(0, y as u16, line, ContentStyle::default());
+            }
+
+            board_view(game).draw(&mut self.screen, layout.board_top);
+
+            let bottom = format!("{}{}{}", border.bottom_left, horizontal(width), border.bottom_right);
+            self.screen.print(0, layout.board_top + layout.height, bottom, ContentStyle::default());
+
+            self.draw_line_hints(game);
+
+            if game.settings.show_history && !self.history_fits(game) {
+                self.flash_message(game, "The move history needs a wider terminal");
+            }
+
+            self.draw_history(game);
+            self.draw_bot(game);
+            self.draw_inventory(game);
+            self.draw_header(game);
+
+            self.end();
+        }
+
+        fn draw_header(&mut self, game: &Game) {
+            self.begin();
+
+            let layout = &game.layout;
+            let theme = &game.settings.theme;
+            let (middle_x, middle_y, middle_width) = layout.middle;
+
+            self.screen.print_styled(layout.counter.0, layout.counter.1, &paint(layout.counter_text(&game.board), theme.counter));
+            self.screen.print_styled(layout.timer.0, layout.timer.1, &paint(layout.timer_text(game.clock.seconds()), theme.timer));
+
+            // Shows the game in the title too, so it can be followed while the terminal is in the background
+            let seconds = game.clock.seconds();
+            let title = format!("rs-minesweeper — {:03} mines, {:02}:{:02}", game.board.mines_left(), seconds / 60, seconds % 60);
+
+            if title != self.title {
+                self.screen.title(&title);
+                self.title = title;
+            }
+
+            if let Some(i) = game.board.won {
+                let message = match (&game.hotseat, i) {
+                    (Some(turns), _) => turns.winner().map_or(String::from("DRAW"), |i| format!("P{} WINS", i + 1)),
+                    (None, true) => String::from("YOU WON"),
+                    (None, false) => String::from("YOU LOST"),
+                };
+
+                self.screen.print(middle_x, middle_y, format_args!("{:^1$}", message, middle_width as usize), ContentStyle::default());
+            } else {
+                let text = if game.idle {
+                    String::from("IDLE")
+                } else if let Some(turns) = &game.hotseat {
+                    let mark = |i| if turns.current == i { '>' } else { ' ' };
+                    let scores = format!("{}P1 {} {}P2 {}", mark(0), turns.scores[0], mark(1), turns.scores[1]);
+
+                    // Falls back to "<12:7", with the arrow pointing at the player whose turn it is
+                    if scores.len() as u16 + 4 <= middle_width {
+                        scores
+                    } else if turns.current == 0 {
+                        format!("<{}:{}", turns.scores[0], turns.scores[1])
+                    } else {
+                        format!("{}:{}>", turns.scores[0], turns.scores[1])
+                    }
+                } else if game.board.layers() > 1 {
+                    let layer = format!("L{}/{}", game.layer() + 1, game.board.layers());
+
+                    if layer.len() as u16 + 10 <= middle_width {
+                        format!("{} {:>3}%", layer, game.board.progress())
+                    } else {
+                        layer
+                    }
+                } else if let Some(puzzle) = &game.daily {
+                    let streak = format!("streak {} best {}", puzzle.current, puzzle.best);
+                    let short = format!("streak {}", puzzle.current);
+
+                    // Falls back to "3/7", the current streak and then the best
+                    if streak.len() as u16 + 4 <= middle_width {
+                        streak
+                    } else if short.len() as u16 + 4 <= middle_width {
+                        short
+                    } else {
+                        format!("{}/{}", puzzle.current, puzzle.best)
+                    }
+                } else if game.settings.show_score {
+                    let points = Score::of(&game.board, game.clock.seconds()).points.to_string();
+
+                    // Leaves room on both sides for the flag mode indicator
+                    if points.len() as u16 + 8 <= middle_width {
+                        format!("{} pts", points)
+                    } else {
+                        points
+                    }
+                } else {
+                    format!("{:>3}%", game.board.progress())
+                };
+
+                let indicator = if game.flag_mode {
+                    theme.flag_mode.styled()
+                } else {
+                    style(" ")
+                };
+
+                self.screen.print(middle_x, middle_y, " ".repeat(middle_width as usize), ContentStyle::default());
+                self.screen.print_styled(middle_x + (middle_width - text.len() as u16) / 2, middle_y, &paint(text, theme.progress));
+                self.screen.print_styled(layout.flag_mode.0, layout.flag_mode.1, &indicator);
+            }
+
+            self.move_cursor(game);
+
+            self.end();
+        }
+
+        fn draw_board(&mut self, game: &Game) {
+            self.begin();
+
+            board_view(game).draw(&mut self.screen, game.layout.board_top);
+
+            self.draw_history(game);
+            self.draw_line_hints(game);
+            self.move_cursor(game);
+
+            self.end();
+        }
+
+        fn draw_tiles(&mut self, game: &Game, tiles: &[(u16, u16)]) {
+            // Any tile can change a block of the overview, and it is small enough to draw whole
+            if game.overview {
+                return self.draw_board(game);
+            }
+
+            self.begin();
+
+            let view = board_view(game);
+
+            for tile in tiles {
+                let shown = match game.on_screen(*tile) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                for row in 0..game.layout.cell.1 {
+                    let (x, y) = game.layout.row_at(&shown, row);
+                    self.screen.print_styled(x, y, &view.tile_row(tile.0 as usize, tile.1 as usize, row as usize));
+                }
+            }
+
+            self.draw_line_hints(game);
+            self.move_cursor(game);
+
+            self.end();
+        }
+
+        fn flash_message(&mut self, game: &Game, message: &str) {
+            self.show_prompt(game, message);
+
+            if !message.is_empty() {
+                self.message_expires = Some(Instant::now() + MESSAGE_TIMEOUT);
+            }
+        }
+
+        fn show_prompt(&mut self, game: &Game, message: &str) {
+            self.begin();
+
+            self.screen.clear_line(game.layout.status);
+            self.screen.print(0, game.layout.status, message, ContentStyle::default());
+
+            self.message_expires = None;
+            self.move_cursor(game);
+
+            self.end();
+        }
+
+        fn expire_message(&mut self, game: &Game) {
+            if self.message_expires.map_or(false, |i| Instant::now() >= i) {
+                self.show_prompt(game, "");
+            }
+        }
+
+        // A box over the middle of the board, which stays until the next key redraws everything
+        fn show_error(&mut self, game: &Game, message: &str) {
+            #[cfg(feature = "log")]
+            log::event("error", serde_json::json!({ "text": message }));
+
+            self.begin();
+
+            let border = &game.settings.theme.border;
+            let columns = size().map_or(80, |i| i.0) as usize;
+            let width = cmp::min(cmp::max(game.layout.width as usize + 2, 24), columns);
+            let horizontal = border.horizontal.to_string().repeat(width - 2);
+
+            let mut lines = wrap(message, width - 4);
+            lines.push(String::new());
+            lines.push(String::from("Press any key"));
+
+            let top = game.layout.board_top + game.layout.height.saturating_sub(lines.len() as u16 + 2) / 2;
+
+            // Joins up with the sides of the board when it is just as wide
+            let (left, right) = if width == game.layout.width as usize + 2 {
+                (border.left_tee, border.right_tee)
+            } else {
+                (border.top_left, border.top_right)
+            };
+
+            self.screen.show_cursor(None);
+            self.screen.clear_line(game.layout.status);
+            self.screen.print(0, top, format_args!("{}{}{}", left, horizontal, right), ContentStyle::default());
+
+            for (n, line) in lines.iter().enumerate() {
+                self.screen.print(0, top + n as u16 + 1, format_args!("{0} {1:2$} {0}", border.vertical, line, width - 4), ContentStyle::default());
+            }
+
+            let (left, right) = if width == game.layout.width as usize + 2 {
+                (border.left_tee, border.right_tee)
+            } else {
+                (border.bottom_left, border.bottom_right)
+            };
+
+            self.screen.print(0, top + lines.len() as u16 + 1, format_args!("{}{}{}", left, horizontal, right), ContentStyle::default());
+
+            self.end();
+        }
+
+        // The bot's progress goes on the line under the status line, as a bar as wide as the board
+        fn draw_bot(&mut self, game: &Game) {
+            let bot = match &game.bot {
+                Some(i) => i,
+                None => return,
+            };
+
+            let row = game.layout.status + 1;
+
+            if size().map_or(true, |i| row >= i.1) {
+                return;
+            }
+
+            self.begin();
+
+            let status = bot.status();
+            let width = (game.layout.width as usize + 2).saturating_sub(status.chars().count() + 1);
+            let filled = width * bot.board.progress() as usize / 100;
+            let (full, empty) = if game.settings.theme.capabilities.unicode { ("█", "░") } else { ("#", "-") };
+            let bar = if bot.board.won.is_none() { format!("{}{} ", full.repeat(filled), empty.repeat(width - filled)) } else { String::new() };
+
+            self.screen.clear_line(row);
+            let x = self.screen.print_styled(0, row, &paint(bar, game.settings.theme.progress));
+            self.screen.print(x, row, status, ContentStyle::default());
+
+            self.move_cursor(game);
+            self.end();
+        }
+
+        // Arcade power-ups go on the line under the status line, like "1 Reveal x2  2 Freeze x0  3 Defuse x1", and so
+        // do the lives left in rush mode
+        fn draw_inventory(&mut self, game: &Game) {
+            let mut line = match (&game.arcade, &game.rush) {
+                (Some(arcade), _) => game::PowerUp::ALL.iter().enumerate()
+                    .map(|(n, i)| format!("{} {} x{}", n + 1, i.name(), arcade.inventory[n]))
+                    .join("  "),
+                (None, Some(rush)) => format!("{}  {}", campaign::lives_text(rush.lives), rush.rows_text()),
+                (None, None) => return,
+            };
+
+            let row = game.layout.status + 1;
+
+            if size().map_or(true, |i| row >= i.1) {
+                return;
+            }
+
+            self.begin();
+
+            if game.clock.frozen() {
+                line.push_str("  FROZEN");
+            }
+
+            self.screen.clear_line(row);
+            self.screen.print_styled(0, row, &paint(line, game.settings.theme.progress));
+
+            self.move_cursor(game);
+            self.end();
+        }
+
+        fn draw_palette(&mut self, game: &Game, line: &str) {
+            self.begin();
+            self.message_expires = None;
+
+            // The cursor stays at the end of what has been typed
+            self.screen.clear_line(game.layout.status);
+            let x = self.screen.print(0, game.layout.status, format_args!(":{}", line), ContentStyle::default());
+            self.screen.show_cursor(Some((x, game.layout.status)));
+
+            self.end();
+        }
+
+        fn move_cursor(&mut self, game: &Game) {
+            self.begin();
+
+            let shown = game.on_screen(game.pos).unwrap_or(game.pos);
+            let to = if game.overview {
+                (shown.0 / 2 + 1, shown.1 / 4 + game.layout.board_top)
+            } else {
+                game.layout.tile_at(&shown)
+            };
+
+            self.screen.show_cursor(Some(to));
+
+            self.end();
+        }
+
+        fn finish(&mut self, game: &Game) {
+            self.begin();
+
+            if game.board.won.is_some() {
+                let mut lines = vec![];
+
+                if game.settings.show_score {
+                    lines.push(Score::of(&game.board, game.clock.seconds()).to_string());
+                }
+
+                if let Some(turns) = &game.hotseat {
+                    lines.push(turns.result().to_string());
+                }
+
+                if let Some(bot) = &game.bot {
+                    lines.push(bot.race(game.board.won == Some(true), game.clock.seconds()).to_string());
+                }
+
+                let (found, total) = game.board.treasure();
+
+                if total > 0 {
+                    lines.push(format!("Treasure: {} of {} found, {} seconds off", found, total, found as u64 * game::TREASURE_BONUS));
+                }
+
+                if game.board.lie_chance > 0 {
+                    lines.push(format!("Lies: {} of the numbers were off by one", game.board.lies()));
+                }
+
+                if let Some(rush) = &game.rush {
+                    lines.push(format!("Rush: {} scrolled with {} left", rush.rows_text(), campaign::lives_text(rush.lives)));
+                }
+
+                // Lines past the bottom push the board up, as printing them would, and the shell's prompt gets the
+                // line after them
+                let scrolled = (game.layout.status as usize + lines.len() + 1).saturating_sub(self.screen.height() as usize);
+                let top = game.layout.status.saturating_sub(scrolled as u16);
+                self.screen.scroll(scrolled as u16);
+
+                self.screen.clear_line(top);
+
+                for (n, line) in lines.iter().enumerate() {
+                    self.screen.clear_line(top + n as u16);
+                    self.screen.print(0, top + n as u16, line, ContentStyle::default());
+                }
+
+                self.screen.show_cursor(Some((0, top + lines.len() as u16)));
+            } else {
+                self.screen.clear();
+                self.screen.show_cursor(Some((0, 0)));
+            }
+
+            self.end();
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn history_lines() {
+            let step = Step { time: 62500, update: PushState::Uncover, x: 4, y: 2 };
+
+            assert_eq!(history_line(&step, 12, false), "01:02 uncover (5, 3), opened 12");
+            assert_eq!(history_line(&step, 0, true), "01:02 uncover (5, 3), hit a mine");
+            assert_eq!(history_line(&Step { update: PushState::Flag, ..step }, 0, false), "01:02 flag (5, 3)");
+        }
+    }
+}
+
+// Controllers, read on their own thread and turned into the same actions the keyboard gives
+#[cfg(feature = "gamepad")]
+mod gamepad {
+    use gilrs::{Axis, Button, EventType, Gilrs};
+    use std::mem;
+
+    use super::*;
+
+    // How far a stick has to be pushed before it counts
+    const DEADZONE: f32 = 0.5;
+
+    pub fn launch(tx: mpsc::Sender<Message>) {
+        thread::spawn(move || {
+            // Without controller support on the system the game carries on with just the keyboard
+            let mut gilrs = match Gilrs::new() {
+                Ok(i) => i,
+                Err(_) => return,
+            };
+
+            let mut stick = Stick::default();
+
+            while let Some(event) = gilrs.next_event_blocking(None) {
+                let action = match event.event {
+                    EventType::ButtonPressed(i, _) => button(i),
+                    EventType::AxisChanged(axis, value, _) => stick.push(axis, value),
+                    _ => None,
+                };
+
+                if let Some(i) = action {
+                    if tx.send(Message::Pad(i)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    fn button(button: Button) -> Option<Action> {
+        match button {
+            Button::South => Some(Action::Uncover),
+            Button::East => Some(Action::Flag),
+            Button::DPadLeft => Some(Action::Move(Motion::Left)),
+            Button::DPadRight => Some(Action::Move(Motion::Right)),
+            Button::DPadUp => Some(Action::Move(Motion::Up)),
+            Button::DPadDown => Some(Action::Move(Motion::Down)),
+            _ => None,
+        }
+    }
+
+    // The left stick moves the cursor once each time it is pushed past the deadzone, like a d-pad press
+    #[derive(Default)]
+    struct Stick {
+        x: bool,
+        y: bool,
+    }
+
+    impl Stick {
+        fn push(&mut self, axis: Axis, value: f32) -> Option<Action> {
+            let (pushed, motion) = match axis {
+                Axis::LeftStickX => (&mut self.x, if value > 0.0 { Motion::Right } else { Motion::Left }),
+                Axis::LeftStickY => (&mut self.y, if value > 0.0 { Motion::Up } else { Motion::Down }),
+                _ => return None,
+            };
+
+            let held = mem::replace(pushed, value.abs() >= DEADZONE);
+
+            if *pushed && !held {
+                Some(Action::Move(motion))
+            } else {
+                None
+            }
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn pad_actions() {
+            assert_eq!(button(Button::South), Some(Action::Uncover));
+            assert_eq!(button(Button::Start), None);
+
+            let mut stick = Stick::default();
+            assert_eq!(stick.push(Axis::LeftStickY, 0.3), None);
+            assert_eq!(stick.push(Axis::LeftStickY, 0.8), Some(Action::Move(Motion::Up)));
+            assert_eq!(stick.push(Axis::LeftStickY, 1.0), None);
+            assert_eq!(stick.push(Axis::LeftStickY, 0.0), None);
+            assert_eq!(stick.push(Axis::LeftStickX, -0.9), Some(Action::Move(Motion::Left)));
+        }
+    }
+}
+
+// Diagnostic events written to a file as the game runs, since nothing can be printed while it has the terminal
+#[cfg(feature = "log")]
+mod log {
+    use serde_json::{json, Value};
+    use std::fs::File;
+    use std::io::{LineWriter, Write};
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    static LOG: Mutex<Option<(LineWriter<File>, Instant)>> = Mutex::new(None);
+
+    pub fn open(path: &str) -> Result<(), String> {
+        let file = File::create(path).map_err(|e| format!("could not open the log {}: {}", path, e))?;
+        *LOG.lock().unwrap() = Some((LineWriter::new(file), Instant::now()));
+        Ok(())
+    }
+
+    // Does nothing until a log is opened. Failing to write is ignored, since there is nowhere to report it
+    pub fn event(name: &str, fields: Value) {
+        if let Some((file, started)) = LOG.lock().unwrap().as_mut() {
+            let _ = writeln!(file, "{}", line(name, fields, started.elapsed().as_millis() as u64));
+        }
+    }
+
+    // The event and milliseconds since the log was opened, followed by the fields
+    fn line(name: &str, fields: Value, time: u64) -> Value {
+        let mut out = json!({ "event": name, "time": time });
+
+        if let (Some(out), Value::Object(fields)) = (out.as_object_mut(), fields) {
+            out.extend(fields);
+        }
+
+        out
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn log_lines() {
+            let out = line("move", json!({ "x": 3, "y": 4 }), 120);
+            assert_eq!(out.to_string(), r#"{"event":"move","time":120,"x":3,"y":4}"#);
+        }
+    }
+}
+
+// Every recorded game is also kept in a SQLite database next to the stats, with its 3BV and when it was played
+#[cfg(feature = "sqlite")]
+mod history {
+    use super::*;
+    use rusqlite::{params, Connection};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn default_path() -> Option<PathBuf> {
+        stats::default_path().map(|i| i.with_file_name("history.sqlite"))
+    }
+
+    pub fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |i| i.as_secs())
+    }
+
+    pub fn open(path: &Path) -> Result<Connection, String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        let connection = Connection::open(path).map_err(|e| e.to_string())?;
+        create(&connection)?;
+        Ok(connection)
+    }
+
+    fn create(connection: &Connection) -> Result<(), String> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS games (
+                id INTEGER PRIMARY KEY,
+                played_at INTEGER NOT NULL,
+                won INTEGER NOT NULL,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                mines INTEGER NOT NULL,
+                mode TEXT NOT NULL,
+                seconds INTEGER NOT NULL,
+                seed TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                three_bv INTEGER NOT NULL
+            );"
+        ).map_err(|e| e.to_string())
+    }
+
+    // Seeds are kept as text, since they use all 64 bits and SQLite integers are signed
+    pub fn record(connection: &Connection, record: &stats::Record, three_bv: usize, played_at: u64) -> Result<(), String> {
+        connection.execute(
+            "INSERT INTO games (played_at, won, width, height, mines, mode, seconds, seed, score, three_bv) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                played_at as i64,
+                record.won,
+                record.width as i64,
+                record.height as i64,
+                record.mines as i64,
+                record.mode(),
+                record.seconds as i64,
+                record.seed.to_string(),
+                record.score as i64,
+                three_bv as i64,
+            ],
+        ).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    // The games of the last few days on each board, with how fast the wins cleared the board in 3BV a second.
+    // Assisted games are left out
+    pub fn summary(connection: &Connection, days: u64, now: u64) -> Result<String, String> {
+        let mut query = connection.prepare(
+            "SELECT width, height, mines, COUNT(*), SUM(won),
+                MAX(CASE WHEN won AND seconds > 0 THEN three_bv * 1.0 / seconds END),
+                AVG(CASE WHEN won AND seconds > 0 THEN three_bv * 1.0 / seconds END)
+            FROM games WHERE played_at >= ?1 AND mode != 'assisted'
+            GROUP BY width, height, mines ORDER BY width * height, mines"
+        ).map_err(|e| e.to_string())?;
+
+        let since = now.saturating_sub(days * 86400) as i64;
+        let rows = query.query_map(params![since], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?, row.get::<_, i64>(4)?, row.get::<_, Option<f64>>(5)?, row.get::<_, Option<f64>>(6)?))
+        }).map_err(|e| e.to_string())?;
+
+        let rate = |i: Option<f64>| i.map_or(String::from("-"), |i| format!("{:.2}", i));
+        let mut text = format!("\nlast {} days\n{:<16}{:>7}{:>6}{:>8}{:>11}{:>11}\n", days, "board", "played", "won", "win%", "best 3BV/s", "avg 3BV/s");
+
+        for row in rows {
+            let (width, height, mines, played, won, best, average) = row.map_err(|e| e.to_string())?;
+
+            text.push_str(&format!(
+                "{:<16}{:>7}{:>6}{:>7.1}%{:>11}{:>11}\n",
+                format!("{}x{}, {} mines", width, height, mines),
+                played,
+                won,
+                won as f64 * 100.0 / played as f64,
+                rate(best),
+                rate(average),
+            ));
+        }
+
+        Ok(text)
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn history_summary() {
+            let connection = Connection::open_in_memory().unwrap();
+            create(&connection).unwrap();
+
+            let games = [("win 9x9 10 flags 20 7 800", 30, 1000), ("loss 9x9 10 flags 12 8", 10, 1000), ("win 9x9 10 flags 10 9 1200", 25, 1000), ("win 9x9 10 flags 5 10 900", 30, 0), ("win 9x9 10 assisted 5 11 900", 30, 1000)];
+
+            for (line, three_bv, played_at) in &games {
+                let game = stats::Record::parse(line).unwrap();
+                record(&connection, &game, *three_bv, *played_at).unwrap();
+            }
+
+            let summary = summary(&connection, 1, 86400 + 500).unwrap();
+            assert_eq!(summary.lines().nth(3), Some("9x9, 10 mines         3     2   66.7%       2.50       2.00"));
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+mod gui {
+    use super::*;
+    use board::Cell;
+    use macroquad::prelude as mq;
+
+    const TILE: f32 = 24.0;
+    const HEADER: f32 = 40.0;
+
+    const NUMBERS: [(u8, u8, u8); 8] = [
+        (0, 0, 255),
+        (0, 128, 0),
+        (255, 0, 0),
+        (0, 0, 128),
+        (128, 0, 0),
+        (0, 128, 128),
+        (0, 0, 0),
+        (128, 128, 128),
+    ];
+
+    pub fn run(board: Board) {
+        let conf = mq::Conf {
+            window_title: String::from("rs-minesweeper"),
+            window_width: (board.width() as f32 * TILE) as i32,
+            window_height: (board.height() as f32 * TILE + HEADER) as i32,
+            window_resizable: false,
+            ..Default::default()
+        };
+
+        macroquad::Window::from_config(conf, play(board));
+    }
+
+    async fn play(mut board: Board) {
+        let mut clock = Clock::new();
+
+        loop {
+            if mq::is_key_pressed(mq::KeyCode::Escape) {
+                return;
+            }
+
+            if mq::is_key_pressed(mq::KeyCode::R) {
+                // A seed that can't make a board of the same kind leaves the current one, and R can be pressed again
+                if let Ok(i) = board.regenerate(rand::random()) {
+                    board = i;
+                    clock = Clock::new();
+                }
+            }
+
+            if let (Some(pos), None) = (tile_at(mq::mouse_position(), &board), board.won) {
+                let update = if mq::is_mouse_button_pressed(mq::MouseButton::Left) {
+                    Some(PushState::Uncover)
+                } else if mq::is_mouse_button_pressed(mq::MouseButton::Right) {
+                    Some(PushState::Flag)
+                } else {
+                    None
+                };
+
+                if let Some(i) = update {
+                    board.push_state(pos.0, pos.1, i);
+
+                    match board.won {
+                        Some(_) => clock.stop(),
+                        None => clock.start(),
+                    }
+                }
+            }
+
+            mq::clear_background(mq::Color::from_rgba(192, 192, 192, 255));
+            draw_header(&board, &clock);
+
+            for y in 0..board.height() {
+                for x in 0..board.width() {
+                    draw_cell(x as f32 * TILE, y as f32 * TILE + HEADER, board.cell(x, y));
+                }
+            }
+
+            mq::next_frame().await;
+        }
+    }
+
+    fn tile_at(mouse: (f32, f32), board: &Board) -> Option<(usize, usize)> {
+        if mouse.0 < 0.0 || mouse.1 < HEADER {
+            return None;
+        }
+
+        let x = (mouse.0 / TILE) as usize;
+        let y = ((mouse.1 - HEADER) / TILE) as usize;
+
+        if x < board.width() && y < board.height() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    fn draw_header(board: &Board, clock: &Clock) {
+        let counter = format!("{:03}", cmp::max(board.mines_left(), -99));
+        let timer = format_time(clock.seconds());
+        let middle = match board.won {
+            Some(true) => String::from("YOU WON"),
+            Some(false) => String::from("YOU LOST"),
+            None => format!("{}%", board.progress()),
+        };
+
+        let width = board.width() as f32 * TILE;
+        let timer_width = mq::measure_text(&timer, None, 28, 1.0).width;
+        let middle_width = mq::measure_text(&middle, None, 24, 1.0).width;
+
+        draw_display(6.0, &counter);
+        draw_display(width - timer_width - 14.0, &timer);
+        mq::draw_text(&middle, (width - middle_width) / 2.0, 27.0, 24.0, mq::BLACK);
+    }
+
+    fn draw_display(x: f32, text: &str) {
+        let size = mq::measure_text(text, None, 28, 1.0);
+
+        mq::draw_rectangle(x, 6.0, size.width + 8.0, 28.0, mq::BLACK);
+        mq::draw_text(text, x + 4.0, 29.0, 28.0, mq::RED);
+    }
+
+    fn draw_cell(x: f32, y: f32, cell: Cell) {
+        let center = (x + TILE / 2.0, y + TILE / 2.0);
+
+        match cell {
+            Cell::Covered | Cell::Flagged => {
+                mq::draw_rectangle(x, y, TILE, TILE, mq::Color::from_rgba(192, 192, 192, 255));
+                mq::draw_line(x, y + 1.0, x + TILE, y + 1.0, 2.0, mq::WHITE);
+                mq::draw_line(x + 1.0, y, x + 1.0, y + TILE, 2.0, mq::WHITE);
+                mq::draw_line(x, y + TILE - 1.0, x + TILE, y + TILE - 1.0, 2.0, mq::GRAY);
+                mq::draw_line(x + TILE - 1.0, y, x + TILE - 1.0, y + TILE, 2.0, mq::GRAY);
+            },
+            Cell::Exploded => mq::draw_rectangle(x, y, TILE, TILE, mq::RED),
+            _ => {
+                mq::draw_rectangle(x, y, TILE, TILE, mq::Color::from_rgba(189, 189, 189, 255));
+                mq::draw_rectangle_lines(x, y, TILE, TILE, 1.0, mq::GRAY);
+            },
+        }
+
+        match cell {
+            Cell::Flagged => {
+                mq::draw_line(center.0 + 2.0, y + 5.0, center.0 + 2.0, y + TILE - 5.0, 2.0, mq::BLACK);
+                mq::draw_triangle(
+                    mq::vec2(center.0 + 2.0, y + 5.0),
+                    mq::vec2(center.0 + 2.0, center.1),
+                    mq::vec2(x + 5.0, y + 8.0),
+                    mq::RED,
+                );
+            },
+            Cell::Open(n) if n > 0 => {
+                let (r, g, b) = NUMBERS[n.min(8) - 1];
+                let text = n.to_string();
+                let size = mq::measure_text(&text, None, 24, 1.0);
+
+                mq::draw_text(&text, center.0 - size.width / 2.0, center.1 + size.height / 2.0, 24.0, mq::Color::from_rgba(r, g, b, 255));
+            },
+            Cell::Mine | Cell::Exploded => mq::draw_circle(center.0, center.1, TILE / 4.0, mq::BLACK),
+            Cell::WrongFlag => {
+                mq::draw_circle(center.0, center.1, TILE / 4.0, mq::BLACK);
+                mq::draw_line(x + 4.0, y + 4.0, x + TILE - 4.0, y + TILE - 4.0, 2.0, mq::RED);
+                mq::draw_line(x + TILE - 4.0, y + 4.0, x + 4.0, y + TILE - 4.0, 2.0, mq::RED);
+            },
+            _ => (),
+        }
+    }
+}
+
+mod solver {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use std::fmt;
+    use std::str::FromStr;
+
+    use super::*;
+    use board::{Cell, Visible};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Strategy {
+        Random,
+        SinglePoint,
+        Csp,
+    }
+
+    impl Strategy {
+        pub fn variants() -> [&'static str; 3] {
+            ["random", "single-point", "csp"]
+        }
+    }
+
+    impl fmt::Display for Strategy {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match *self {
+                Strategy::Random => "random",
+                Strategy::SinglePoint => "single-point",
+                Strategy::Csp => "csp",
+            };
+
+            write!(f, "{}", name)
+        }
+    }
+
+    impl FromStr for Strategy {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Strategy, String> {
+            match s.to_lowercase().as_str() {
+                "random" => Ok(Strategy::Random),
+                "single-point" => Ok(Strategy::SinglePoint),
+                "csp" => Ok(Strategy::Csp),
+                _ => Err(format!("unknown strategy: {}", s)),
+            }
+        }
+    }
+
+    // Every deduced tile keeps the reason it was first found for, so hints can explain themselves
+    #[derive(Debug, Default, PartialEq)]
+    pub struct Deductions {
+        pub safe: Vec<(usize, usize)>,
+        pub mines: Vec<(usize, usize)>,
+        reasons: Vec<((usize, usize), String)>,
+    }
+
+    impl Deductions {
+        pub fn is_empty(&self) -> bool {
+            self.safe.is_empty() && self.mines.is_empty()
+        }
+
+        pub fn reason(&self, cell: (usize, usize)) -> Option<&str> {
+            self.reasons.iter().find(|i| i.0 == cell).map(|i| i.1.as_str())
+        }
+
+        fn add(&mut self, cells: &[(usize, usize)], mines: bool, reason: String) {
+            let list = if mines { &mut self.mines } else { &mut self.safe };
+
+            for i in cells {
+                if !list.contains(i) {
+                    list.push(*i);
+                    self.reasons.push((*i, reason.clone()));
+                }
+            }
+        }
+    }
+
+    // The covered neighbors of an open number, and how many of them are still mines. In liar mode that is a range
+    // rather than an exact count
+    #[derive(Debug, PartialEq)]
+    struct Constraint {
+        at: (usize, usize),
+        number: usize,
+        cells: Vec<(usize, usize)>,
+        flags: usize,
+        least: usize,
+        most: usize,
+    }
+
+    impl Constraint {
+        // Coordinates are 1-based, like the rest of the user facing text
+        fn name(&self) -> String {
+            format!("the {} at ({}, {})", self.number, self.at.0 + 1, self.at.1 + 1)
+        }
+
+        fn needs(&self) -> String {
+            if self.least == self.most {
+                plural(self.least, "more mine")
+            } else {
+                format!("{} to {} more mines", self.least, self.most)
+            }
+        }
+    }
+
+    fn plural(n: usize, word: &str) -> String {
+        format!("{} {}{}", n, word, if n == 1 { "" } else { "s" })
+    }
+
+    // Deductions only ever look at the visible state, so they can't use anything a player wouldn't know
+    fn constraints(board: &Visible) -> Vec<Constraint> {
+        let mut out = vec![];
+
+        // With anti-mines, stacked mines or rows still to scroll in, numbers don't say how many of the tiles are
+        // mines, so nothing can be told from them
+        if !board.exact {
+            return out;
+        }
+
+        for y in 0..board.height {
+            for x in 0..board.width {
+                let number = match board.cell(x, y) {
+                    Cell::Open(i) => i,
+                    _ => continue,
+                };
+
+                let around = board.neighbors(x, y);
+                let flags = around.iter().filter(|i| board.cell(i.0, i.1) == Cell::Flagged).count();
+                let cells: Vec<(usize, usize)> = around.into_iter()
+                    .filter(|i| board.cell(i.0, i.1) == Cell::Covered)
+                    .collect();
+
+                let (least, most) = board.number_range(number);
+
+                if !cells.is_empty() && most >= flags {
+                    out.push(Constraint {
+                        at: (x, y),
+                        number: number,
+                        cells: cells,
+                        flags: flags,
+                        least: least.saturating_sub(flags),
+                        most: most - flags,
+                    });
+                }
+            }
+        }
+
+        out
+    }
+
+    pub fn single_point(board: &Visible) -> Deductions {
+        let mut found = Deductions::default();
+
+        let lying = if board.lie_chance > 0 { ", even if it is off by one" } else { "" };
+
+        for i in constraints(board) {
+            let flags = i.flags;
+
+            if i.most == 0 {
+                found.add(&i.cells, false, format!("{} already has {}{}, so its other neighbors are safe", i.name(), plural(flags, "flag"), lying));
+            } else if i.least == i.cells.len() {
+                let conclusion = if i.cells.len() == 1 { "it is a mine" } else { "they are all mines" };
+                let reason = match flags {
+                    0 => format!("{} has only {} left{}, so {}", i.name(), plural(i.cells.len(), "covered neighbor"), lying, conclusion),
+                    _ => format!("{} has {} and only {} left{}, so {}", i.name(), plural(flags, "flag"), plural(i.cells.len(), "covered neighbor"), lying, conclusion),
+                };
+
+                found.add(&i.cells, true, reason);
+            }
+        }
+
+        found
+    }
+
+    // Single point deductions, plus comparing every pair of constraints that share covered tiles. The mines a
+    // number still needs that can't fit outside the shared tiles have to be in them, and the most it can have there
+    // is its own count, which can settle the rest of the other number's tiles
+    pub fn csp(board: &Visible) -> Deductions {
+        let mut found = single_point(board);
+        let constraints = constraints(board);
+
+        for a in &constraints {
+            for b in &constraints {
+                let overlap = a.cells.iter().filter(|i| b.cells.contains(i)).count();
+                let rest: Vec<(usize, usize)> = b.cells.iter()
+                    .filter(|i| !a.cells.contains(i))
+                    .cloned()
+                    .collect();
+
+                if overlap == 0 || rest.is_empty() {
+                    continue;
+                }
+
+                let outside = a.cells.len() - overlap;
+                let shared = match outside {
+                    0 => format!("{} needs {} and all of its covered neighbors touch {}", a.name(), a.needs(), b.name()),
+                    1 => format!("{} needs {} and only 1 of its covered neighbors doesn't touch {}", a.name(), a.needs(), b.name()),
+                    n => format!("{} needs {} and only {} of its covered neighbors don't touch {}", a.name(), a.needs(), n, b.name()),
+                };
+
+                if b.most == a.least.saturating_sub(outside) {
+                    found.add(&rest, false, format!("{}, so the other neighbors of {} are safe", shared, b.name()));
+                } else if b.least.checked_sub(cmp::min(a.most, overlap)) == Some(rest.len()) {
+                    let conclusion = match rest.len() {
+                        1 => format!("the other neighbor of {} is a mine", b.name()),
+                        _ => format!("the other neighbors of {} are all mines", b.name()),
+                    };
+
+                    found.add(&rest, true, format!("{}, so {}", shared, conclusion));
+                }
+            }
+        }
+
+        found
+    }
+
+    // Frontiers with more covered tiles than this take too long to go through every way the mines could lie
+    const MAX_FRONTIER: usize = 24;
+
+    // The chance of every covered tile being a mine, in reading order, with None for every other tile
+    #[derive(Debug, PartialEq)]
+    pub struct Probabilities {
+        width: usize,
+        pub chances: Vec<Option<f64>>,
+        // The covered tiles next to numbers, in the order they were counted. Every other covered tile is as likely
+        // as the rest to be a mine
+        pub frontier: Vec<(usize, usize)>,
+    }
+
+    impl Probabilities {
+        pub fn get(&self, x: usize, y: usize) -> Option<f64> {
+            self.chances[y * self.width + x]
+        }
+
+        // The covered tile least likely to be a mine, the first in reading order when several are as likely
+        pub fn best(&self) -> Option<((usize, usize), f64)> {
+            self.chances.iter().enumerate()
+                .filter_map(|(i, chance)| chance.map(|p| ((i % self.width, i / self.width), p)))
+                .fold(None, |best, i| match best {
+                    Some((_, p)) if p <= i.1 => best,
+                    _ => Some(i),
+                })
+        }
+    }
+
+    // Goes through every way the mines can lie on the covered tiles next to numbers, weighting each by how many
+    // ways the rest of the mines fit on the other covered tiles, so the chances are exact rather than guessed from
+    // each number on its own. Only small frontiers are counted, which in practice means late in a game, and boards
+    // whose numbers can't be taken at their word have no exact chances at all
+    pub fn probabilities(board: &Visible) -> Option<Probabilities> {
+        if !board.exact || board.lie_chance > 0 || board.won.is_some() {
+            return None;
+        }
+
+        let constraints = constraints(board);
+        let mut frontier: Vec<(usize, usize)> = vec![];
+
+        for i in constraints.iter().flat_map(|i| i.cells.iter()) {
+            if !frontier.contains(i) {
+                frontier.push(*i);
+            }
+        }
+
+        if frontier.len() > MAX_FRONTIER {
+            return None;
+        }
+
+        let covered = board.cells.iter().filter(|i| **i == Cell::Covered).count();
+        let others = covered - frontier.len();
+        let left = board.mines.saturating_sub(board.flags);
+
+        let mut search = Search {
+            touching: frontier.iter()
+                .map(|f| (0..constraints.len()).filter(|c| constraints[*c].cells.contains(f)).collect())
+                .collect(),
+            needs: constraints.iter().map(|c| c.least).collect(),
+            mines: vec![0; constraints.len()],
+            open: constraints.iter().map(|c| c.cells.len()).collect(),
+            assigned: vec![false; frontier.len()],
+            left: left,
+            others: others,
+            ways: vec![0.0; frontier.len() + 1],
+            hits: vec![vec![0.0; frontier.len() + 1]; frontier.len()],
+        };
+
+        search.next(0, 0);
+
+        // Each way of placing k mines on the frontier counts as many times as the other left - k mines fit on the
+        // other tiles. Those counts are far too big to keep, so they are kept as logarithms relative to the largest
+        let weights: Vec<Option<f64>> = (0..=frontier.len())
+            .map(|k| if search.ways[k] > 0.0 { Some(ln_choose(others, left - k)) } else { None })
+            .collect();
+        let top = weights.iter().filter_map(|i| *i).fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = weights.iter().map(|i| i.map_or(0.0, |w| (w - top).exp())).collect();
+
+        let total: f64 = (0..=frontier.len()).map(|k| search.ways[k] * weights[k]).sum();
+
+        if total == 0.0 {
+            return None;
+        }
+
+        let elsewhere = if others == 0 {
+            0.0
+        } else {
+            (0..=frontier.len()).map(|k| search.ways[k] * weights[k] * left.saturating_sub(k) as f64 / others as f64).sum::<f64>() / total
+        };
+
+        let chances = board.cells.iter().enumerate()
+            .map(|(i, cell)| match cell {
+                Cell::Covered => Some(match frontier.iter().position(|f| *f == (i % board.width, i / board.width)) {
+                    Some(f) => (0..=frontier.len()).map(|k| search.hits[f][k] * weights[k]).sum::<f64>() / total,
+                    None => elsewhere,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Some(Probabilities { width: board.width, chances: chances, frontier: frontier })
+    }
+
+    fn ln_choose(n: usize, k: usize) -> f64 {
+        (0..k).map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln()).sum()
+    }
+
+    // The state of going through the frontier tile by tile, with every constraint keeping how many mines it has so
+    // far and how many of its tiles are still open, so a placement that can't work is dropped as soon as it is made
+    struct Search {
+        // The constraints every frontier tile is in
+        touching: Vec<Vec<usize>>,
+        needs: Vec<usize>,
+        mines: Vec<usize>,
+        open: Vec<usize>,
+        assigned: Vec<bool>,
+        left: usize,
+        others: usize,
+        // For every number of mines on the frontier, how many placements there are and how often each tile has one
+        ways: Vec<f64>,
+        hits: Vec<Vec<f64>>,
+    }
+
+    impl Search {
+        fn next(&mut self, tile: usize, placed: usize) {
+            if tile == self.assigned.len() {
+                if placed <= self.left && self.left - placed <= self.others {
+                    self.ways[placed] += 1.0;
+
+                    for (hits, _) in self.hits.iter_mut().zip(&self.assigned).filter(|i| *i.1) {
+                        hits[placed] += 1.0;
+                    }
+                }
+
+                return;
+            }
+
+            for &mine in &[false, true] {
+                if mine && placed == self.left {
+                    continue;
+                }
+
+                self.assigned[tile] = mine;
+
+                if self.place(tile, mine) {
+                    self.next(tile + 1, placed + mine as usize);
+                }
+
+                self.unplace(tile, mine);
+            }
+        }
+
+        // Returns whether every constraint the tile is in can still be met
+        fn place(&mut self, tile: usize, mine: bool) -> bool {
+            let mut fits = true;
+
+            for i in 0..self.touching[tile].len() {
+                let c = self.touching[tile][i];
+                self.open[c] -= 1;
+                self.mines[c] += mine as usize;
+                fits &= self.mines[c] <= self.needs[c] && self.mines[c] + self.open[c] >= self.needs[c];
+            }
+
+            fits
+        }
+
+        fn unplace(&mut self, tile: usize, mine: bool) {
+            for i in 0..self.touching[tile].len() {
+                let c = self.touching[tile][i];
+                self.open[c] += 1;
+                self.mines[c] -= mine as usize;
+            }
+        }
+    }
+
+    pub fn deduce(board: &Visible, strategy: Strategy) -> Deductions {
+        match strategy {
+            Strategy::Random => Deductions::default(),
+            Strategy::SinglePoint => single_point(board),
+            Strategy::Csp => csp(board),
+        }
+    }
+
+    pub fn apply(board: &mut Board, found: &Deductions) {
+        for &(x, y) in &found.mines {
+            if board.cell(x, y) == Cell::Covered {
+                board.push_state(x, y, PushState::Flag);
+            }
+        }
+
+        for &(x, y) in &found.safe {
+            if board.cell(x, y) == Cell::Covered {
+                board.push_state(x, y, PushState::Uncover);
+            }
+        }
+    }
+
+    pub struct Outcome {
+        pub won: bool,
+        pub guesses: usize,
+    }
+
+    // Makes a single move, for an opponent that plays at a set pace. Flags come before uncovers, and when the
+    // strategy is stuck it guesses the same way play does
+    pub fn step(board: &mut Board, strategy: Strategy, rng: &mut StdRng) {
+        if board.opened() == 0 {
+            board.push_state(board.width() / 2, board.height() / 2, PushState::Uncover);
+            return;
+        }
+
+        let found = deduce(&board.visible_state(), strategy);
+        let flags = if board.no_flag { &[][..] } else { &found.mines[..] };
+
+        let next = flags.iter().map(|i| (*i, PushState::Flag))
+            .chain(found.safe.iter().map(|i| (*i, PushState::Uncover)))
+            .find(|(i, _)| board.cell(i.0, i.1) == Cell::Covered);
+
+        let ((x, y), update) = match next {
+            Some(i) => i,
+            None => (guess(board, rng), PushState::Uncover),
+        };
+
+        board.push_state(x, y, update);
+    }
+
+    fn guess(board: &Board, rng: &mut StdRng) -> (usize, usize) {
+        let covered: Vec<(usize, usize)> = (0..board.height())
+            .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+            .filter(|i| board.cell(i.0, i.1) == Cell::Covered)
+            .collect();
+
+        *covered.choose(rng).unwrap()
+    }
+
+    // Plays a board to the end, starting in the middle and guessing at random whenever the strategy is stuck
+    pub fn play(board: &mut Board, strategy: Strategy, rng: &mut StdRng) -> Outcome {
+        let mut guesses = 0;
+        board.push_state(board.width() / 2, board.height() / 2, PushState::Uncover);
+
+        while board.won.is_none() {
+            let found = deduce(&board.visible_state(), strategy);
+
+            if found.is_empty() {
+                let (x, y) = guess(board, rng);
+                board.push_state(x, y, PushState::Uncover);
+                guesses += 1;
+                continue;
+            }
+
+            apply(board, &found);
+        }
+
+        Outcome { won: board.won == Some(true), guesses: guesses }
+    }
+
+    pub struct Solvability {
+        // Safe tiles that had to be uncovered when nothing was left to deduce, in the order it got stuck
+        pub guesses: Vec<(usize, usize)>,
+        pub deductions: usize,
+    }
+
+    // An opening if the board has one, since that is where players would want to start, or else the first safe tile
+    pub fn default_start(board: &Board) -> (usize, usize) {
+        let tiles: Vec<(usize, usize)> = (0..board.height())
+            .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+            .collect();
+
+        tiles.iter().find(|i| board.answer(i.0, i.1) == Cell::Open(0))
+            .or_else(|| tiles.iter().find(|i| board.answer(i.0, i.1) != Cell::Mine))
+            .cloned()
+            .unwrap_or((0, 0))
+    }
+
+    // Clears a board from the given tile with a strategy, and whenever that gets stuck uncovers a safe tile next
+    // to the numbers as the guess a player would have to make there. Knows where the mines are, so it can only
+    // be used to check boards, not to play them
+    pub fn solvability(board: &Board, start: (usize, usize), strategy: Strategy) -> Result<Solvability, String> {
+        let mut board = board.clone();
+
+        board.push_state(start.0, start.1, PushState::Uncover);
+
+        if board.won == Some(false) {
+            return Err(format!("({}, {}) is a mine", start.0 + 1, start.1 + 1));
+        }
+
+        Ok(solvability_from(&board, strategy))
+    }
+
+    // Plays the rest of a board from where it is, uncovering a safe tile whenever the strategy is stuck
+    pub fn solvability_from(board: &Board, strategy: Strategy) -> Solvability {
+        let mut board = board.clone();
+        let mut guesses = vec![];
+        let mut deductions = 0;
+
+        while board.won.is_none() {
+            let visible = board.visible_state();
+            let found = deduce(&visible, strategy);
+
+            if found.is_empty() {
+                let safe: Vec<(usize, usize)> = (0..board.height())
+                    .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+                    .filter(|i| visible.cell(i.0, i.1) == Cell::Covered && board.answer(i.0, i.1) != Cell::Mine)
+                    .collect();
+
+                let frontier = safe.iter().find(|i| {
+                    visible.neighbors(i.0, i.1).iter().any(|n| matches!(visible.cell(n.0, n.1), Cell::Open(_) | Cell::Negative(_)))
+                });
+
+                let (x, y) = *frontier.or_else(|| safe.first()).unwrap();
+                board.push_state(x, y, PushState::Uncover);
+                guesses.push((x, y));
+                continue;
+            }
+
+            deductions += found.safe.len() + found.mines.len();
+            apply(&mut board, &found);
+        }
+
+        Solvability { guesses: guesses, deductions: deductions }
+    }
+
+    // What the solver makes of a position: the tiles it can work out, the chance of a mine on each covered tile
+    // next to the numbers, least likely first, and whether the rest of the board needs any guesses
+    pub fn assess(board: &Board) -> String {
+        if board.won.is_some() {
+            return String::from("The game is over");
+        }
+
+        if board.progress() == 0 {
+            return String::from("Nothing is uncovered yet, so the first move has to be a guess");
+        }
+
+        let visible = board.visible_state();
+        let found = csp(&visible);
+        let tiles = |list: &[(usize, usize)]| match list.len() {
+            0 => String::from("none"),
+            _ => list.iter().map(|i| format!("({}, {})", i.0 + 1, i.1 + 1)).join(", "),
+        };
+
+        let mut lines = vec![format!("Safe: {}", tiles(&found.safe)), format!("Mines: {}", tiles(&found.mines))];
+
+        match probabilities(&visible) {
+            Some(chances) => {
+                let mut frontier: Vec<((usize, usize), f64)> = chances.frontier.iter()
+                    .map(|i| (*i, chances.get(i.0, i.1).unwrap()))
+                    .collect();
+                frontier.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(((a.0).1, (a.0).0).cmp(&((b.0).1, (b.0).0))));
+
+                lines.push(String::from("Chance of a mine:"));
+
+                for (at, p) in frontier {
+                    lines.push(format!("  {:<10}{:>6.1}%", format!("({}, {})", at.0 + 1, at.1 + 1), p * 100.0));
+                }
+
+                let elsewhere = chances.chances.iter().enumerate()
+                    .find(|(i, p)| p.is_some() && !chances.frontier.contains(&(i % board.width(), i / board.width())));
+
+                if let Some((_, Some(p))) = elsewhere {
+                    lines.push(format!("  {:<10}{:>6.1}%", "elsewhere", p * 100.0));
+                }
+            },
+            None if visible.exact && visible.lie_chance == 0 => {
+                lines.push(format!("Chance of a mine: more than {} tiles next to the numbers, too many to count", MAX_FRONTIER));
+            },
+            None => lines.push(String::from("Chance of a mine: the numbers don't say exactly how many mines they touch")),
+        }
+
+        lines.push(solvability_from(board, Strategy::Csp).describe("From here"));
+        lines.join("\n")
+    }
+
+    impl Solvability {
+        pub fn report(&self, start: (usize, usize)) -> String {
+            self.describe(&format!("Starting at ({}, {})", start.0 + 1, start.1 + 1))
+        }
+
+        // The report after whatever the board is being played from, like "From here"
+        pub fn describe(&self, from: &str) -> String {
+            let at = self.guesses.iter().map(|i| format!("({}, {})", i.0 + 1, i.1 + 1)).join(", ");
+
+            match self.guesses.len() {
+                0 => format!("{}, the board can be cleared without guessing ({})", from, plural(self.deductions, "deduction")),
+                1 => format!("{}, the board needs 1 forced guess at {}", from, at),
+                n => format!("{}, the board needs {} forced guesses, at {}", from, n, at),
+            }
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn solvability() {
+            // The two tiles on the left are a 50/50 that no amount of deduction gets through
+            let board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n").unwrap();
+            let start = default_start(&board);
+            let found = super::solvability(&board, start, Strategy::Csp).unwrap();
+
+            assert_eq!(start, (2, 0));
+            assert_eq!(found.guesses, vec![(0, 1)]);
+            assert_eq!(found.report(start), "Starting at (3, 1), the board needs 1 forced guess at (1, 2)");
+            assert!(super::solvability(&board, (0, 0), Strategy::Csp).is_err());
+
+            let board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n...\n").unwrap();
+            assert!(super::solvability(&board, default_start(&board), Strategy::Csp).unwrap().guesses.is_empty());
+        }
+
+        #[test]
+        fn deductions_are_sound() {
+            for seed in 0..20 {
+                for strategy in &[Strategy::SinglePoint, Strategy::Csp] {
+                    let mut test_board = Board::with_seed(16, 16, 40, seed).unwrap();
+                    test_board.push_state(8, 8, PushState::Uncover);
+
+                    loop {
+                        let found = deduce(&test_board.visible_state(), *strategy);
+
+                        if found.is_empty() || test_board.won.is_some() {
+                            break;
+                        }
+
+                        apply(&mut test_board, &found);
+
+                        assert_ne!(test_board.won, Some(false));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn liar_deductions_are_sound() {
+            for seed in 0..20 {
+                let mut test_board = Board::with_seed(16, 16, 40, seed).unwrap();
+                test_board.add_lies(30);
+                test_board.push_state(8, 8, PushState::Uncover);
+
+                let loaded = Board::from_save(&test_board.to_save()).unwrap();
+                assert_eq!(loaded.tiles, test_board.tiles);
+                assert_eq!(loaded.lie_chance, 30);
+
+                loop {
+                    let found = csp(&test_board.visible_state());
+
+                    if found.is_empty() || test_board.won.is_some() {
+                        break;
+                    }
+
+                    apply(&mut test_board, &found);
+
+                    assert_ne!(test_board.won, Some(false));
+                }
+            }
+        }
+
+        #[test]
+        fn deduction_reasons() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n..*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            test_board.push_state(0, 2, PushState::Uncover);
+            test_board.push_state(1, 0, PushState::Uncover);
+
+            let found = csp(&test_board.visible_state());
+            assert_eq!(found.mines[0], (0, 0));
+            assert_eq!(found.reason((0, 0)), Some("the 1 at (1, 2) has only 1 covered neighbor left, so it is a mine"));
+            assert_eq!(found.reason((2, 2)), Some("the 1 at (2, 1) needs 1 more mine and all of its covered neighbors touch the 2 at (2, 2), so the other neighbor of the 2 at (2, 2) is a mine"));
+
+            test_board.push_state(0, 0, PushState::Flag);
+
+            let found = csp(&test_board.visible_state());
+            assert!(found.safe.contains(&(2, 0)));
+            assert_eq!(found.reason((2, 0)), Some("the 1 at (2, 1) already has 1 flag, so its other neighbors are safe"));
+        }
+
+        #[test]
+        fn overlapping_constraints() {
+            let mut test_board = Board::from_save("rs-minesweeper save\nwidth 4\nfirst-uncover false\n\n..**\n...*\n").unwrap();
+            test_board.push_state(1, 1, PushState::Uncover);
+            test_board.push_state(2, 1, PushState::Uncover);
+
+            // Neither number's covered neighbors hold the other's, but the 1 can only put one mine where they meet
+            assert!(single_point(&test_board.visible_state()).is_empty());
+
+            let found = csp(&test_board.visible_state());
+            assert_eq!(found.mines, vec![(3, 0), (3, 1)]);
+            assert_eq!(found.safe, vec![(0, 0), (0, 1)]);
+            assert_eq!(found.reason((0, 1)), Some("the 3 at (3, 2) needs 3 more mines and only 2 of its covered neighbors don't touch the 1 at (2, 2), so the other neighbors of the 1 at (2, 2) are safe"));
+        }
+
+        #[test]
+        fn mine_chances() {
+            let layout = |rows: &str| Board::from_save(&format!("rs-minesweeper save\nwidth 4\nfirst-uncover false\n\n{}", rows)).unwrap();
+
+            // The 1 has one mine somewhere in its eight neighbors, and the other mine is on one of the three tiles
+            // past them
+            let mut test_board = layout("*...\n....\n...*\n");
+            test_board.push_state(1, 1, PushState::Uncover);
+
+            let found = probabilities(&test_board.visible_state()).unwrap();
+            assert!((found.get(0, 0).unwrap() - 0.125).abs() < 1e-9);
+            assert!((found.get(3, 1).unwrap() - 1.0 / 3.0).abs() < 1e-9);
+            assert_eq!(found.get(1, 1), None);
+            assert_eq!(found.best().unwrap().0, (0, 0));
+
+            // With only one mine on the board it has to be next to the 1, which makes the tiles past it safe
+            let mut test_board = layout("*...\n....\n....\n");
+            test_board.push_state(1, 1, PushState::Uncover);
+
+            let found = probabilities(&test_board.visible_state()).unwrap();
+            assert!(csp(&test_board.visible_state()).is_empty());
+            assert_eq!(found.best(), Some(((3, 0), 0.0)));
+        }
+
+        #[test]
+        fn assessment() {
+            let mut test_board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n").unwrap();
+            assert_eq!(assess(&test_board), "Nothing is uncovered yet, so the first move has to be a guess");
+
+            test_board.push_state(2, 0, PushState::Uncover);
+            assert_eq!(assess(&test_board).lines().collect::<Vec<_>>(), [
+                "Safe: none",
+                "Mines: none",
+                "Chance of a mine:",
+                "  (1, 1)      50.0%",
+                "  (1, 2)      50.0%",
+                "From here, the board needs 1 forced guess at (1, 2)",
+            ]);
+        }
+
+        #[test]
+        fn csp_finds_more() {
+            let mut single_wins = 0;
+            let mut csp_wins = 0;
+
+            for seed in 0..50 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                single_wins += play(&mut Board::with_seed(9, 9, 10, seed).unwrap(), Strategy::SinglePoint, &mut rng).won as usize;
+
+                let mut rng = StdRng::seed_from_u64(seed);
+                csp_wins += play(&mut Board::with_seed(9, 9, 10, seed).unwrap(), Strategy::Csp, &mut rng).won as usize;
+            }
+
+            assert!(single_wins > 0);
+            assert!(csp_wins >= single_wins);
+        }
+
+        #[test]
+        fn stepping() {
+            let mut rng = StdRng::seed_from_u64(3);
+            let mut test_board = Board::with_seed(9, 9, 10, 3).unwrap();
+            let mut moves = 0;
+
+            while test_board.won.is_none() {
+                let before = (test_board.opened(), test_board.flag_total);
+                step(&mut test_board, Strategy::Csp, &mut rng);
+
+                assert!(test_board.won.is_some() || (test_board.opened(), test_board.flag_total) != before);
+                moves += 1;
+            }
+
+            assert!(moves <= 81);
+        }
+    }
+}
+
+mod replay {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Step {
+        pub time: u64,
+        pub update: PushState,
+        pub x: usize,
+        pub y: usize,
+    }
+
+    // Replays only store how the board was generated, so they can't be made from loaded saves
+    #[derive(Debug, PartialEq)]
+    pub struct Replay {
+        pub width: usize,
+        pub height: usize,
+        pub mines: usize,
+        pub seed: u64,
+        pub no_flag: bool,
+        pub auto_flag: bool,
+        pub hardcore: bool,
+        pub strict_flags: bool,
+        pub treasure: usize,
+        pub lie_chance: usize,
+        pub anti_mines: usize,
+        pub stacked: usize,
+        pub line_hints: bool,
+        pub generation: Generation,
+        pub steps: Vec<Step>,
+    }
+
+    impl Replay {
+        pub fn board(&self) -> Result<Board, String> {
+            let mut board = Board::generate(self.width, self.height, self.mines, self.seed, self.generation)?;
+            board.no_flag = self.no_flag;
+            board.auto_flag = self.auto_flag;
+            board.hardcore = self.hardcore;
+            board.strict_flags = self.strict_flags;
+            board.place_treasure(self.treasure);
+            board.add_lies(self.lie_chance);
+            board.add_anti_mines(self.anti_mines);
+            board.stack_mines(self.stacked);
+            board.line_hints = self.line_hints;
+            Ok(board)
+        }
+
+        pub fn parse(text: &str) -> Result<Replay, String> {
+            let mut lines = seal::strip(text).lines();
+
+            if lines.next() != Some("rs-minesweeper replay") {
+                return Err(String::from("not a replay file"));
+            }
+
+            let mut width = None;
+            let mut height = None;
+            let mut mines = None;
+            let mut seed = None;
+            let mut no_flag = false;
+            let mut auto_flag = false;
+            let mut hardcore = false;
+            let mut strict_flags = false;
+            let mut treasure = 0;
+            let mut lie_chance = 0;
+            let mut anti_mines = 0;
+            let mut stacked = 0;
+            let mut line_hints = false;
+            let mut generation = Generation::default();
+
+            for line in lines.by_ref() {
+                if line.is_empty() {
+                    break;
+                }
+
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let invalid = || format!("invalid value for {}: {}", key, value);
+
+                match key {
+                    "width" => width = Some(value.parse().map_err(|_| invalid())?),
+                    "height" => height = Some(value.parse().map_err(|_| invalid())?),
+                    "mines" => mines = Some(value.parse().map_err(|_| invalid())?),
+                    "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                    "no-flag" => no_flag = value.parse().map_err(|_| invalid())?,
+                    "auto-flag" => auto_flag = value.parse().map_err(|_| invalid())?,
+                    "hardcore" => hardcore = value.parse().map_err(|_| invalid())?,
+                    "strict-flags" => strict_flags = value.parse().map_err(|_| invalid())?,
+                    "treasure" => treasure = value.parse().map_err(|_| invalid())?,
+                    "lie-chance" => lie_chance = value.parse().map_err(|_| invalid())?,
+                    "anti-mines" => anti_mines = value.parse().map_err(|_| invalid())?,
+                    "stacked-mines" => stacked = value.parse().map_err(|_| invalid())?,
+                    "line-hints" => line_hints = value.parse().map_err(|_| invalid())?,
+                    "no-border-mines" => generation.no_border_mines = value.parse().map_err(|_| invalid())?,
+                    "symmetry" => generation.symmetry = Some(value.parse().map_err(|_| invalid())?),
+                    "min-opening" => generation.min_opening = value.parse().map_err(|_| invalid())?,
+                    _ => return Err(format!("unknown field: {}", key)),
+                }
+            }
+
+            let missing = |field: &str| format!("missing field: {}", field);
+            let width = width.ok_or_else(|| missing("width"))?;
+            let height = height.ok_or_else(|| missing("height"))?;
+            let mut steps = vec![];
+
+            for (i, line) in lines.enumerate() {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                let invalid = || format!("invalid move {}: {}", i + 1, line);
+
+                if parts.len() != 4 {
+                    return Err(invalid());
+                }
+
+                let step = Step {
+                    time: parts[0].parse().map_err(|_| invalid())?,
+                    update: match parts[1] {
+                        "uncover" => PushState::Uncover,
+                        "flag" => PushState::Flag,
+                        _ => return Err(invalid()),
+                    },
+                    x: parts[2].parse().map_err(|_| invalid())?,
+                    y: parts[3].parse().map_err(|_| invalid())?,
+                };
+
+                if step.x >= width || step.y >= height {
+                    return Err(invalid());
+                }
+
+                steps.push(step);
+            }
+
+            Ok(Replay {
+                width: width,
+                height: height,
+                mines: mines.ok_or_else(|| missing("mines"))?,
+                seed: seed.ok_or_else(|| missing("seed"))?,
+                no_flag: no_flag,
+                auto_flag: auto_flag,
+                hardcore: hardcore,
+                strict_flags: strict_flags,
+                treasure: treasure,
+                lie_chance: lie_chance,
+                anti_mines: anti_mines,
+                stacked: stacked,
+                line_hints: line_hints,
+                generation: generation,
+                steps: steps,
+            })
+        }
+
+        pub fn to_text(&self) -> String {
+            let mut text = format!(
+                "rs-minesweeper replay\nwidth {}\nheight {}\nmines {}\nseed {}\nno-flag {}\nauto-flag {}\nhardcore {}\nstrict-flags {}\n",
+                self.width, self.height, self.mines, self.seed, self.no_flag, self.auto_flag, self.hardcore, self.strict_flags,
+            );
+
+            // Left out when not used, so older versions can still read the replay
+            if self.treasure > 0 {
+                text.push_str(&format!("treasure {}\n", self.treasure));
+            }
+
+            if self.lie_chance > 0 {
+                text.push_str(&format!("lie-chance {}\n", self.lie_chance));
+            }
+
+            if self.anti_mines > 0 {
+                text.push_str(&format!("anti-mines {}\n", self.anti_mines));
+            }
+
+            if self.stacked > 0 {
+                text.push_str(&format!("stacked-mines {}\n", self.stacked));
+            }
+
+            if self.line_hints {
+                text.push_str("line-hints true\n");
+            }
+
+            if self.generation.no_border_mines {
+                text.push_str("no-border-mines true\n");
+            }
+
+            if let Some(symmetry) = self.generation.symmetry {
+                text.push_str(&format!("symmetry {}\n", symmetry));
+            }
+
+            if self.generation.min_opening > 0 {
+                text.push_str(&format!("min-opening {}\n", self.generation.min_opening));
+            }
+
+            text.push('\n');
+
+            for step in &self.steps {
+                let update = match step.update {
+                    PushState::Uncover => "uncover",
+                    PushState::Flag => "flag",
+                };
+
+                text.push_str(&format!("{} {} {} {}\n", step.time, update, step.x, step.y));
+            }
+
+            text
+        }
+    }
+
+    // Speeds a replay can be played back at, changed with + and -
+    pub const SPEEDS: [f64; 6] = [0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+    // A copy of the board is kept every this many moves, so jumping to a move only has to play the ones
+    // since the copy before it
+    const CHECKPOINT: usize = 32;
+
+    pub struct Playback {
+        pub steps: Vec<Step>,
+        pub played: usize,
+        pub paused: bool,
+        // Notes on moves, by how many moves have been played when they're shown
+        pub notes: BTreeMap<usize, String>,
+        pub notes_path: Option<PathBuf>,
+        // The note being typed for the current move
+        pub writing: Option<String>,
+        speed: usize,
+        // How far into the recording playback is, in milliseconds of the original game
+        position: u64,
+        checkpoints: Vec<Board>,
+    }
+
+    impl Playback {
+        pub fn new(board: &Board, steps: Vec<Step>) -> Playback {
+            let mut board = board.clone();
+            let mut checkpoints = vec![];
+
+            for (i, step) in steps.iter().enumerate() {
+                if i % CHECKPOINT == 0 {
+                    checkpoints.push(board.clone());
+                }
+
+                if board.won.is_none() {
+                    board.push_state(step.x, step.y, step.update);
+                }
+            }
+
+            if steps.len() % CHECKPOINT == 0 {
+                checkpoints.push(board);
+            }
+
+            Playback {
+                steps: steps,
+                played: 0,
+                paused: false,
+                notes: BTreeMap::new(),
+                notes_path: None,
+                writing: None,
+                speed: 1,
+                position: 0,
+                checkpoints: checkpoints,
+            }
+        }
+
+        pub fn speed(&self) -> f64 {
+            SPEEDS[self.speed]
+        }
+
+        pub fn faster(&mut self) {
+            self.speed = cmp::min(self.speed + 1, SPEEDS.len() - 1);
+        }
+
+        pub fn slower(&mut self) {
+            self.speed = self.speed.saturating_sub(1);
+        }
+
+        pub fn position(&self) -> Duration {
+            Duration::from_millis(self.position)
+        }
+
+        pub fn finished(&self) -> bool {
+            self.played == self.steps.len()
+        }
+
+        // Moves playback on by some real time, returning how many moves should have been played by then if
+        // that changed
+        pub fn advance(&mut self, elapsed: Duration) -> Option<usize> {
+            if self.paused || self.finished() {
+                return None;
+            }
+
+            self.position += (elapsed.as_millis() as f64 * self.speed()) as u64;
+            let played = self.steps.iter().take_while(|i| i.time <= self.position).count();
+
+            if played != self.played {
+                Some(played)
+            } else {
+                None
+            }
+        }
+
+        // Jumps to just after the given number of moves, which also moves the position to when the last of
+        // them was made
+        pub fn seek(&mut self, played: usize) -> Board {
+            let played = cmp::min(played, self.steps.len());
+
+            self.played = played;
+            self.position = if played == 0 { 0 } else { self.steps[played - 1].time };
+
+            let mut board = self.checkpoints[played / CHECKPOINT].clone();
+
+            for step in &self.steps[played / CHECKPOINT * CHECKPOINT..played] {
+                if board.won.is_none() {
+                    board.push_state(step.x, step.y, step.update);
+                }
+            }
+
+            board
+        }
+
+        // The closest move before or after the current one with a note
+        pub fn next_note(&self, forward: bool) -> Option<usize> {
+            if forward {
+                self.notes.range(self.played + 1..).next().map(|i| *i.0)
+            } else {
+                self.notes.range(..self.played).next_back().map(|i| *i.0)
+            }
+        }
+
+        // Stores the note being written, where an empty one removes the move's note
+        pub fn finish_note(&mut self) -> std::io::Result<()> {
+            let text = match self.writing.take() {
+                Some(i) => i.trim().to_string(),
+                None => return Ok(()),
+            };
+
+            if text.is_empty() {
+                self.notes.remove(&self.played);
+            } else {
+                self.notes.insert(self.played, text);
+            }
+
+            match &self.notes_path {
+                Some(path) => fs::write(path, notes_text(&self.notes)),
+                None => Ok(()),
+            }
+        }
+
+        pub fn status(&self) -> String {
+            if let Some(text) = &self.writing {
+                return format!("Note on move {}: {}", self.played, text);
+            }
+
+            let width = 20;
+            let filled = if self.steps.is_empty() { width } else { self.played * width / self.steps.len() };
+            let state = if self.finished() { "End" } else if self.paused { "Paused" } else { "Playing" };
+
+            let mut status = format!(
+                "{} {}x [{}{}] {}/{}",
+                state, self.speed(), "#".repeat(filled), "-".repeat(width - filled), self.played, self.steps.len(),
+            );
+
+            if let Some(note) = self.notes.get(&self.played) {
+                status.push_str(&format!("  {}", note));
+            }
+
+            status
+        }
+    }
+
+    // Notes are kept next to the replay rather than in it, so adding them leaves the replay's checksum intact
+    pub fn notes_path(replay: &Path) -> PathBuf {
+        let mut name = replay.as_os_str().to_owned();
+        name.push(".notes");
+        PathBuf::from(name)
+    }
+
+    pub fn parse_notes(text: &str) -> Result<BTreeMap<usize, String>, String> {
+        let mut notes = BTreeMap::new();
+
+        for line in text.lines().filter(|i| !i.is_empty()) {
+            let (played, note) = line.split_once(' ')
+                .and_then(|(played, note)| Some((played.parse().ok()?, note)))
+                .ok_or_else(|| format!("invalid note: {}", line))?;
+
+            notes.insert(played, note.to_string());
+        }
+
+        Ok(notes)
+    }
+
+    pub fn notes_text(notes: &BTreeMap<usize, String>) -> String {
+        notes.iter().map(|i| format!("{} {}\n", i.0, i.1)).collect()
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn playback() {
+            let mut board = Board::with_seed(9, 9, 10, 7).unwrap();
+            let mut steps = vec![];
+
+            // Enough moves to need a few checkpoints, played on the tiles left covered so every move counts
+            for (i, (x, y)) in (0..9).flat_map(|y| (0..9).map(move |x| (x, y))).enumerate() {
+                steps.push(Step { time: i as u64 * 100, update: PushState::Flag, x: x, y: y });
+            }
+
+            board.push_state(0, 0, PushState::Uncover);
+            let mut playback = Playback::new(&board, steps.clone());
+            assert_eq!(playback.checkpoints.len(), 3);
+
+            let mut expected = board.clone();
+
+            for step in &steps[..70] {
+                expected.push_state(step.x, step.y, step.update);
+            }
+
+            assert_eq!(playback.seek(70), expected);
+            assert_eq!(playback.position(), Duration::from_millis(6900));
+            assert_eq!(playback.seek(0), board);
+
+            playback.faster();
+            assert_eq!(playback.advance(Duration::from_millis(1000)), Some(21));
+            playback.paused = true;
+            assert_eq!(playback.advance(Duration::from_millis(1000)), None);
+
+            playback.seek(12);
+            playback.writing = Some(String::from("a forced guess "));
+            playback.finish_note().unwrap();
+            playback.seek(40);
+            playback.writing = Some(String::from("50/50"));
+            playback.finish_note().unwrap();
+            assert_eq!(playback.next_note(false), Some(12));
+            assert_eq!(playback.next_note(true), None);
+            assert!(playback.status().ends_with("40/81  50/50"));
+
+            let text = notes_text(&playback.notes);
+            assert_eq!(text, "12 a forced guess\n40 50/50\n");
+            assert_eq!(parse_notes(&text), Ok(playback.notes.clone()));
+            assert!(parse_notes("twelve a note").is_err());
+
+            playback.writing = Some(String::new());
+            playback.finish_note().unwrap();
+            assert_eq!(playback.notes.len(), 1);
+        }
+
+        #[test]
+        fn replay_round_trip() {
+            let test_replay = Replay {
+                width: 9,
+                height: 9,
+                mines: 10,
+                seed: 42,
+                no_flag: false,
+                auto_flag: true,
+                hardcore: false,
+                strict_flags: false,
+                treasure: 3,
+                lie_chance: 10,
+                anti_mines: 2,
+                stacked: 4,
+                line_hints: true,
+                generation: Generation { no_border_mines: true, symmetry: Some(Symmetry::Rotational), min_opening: 12 },
+                steps: vec![
+                    Step { time: 0, update: PushState::Uncover, x: 4, y: 4 },
+                    Step { time: 1250, update: PushState::Flag, x: 0, y: 8 },
+                ],
+            };
+
+            assert_eq!(Replay::parse(&test_replay.to_text()), Ok(test_replay));
+            assert!(Replay::parse("rs-minesweeper replay\nwidth 9\nheight 9\nmines 10\nseed 1\n\n0 uncover 9 0\n").is_err());
+            assert!(Replay::parse("rs-minesweeper save\nwidth 9\n").is_err());
+        }
+    }
+}
+
+// Checksums on the last line of saves and replays, so files changed by hand can be told apart from the ones the game
+// wrote. The key is part of the source, so this keeps honest players honest rather than stopping a determined forger
+mod seal {
+    const KEY: &[u8] = b"rs-minesweeper seal";
+
+    // 64 bit FNV-1a over the key and the text
+    pub fn checksum(text: &str) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+        for byte in KEY.iter().chain(text.as_bytes()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+
+        format!("{:016x}", hash)
+    }
+
+    pub fn sign(text: &str) -> String {
+        format!("{}checksum {}\n", text, checksum(text))
+    }
+
+    // Splits the checksum line off the end of the text, if there is one
+    fn split(text: &str) -> (&str, Option<&str>) {
+        let trimmed = text.strip_suffix('\n').unwrap_or(text);
+
+        match trimmed.rfind('\n') {
+            Some(i) if trimmed[i + 1..].starts_with("checksum ") => (&text[..i + 1], Some(&trimmed[i + 10..])),
+            _ => (text, None),
+        }
+    }
+
+    pub fn strip(text: &str) -> &str {
+        split(text).0
+    }
+
+    // Returns the text without its checksum if the checksum matches
+    pub fn verify(text: &str) -> Result<&str, String> {
+        match split(text) {
+            (body, Some(sum)) if sum == checksum(body) => Ok(body),
+            (_, Some(_)) => Err(String::from("the checksum doesn't match, so it was changed after the game wrote it")),
+            (_, None) => Err(String::from("there is no checksum, so it wasn't written by the game")),
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn seal() {
+            let text = "rs-minesweeper replay\nseed 4\n\n120 uncover 3 4\n";
+            let signed = sign(text);
+
+            assert_eq!(strip(&signed), text);
+            assert_eq!(strip(text), text);
+            assert_eq!(verify(&signed), Ok(text));
+            assert!(verify(&signed.replace("120", "110")).is_err());
+            assert!(verify(text).is_err());
+        }
+    }
+}
+
+mod campaign {
+    use super::*;
+    use std::io::Write;
+
+    pub const LIVES: usize = 3;
+    // A reward can be picked after every this many levels
+    pub const REWARD_EVERY: usize = 3;
+    pub const TIME_REWARD: u64 = 30;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Reward {
+        Life,
+        Time,
+    }
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Campaign {
+        pub seed: u64,
+        pub level: usize,
+        pub lives: usize,
+        // Over every level so far, including lost and abandoned ones
+        pub seconds: u64,
+        // Tries at the current level, so that every try gets a new board
+        pub attempt: usize,
+    }
+
+    impl Campaign {
+        pub fn new(seed: u64) -> Campaign {
+            Campaign {
+                seed: seed,
+                level: 1,
+                lives: LIVES,
+                seconds: 0,
+                attempt: 0,
+            }
+        }
+
+        // Starts at beginner, then every level adds two columns, a row and a percent of mines, until the board
+        // fills the terminal and a quarter of it is mines
+        pub fn board(&self, terminal: (u16, u16)) -> (usize, usize, usize) {
+            let level = self.level - 1;
+            let width = cmp::min(9 + 2 * level, terminal.0.saturating_sub(2) as usize);
+            let height = cmp::min(9 + level, terminal.1.saturating_sub(5) as usize);
+            let density = (0.12 + 0.01 * level as f64).min(0.25);
+
+            (width, height, ((width * height) as f64 * density).max(10.0) as usize)
+        }
+
+        pub fn board_seed(&self) -> u64 {
+            self.seed.wrapping_add(self.level as u64 * 1000 + self.attempt as u64)
+        }
+
+        // Returns whether a reward is due
+        pub fn won(&mut self, seconds: u64) -> bool {
+            self.seconds += seconds;
+            self.level += 1;
+            self.attempt = 0;
+            (self.level - 1) % REWARD_EVERY == 0
+        }
+
+        pub fn lost(&mut self, seconds: u64) {
+            self.seconds += seconds;
+            self.lives -= 1;
+            self.attempt += 1;
+        }
+
+        // Leaving a level keeps the time spent on it, and it is played on a new board next time
+        pub fn quit(&mut self, seconds: u64) {
+            self.seconds += seconds;
+            self.attempt += 1;
+        }
+
+        pub fn reward(&mut self, reward: Reward) {
+            match reward {
+                Reward::Life => self.lives += 1,
+                Reward::Time => self.seconds = self.seconds.saturating_sub(TIME_REWARD),
+            }
+        }
+
+        pub fn parse(text: &str) -> Result<Campaign, String> {
+            let mut lines = text.lines();
+
+            if lines.next() != Some("rs-minesweeper campaign") {
+                return Err(String::from("not a campaign file"));
+            }
+
+            let mut campaign = Campaign::new(0);
+            let mut seed = None;
+
+            for line in lines {
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let invalid = || format!("invalid value for {}: {}", key, value);
+
+                match key {
+                    "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                    "level" => campaign.level = value.parse().ok().filter(|i| *i > 0).ok_or_else(invalid)?,
+                    "lives" => campaign.lives = value.parse().ok().filter(|i| *i > 0).ok_or_else(invalid)?,
+                    "seconds" => campaign.seconds = value.parse().map_err(|_| invalid())?,
+                    "attempt" => campaign.attempt = value.parse().map_err(|_| invalid())?,
+                    "" => (),
+                    _ => return Err(format!("unknown field: {}", key)),
+                }
+            }
+
+            campaign.seed = seed.ok_or_else(|| String::from("missing field: seed"))?;
+            Ok(campaign)
+        }
+
+        pub fn to_text(&self) -> String {
+            format!(
+                "rs-minesweeper campaign\nseed {}\nlevel {}\nlives {}\nseconds {}\nattempt {}\n",
+                self.seed, self.level, self.lives, self.seconds, self.attempt,
+            )
+        }
+    }
+
+    pub fn lives_text(lives: usize) -> String {
+        if lives == 1 { String::from("1 life") } else { format!("{} lives", lives) }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        stats::default_path().map(|i| i.with_file_name("campaign"))
+    }
+
+    pub fn save(path: &Path, campaign: &Campaign) -> Result<(), String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        fs::write(path, campaign.to_text()).map_err(|e| e.to_string())
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn campaign() {
+            let mut campaign = Campaign::new(7);
+            assert_eq!(campaign.board((200, 100)), (9, 9, 10));
+
+            assert!(!campaign.won(40));
+            campaign.lost(12);
+            assert_eq!((campaign.level, campaign.lives, campaign.seconds, campaign.attempt), (2, 2, 52, 1));
+            assert_ne!(campaign.board_seed(), Campaign { attempt: 0, ..campaign.clone() }.board_seed());
+
+            assert!(!campaign.won(20));
+            assert!(campaign.won(30));
+            campaign.reward(Reward::Time);
+            assert_eq!(campaign.seconds, 72);
+            assert_eq!(campaign.board((200, 100)), (15, 12, 27));
+            assert_eq!(campaign.board((20, 12)), (15, 7, 15));
+
+            campaign.level = 40;
+            assert_eq!(campaign.board((200, 100)), (87, 48, 1044));
+
+            assert_eq!(Campaign::parse(&campaign.to_text()), Ok(campaign));
+            assert_eq!(Campaign::parse("rs-minesweeper campaign\nlevel 2\n"), Err(String::from("missing field: seed")));
+            assert!(Campaign::parse("rs-minesweeper campaign\nseed 1\nlives 0\n").is_err());
+        }
+    }
+}
+
+// Small boards with one classic pattern each, for drilling them until they are recognized on sight
+mod trainer {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use std::collections::BTreeMap;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    pub struct Pattern {
+        pub id: &'static str,
+        pub name: &'static str,
+        // Uncovered tiles are o, and covered ones are * for a mine or . for a safe tile. Every covered tile can be
+        // worked out from the numbers
+        rows: &'static [&'static str],
+    }
+
+    pub const PATTERNS: [Pattern; 9] = [
+        Pattern { id: "1-2-1", name: "1-2-1", rows: &["ooo", "*.*"] },
+        Pattern { id: "1-2-2-1", name: "1-2-2-1", rows: &["oooo", ".**."] },
+        Pattern { id: "1-1-1", name: "1-1-1", rows: &["ooo", ".*."] },
+        Pattern { id: "1-1-edge", name: "1-1 from an edge", rows: &["oooo", "*..*"] },
+        Pattern { id: "1-2-edge", name: "1-2 from an edge", rows: &["oooo", "*.*."] },
+        Pattern { id: "2-2-1", name: "2-2-1 at an edge", rows: &["ooo", "**."] },
+        Pattern { id: "1-2-1-2-1", name: "1-2-1-2-1", rows: &["ooooo", "*.*.*"] },
+        Pattern { id: "1-1-gap", name: "1-1 with a gap", rows: &["oooooo", ".*..*."] },
+        Pattern { id: "corner", name: "Inside corner", rows: &["ooo", "oo*", "o*."] },
+    ];
+
+    // How many of the latest tries at a pattern count towards how well it is known
+    pub const RECENT: usize = 10;
+
+    impl Pattern {
+        // The pattern turned or mirrored one of 8 ways, so it isn't always seen from the same side
+        pub fn board(&self, orientation: usize) -> Board {
+            let mut rows: Vec<Vec<char>> = self.rows.iter().map(|i| i.chars().collect()).collect();
+
+            if orientation & 1 != 0 {
+                rows.iter_mut().for_each(|i| i.reverse());
+            }
+
+            if orientation & 2 != 0 {
+                rows.reverse();
+            }
+
+            if orientation & 4 != 0 {
+                rows = (0..rows[0].len()).map(|x| rows.iter().map(|i| i[x]).collect()).collect();
+            }
+
+            let text: String = rows.iter().map(|i| format!("{}\n", i.iter().collect::<String>())).collect();
+            Board::from_save(&format!("rs-minesweeper save\nwidth {}\nfirst-uncover false\n\n{}", rows[0].len(), text)).unwrap()
+        }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        stats::default_path().map(|i| i.with_file_name("trainer"))
+    }
+
+    // Every try as a line of the pattern and whether it was solved, oldest first
+    pub fn load(path: &Path) -> Result<Vec<(String, bool)>, String> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        text.lines().enumerate()
+            .filter(|i| !i.1.is_empty())
+            .map(|(n, line)| match line.split_once(' ') {
+                Some((id, "solved")) => Ok((id.to_string(), true)),
+                Some((id, "missed")) => Ok((id.to_string(), false)),
+                _ => Err(format!("invalid try on line {}: {}", n + 1, line)),
+            })
+            .collect()
+    }
+
+    pub fn append(path: &Path, id: &str, solved: bool) -> Result<(), String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| e.to_string())?;
+        writeln!(file, "{} {}", id, if solved { "solved" } else { "missed" }).map_err(|e| e.to_string())
+    }
+
+    // Picks patterns missed lately more often than the ones that are going well
+    pub fn pick<'a>(tries: &[(String, bool)], rng: &mut StdRng) -> &'a Pattern {
+        PATTERNS.choose_weighted(rng, |pattern| {
+            1 + tries.iter().rev().filter(|i| i.0 == pattern.id).take(RECENT).filter(|i| !i.1).count()
+        }).unwrap()
+    }
+
+    pub fn summary(tries: &[(String, bool)]) -> String {
+        let mut counts: BTreeMap<&str, Vec<bool>> = BTreeMap::new();
+
+        for (id, solved) in tries {
+            counts.entry(id.as_str()).or_insert_with(Vec::new).push(*solved);
+        }
+
+        let percent = |results: &[bool]| format!("{}%", results.iter().filter(|i| **i).count() * 100 / results.len());
+        let mut text = format!("{:<20}{:>7}{:>8}{:>9}\n", "pattern", "tries", "solved", "last 10");
+
+        for pattern in &PATTERNS {
+            if let Some(results) = counts.get(pattern.id) {
+                let recent = &results[results.len().saturating_sub(RECENT)..];
+                text.push_str(&format!("{:<20}{:>7}{:>8}{:>9}\n", pattern.name, results.len(), percent(results), percent(recent)));
+            }
+        }
+
+        text
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn patterns() {
+            // Every pattern has to come out the same however it is turned, and be solvable without a guess
+            for pattern in &PATTERNS {
+                for orientation in 0..8 {
+                    let mut board = pattern.board(orientation);
+
+                    while board.won.is_none() {
+                        let found = solver::csp(&board.visible_state());
+                        assert!(!found.is_empty(), "{} needs a guess", pattern.name);
+                        solver::apply(&mut board, &found);
+                    }
+
+                    assert_eq!(board.won, Some(true), "{} turned {} ways", pattern.name, orientation);
+                }
+            }
+
+            let tries = vec![(String::from("1-2-1"), true), (String::from("1-2-1"), false), (String::from("corner"), true)];
+            let summary = summary(&tries);
+            assert!(summary.contains("1-2-1                     2     50%      50%"));
+            assert!(summary.contains("Inside corner             1    100%     100%"));
+        }
+    }
+}
+
+// Boards made elsewhere, read into a grid of mines
+mod import {
+    use super::*;
+
+    // Anything past this is more than any terminal could show
+    pub const MAX_SIDE: usize = 1000;
+
+    pub fn board(bytes: &[u8], path: &Path) -> Result<Board, String> {
+        let mines = match path.extension().and_then(|i| i.to_str()) {
+            Some(i) if i.eq_ignore_ascii_case("mbf") => mbf(bytes)?,
+            _ => grid(std::str::from_utf8(bytes).map_err(|_| String::from("not a text file, and the name doesn't end in .mbf"))?)?,
+        };
+
+        let (width, height) = (mines[0].len(), mines.len());
+        let total = mines.iter().flatten().filter(|i| **i).count();
+
+        if width > MAX_SIDE || height > MAX_SIDE {
+            return Err(format!("the board is {}x{}, larger than {} on a side", width, height, MAX_SIDE));
+        }
+
+        if total == 0 || total == width * height {
+            return Err(String::from("the board needs at least one mine and one safe tile"));
+        }
+
+        Board::from_layout(width, &mines.concat())
+    }
+
+    // One line per row, with *, x or M for a mine and ., o, _ or a number for a safe tile. Numbers have to match
+    // the mines around them, which catches a grid that was copied wrong
+    fn grid(text: &str) -> Result<Vec<Vec<bool>>, String> {
+        let rows: Vec<&str> = text.lines().map(|i| i.trim()).filter(|i| !i.is_empty()).collect();
+        let mut mines = vec![];
+
+        if rows.is_empty() {
+            return Err(String::from("there is no board in the file"));
+        }
+
+        for (y, row) in rows.iter().enumerate() {
+            let line = row.chars().map(|c| match c {
+                '*' | 'x' | 'X' | 'M' => Ok(true),
+                '.' | 'o' | '_' | '0'..='8' => Ok(false),
+                _ => Err(format!("unknown tile '{}' on row {}", c, y + 1)),
+            }).collect::<Result<Vec<bool>, String>>()?;
+
+            if line.len() != rows[0].chars().count() {
+                return Err(format!("row {} is {} tiles long, but the first row is {}", y + 1, line.len(), rows[0].chars().count()));
+            }
+
+            mines.push(line);
+        }
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let shown = match c.to_digit(10) {
+                    Some(i) => i as usize,
+                    None => continue,
+                };
+
+                let around = (y.saturating_sub(1)..cmp::min(y + 2, mines.len()))
+                    .flat_map(|ny| (x.saturating_sub(1)..cmp::min(x + 2, mines[0].len())).map(move |nx| (nx, ny)))
+                    .filter(|&(nx, ny)| mines[ny][nx])
+                    .count();
+
+                if shown != around {
+                    return Err(format!("the {} at ({}, {}) has {} mines around it", shown, x + 1, y + 1, around));
+                }
+            }
+        }
+
+        Ok(mines)
+    }
+
+    // Minesweeper X and Arbiter boards: a byte each for the width and height, the number of mines as two bytes
+    // (high byte first), then the column and row of every mine as a byte each
+    fn mbf(bytes: &[u8]) -> Result<Vec<Vec<bool>>, String> {
+        let (width, height) = match bytes {
+            [w, h, ..] if *w > 0 && *h > 0 => (*w as usize, *h as usize),
+            _ => return Err(String::from("the file is too short to be a board")),
+        };
+
+        let count = match bytes.get(2..4) {
+            Some([high, low]) => (*high as usize) << 8 | *low as usize,
+            _ => return Err(String::from("the file is too short to be a board")),
+        };
+
+        if bytes.len() != 4 + count * 2 {
+            return Err(format!("the file has {} bytes, but a board with {} mines has {}", bytes.len(), count, 4 + count * 2));
+        }
+
+        let mut mines = vec![vec![false; width]; height];
+
+        for (n, mine) in bytes[4..].chunks(2).enumerate() {
+            let (x, y) = (mine[0] as usize, mine[1] as usize);
+
+            if x >= width || y >= height {
+                return Err(format!("mine {} at ({}, {}) is off the board", n + 1, x, y));
+            }
+
+            if mines[y][x] {
+                return Err(format!("there are two mines at ({}, {})", x, y));
+            }
+
+            mines[y][x] = true;
+        }
+
+        Ok(mines)
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn import_boards() {
+            let text_board = board(b"1*1..\n111..\n.....\n", Path::new("a.txt")).unwrap();
+            assert_eq!((text_board.width(), text_board.height(), text_board.mine_total), (5, 3, 1));
+            assert_eq!(text_board.answer(1, 0), Cell::Mine);
+
+            assert_eq!(grid("x2x\n.o.\n"), Ok(vec![vec![true, false, true], vec![false, false, false]]));
+            assert_eq!(grid("*2.\n...\n"), Err(String::from("the 2 at (2, 1) has 1 mines around it")));
+            assert_eq!(grid("*..\n..\n"), Err(String::from("row 2 is 2 tiles long, but the first row is 3")));
+            assert!(grid("*?.\n").is_err());
+
+            let mbf_board = board(&[3, 2, 0, 2, 0, 0, 2, 1], Path::new("b.MBF")).unwrap();
+            assert_eq!(mbf_board.to_string(), Board::from_layout(3, &[true, false, false, false, false, true]).unwrap().to_string());
+            assert_eq!(mbf(&[3, 2, 0, 2, 0, 0, 0, 0]), Err(String::from("there are two mines at (0, 0)")));
+            assert_eq!(mbf(&[3, 2, 0, 1, 3, 0]), Err(String::from("mine 1 at (3, 0) is off the board")));
+            assert!(mbf(&[3, 2, 0, 2, 0, 0]).is_err());
+            assert!(board(&[3, 2, 0, 0], Path::new("c.mbf")).is_err());
+        }
+    }
+}
+
+mod code {
+    use super::*;
+    use std::convert::TryFrom;
+
+    const VERSION: u8 = 1;
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    const NO_FLAG: u64 = 1;
+    const HARDCORE: u64 = 2;
+    const STRICT_FLAGS: u64 = 4;
+    const LINE_HINTS: u64 = 8;
+
+    // Everything needed to generate the same board again. It is written as a version byte and then the numbers
+    // as varints, in url safe base64 without padding so it can be pasted anywhere
+    #[derive(Debug, PartialEq)]
+    pub struct Code {
+        pub width: u16,
+        // The height of one layer
+        pub height: u16,
+        pub mines: u16,
+        pub layers: usize,
+        pub seed: u64,
+        pub no_flag: bool,
+        pub hardcore: bool,
+        pub strict_flags: bool,
+        pub line_hints: bool,
+    }
+
+    impl Code {
+        pub fn of(board: &Board) -> Code {
+            Code {
+                width: board.width() as u16,
+                height: board.layer_height() as u16,
+                mines: board.mine_total as u16,
+                layers: board.layers(),
+                seed: board.seed,
+                no_flag: board.no_flag,
+                hardcore: board.hardcore,
+                strict_flags: board.strict_flags,
+                line_hints: board.line_hints,
+            }
+        }
+
+        pub fn encode(&self) -> String {
+            let flags = [(self.no_flag, NO_FLAG), (self.hardcore, HARDCORE), (self.strict_flags, STRICT_FLAGS), (self.line_hints, LINE_HINTS)]
+                .iter()
+                .filter(|i| i.0)
+                .fold(0, |all, i| all | i.1);
+
+            let mut bytes = vec![VERSION];
+
+            for i in &[self.width as u64, self.height as u64, self.mines as u64, self.layers as u64, self.seed, flags] {
+                let mut n = *i;
+
+                while n >= 0x80 {
+                    bytes.push(n as u8 | 0x80);
+                    n >>= 7;
+                }
+
+                bytes.push(n as u8);
+            }
+
+            base64(&bytes)
+        }
+
+        pub fn decode(text: &str) -> Result<Code, String> {
+            let bytes = unbase64(text.trim())?;
+
+            match bytes.first() {
+                Some(&VERSION) => (),
+                Some(i) => return Err(format!("unknown version {}", i)),
+                None => return Err(String::from("the code is empty")),
+            }
+
+            let mut rest = bytes[1..].iter();
+            let mut next = || -> Result<u64, String> {
+                let mut n = 0u64;
+
+                for shift in (0..64).step_by(7) {
+                    let byte = *rest.next().ok_or_else(|| String::from("the code is cut short"))?;
+                    n |= ((byte & 0x7f) as u64) << shift;
+
+                    if byte & 0x80 == 0 {
+                        return Ok(n);
+                    }
+                }
+
+                Err(String::from("a number in the code is too long"))
+            };
+
+            let size = |n: u64| u16::try_from(n).map_err(|_| format!("{} is too large", n));
+            let (width, height, mines) = (size(next()?)?, size(next()?)?, size(next()?)?);
+            let (layers, seed, flags) = (next()? as usize, next()?, next()?);
+
+            if width == 0 || height == 0 || layers == 0 {
+                return Err(String::from("the board has no tiles"));
+            }
+
+            Ok(Code {
+                width: width,
+                height: height,
+                mines: mines,
+                layers: layers,
+                seed: seed,
+                no_flag: flags & NO_FLAG != 0,
+                hardcore: flags & HARDCORE != 0,
+                strict_flags: flags & STRICT_FLAGS != 0,
+                line_hints: flags & LINE_HINTS != 0,
+            })
+        }
+    }
+
+    fn base64(bytes: &[u8]) -> String {
+        let mut text = String::new();
+
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |all, (i, byte)| all | (*byte as u32) << (16 - 8 * i));
+
+            for i in 0..=chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            }
+        }
+
+        text
+    }
+
+    fn unbase64(text: &str) -> Result<Vec<u8>, String> {
+        let digits = text.chars()
+            .map(|c| ALPHABET.iter().position(|i| *i as char == c).ok_or_else(|| format!("'{}' can't be in a code", c)))
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        let mut bytes = vec![];
+
+        for chunk in digits.chunks(4) {
+            if chunk.len() == 1 {
+                return Err(String::from("the code is cut short"));
+            }
+
+            let n = chunk.iter().enumerate().fold(0u32, |all, (i, digit)| all | (*digit as u32) << (18 - 6 * i));
+            bytes.extend((0..chunk.len() - 1).map(|i| (n >> (16 - 8 * i)) as u8));
+        }
+
+        Ok(bytes)
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn board_codes() {
+            let mut board = Board::with_seed(30, 16, 99, 0xdead_beef_1234).unwrap();
+            board.no_flag = true;
+            board.line_hints = true;
+
+            let code = Code::of(&board);
+            let text = code.encode();
+
+            assert_eq!(text.len(), 18);
+            assert_eq!(Code::decode(&text), Ok(code));
+            assert_eq!(base64(b"rs-mine"), "cnMtbWluZQ");
+            assert_eq!(unbase64("cnMtbWluZQ"), Ok(b"rs-mine".to_vec()));
+
+            assert!(Code::decode("").is_err());
+            assert!(Code::decode("AR4Q").is_err());
+            assert!(Code::decode("not a code!").is_err());
+        }
+    }
+}
+
+mod daily {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    // Days are counted in UTC, so the puzzle changes at the same moment for everyone and a streak doesn't break
+    // or double up when the clocks change or the player travels
+    pub fn today() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |i| i.as_secs() / 86400)
+    }
+
+    pub fn seed(day: u64) -> u64 {
+        day.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ 0xda11
+    }
+
+    // The day as YYYY-MM-DD, from the civil calendar algorithm in Howard Hinnant's date library
+    pub fn date(day: u64) -> String {
+        let z = day as i64 + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z - era * 146097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let m = (5 * day_of_year + 2) / 153;
+        let month = if m < 10 { m + 3 } else { m - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        format!("{}-{:02}-{:02}", year, month, day_of_year - (153 * m + 2) / 5 + 1)
+    }
+
+    pub struct Daily {
+        pub day: u64,
+        pub current: usize,
+        pub best: usize,
+    }
+
+    impl Daily {
+        pub fn new(day: u64, days: &[u64]) -> Daily {
+            let (current, best) = streaks(days, day);
+            Daily { day: day, current: current, best: best }
+        }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        stats::default_path().map(|i| i.with_file_name("daily"))
+    }
+
+    // The days a daily puzzle was solved on, one number of days since 1970 per line
+    pub fn load(path: &Path) -> Result<Vec<u64>, String> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        text.lines().enumerate()
+            .filter(|i| !i.1.is_empty())
+            .map(|(n, i)| i.trim().parse().map_err(|_| format!("invalid day on line {}: {}", n + 1, i)))
+            .collect()
+    }
+
+    pub fn append(path: &Path, day: u64) -> Result<(), String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", day).map_err(|e| e.to_string())
+    }
+
+    // The current streak and the longest one. A streak that ended yesterday is still current, since today's
+    // puzzle can still be solved
+    pub fn streaks(days: &[u64], today: u64) -> (usize, usize) {
+        let days: Vec<u64> = days.iter().copied().sorted().dedup().collect();
+        let mut runs: Vec<(u64, usize)> = vec![];
+
+        for day in days {
+            match runs.last_mut() {
+                Some((last, length)) if *last + 1 == day => {
+                    *last = day;
+                    *length += 1;
+                },
+                _ => runs.push((day, 1)),
+            }
+        }
+
+        let current = runs.last().filter(|i| i.0 + 1 >= today).map_or(0, |i| i.1);
+        (current, runs.iter().map(|i| i.1).max().unwrap_or(0))
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn daily_streaks() {
+            assert_eq!(date(0), "1970-01-01");
+            assert_eq!(date(11016), "2000-02-29");
+            assert_eq!(date(20742), "2026-10-16");
+
+            let days = [100, 101, 102, 102, 110, 111, 105];
+            assert_eq!(streaks(&days, 111), (2, 3));
+            assert_eq!(streaks(&days, 112), (2, 3));
+            assert_eq!(streaks(&days, 113), (0, 3));
+            assert_eq!(streaks(&[], 113), (0, 0));
+
+            assert_ne!(seed(100), seed(101));
+        }
+    }
+}
+
+// The save files in a directory, for the saves browser
+mod saves {
+    use super::*;
+
+    pub struct Slot {
+        pub path: PathBuf,
+        // The size, time and progress of the board, or why it can't be loaded
+        pub summary: String,
+    }
+
+    impl Slot {
+        pub fn name(&self) -> String {
+            self.path.file_name().map_or(String::new(), |i| i.to_string_lossy().into_owned())
+        }
+    }
+
+    // Every .save file in the directory, by name
+    pub fn list(dir: &Path) -> Vec<Slot> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(i) => i.filter_map(|i| i.ok())
+                .map(|i| i.path())
+                .filter(|i| i.is_file() && i.extension().map_or(false, |e| e == "save"))
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        paths.sort();
+
+        paths.into_iter().map(|path| {
+            let summary = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|i| Board::from_save(&i)) {
+                Ok(board) => summary(&board),
+                Err(e) => format!("can't be loaded: {}", e),
+            };
+
+            Slot { path: path, summary: summary }
+        }).collect()
+    }
+
+    pub fn summary(board: &Board) -> String {
+        let size = match board.layers() {
+            1 => format!("{}x{}", board.width(), board.height()),
+            n => format!("{}x{}x{}", board.width(), board.layer_height(), n),
+        };
+
+        format!("{}, {} mines, {:02}:{:02}, {}% cleared", size, board.mines(), board.elapsed / 60, board.elapsed % 60, board.progress())
+    }
+
+    pub fn autosave_path() -> Option<PathBuf> {
+        stats::default_path().map(|i| i.with_file_name("autosave"))
+    }
+
+    // Writes to a temporary file first and renames it over the autosave, so a crash halfway through a write never
+    // leaves half a save behind
+    pub fn write_autosave(path: &Path, board: &Board) -> Result<(), String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, seal::sign(&board.to_save())).map_err(|e| e.to_string())?;
+        fs::rename(&temporary, path).map_err(|e| e.to_string())
+    }
+
+    // The autosave, if there is one for a board of this size
+    pub fn autosave(path: &Path, width: usize, height: usize, mines: usize, layers: usize) -> Option<Board> {
+        let board = fs::read_to_string(path).ok().and_then(|i| Board::from_save(&i).ok())?;
+
+        if (board.width(), board.layer_height(), board.mine_total, board.layers()) == (width, height, mines, layers) {
+            Some(board)
+        } else {
+            None
+        }
+    }
+
+    // Renames a save in its own directory, keeping the .save extension so it stays in the list
+    pub fn rename(path: &Path, name: &str) -> Result<PathBuf, String> {
+        if name.is_empty() || name.contains(std::path::is_separator) {
+            return Err(format!("{} is not a file name", name));
+        }
+
+        let target = if name.ends_with(".save") { path.with_file_name(name) } else { path.with_file_name(format!("{}.save", name)) };
+
+        if target.exists() {
+            return Err(format!("{} already exists", target.display()));
+        }
+
+        fs::rename(path, &target).map_err(|e| e.to_string())?;
+        Ok(target)
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn saves() {
+            let dir = std::env::temp_dir().join(format!("rs-minesweeper-saves-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+
+            let mut board = Board::with_seed(4, 3, 2, 1).unwrap();
+            board.elapsed = 75;
+            fs::write(dir.join("b.save"), board.to_save()).unwrap();
+            fs::write(dir.join("a.save"), "not a save").unwrap();
+            fs::write(dir.join("notes.txt"), "").unwrap();
+
+            let slots = list(&dir);
+            assert_eq!(slots.iter().map(|i| i.name()).collect::<Vec<_>>(), ["a.save", "b.save"]);
+            assert_eq!(slots[0].summary, "can't be loaded: not a save file");
+            assert_eq!(slots[1].summary, "4x3, 2 mines, 01:15, 0% cleared");
+
+            assert_eq!(rename(&slots[1].path, "c"), Ok(dir.join("c.save")));
+            assert!(rename(&dir.join("c.save"), "a").is_err());
+            assert!(rename(&dir.join("c.save"), "../c").is_err());
+
+            let kept = dir.join("data").join("autosave");
+            write_autosave(&kept, &board).unwrap();
+            assert_eq!(autosave(&kept, 4, 3, 2, 1).map(|i| i.tiles), Some(board.tiles));
+            assert!(autosave(&kept, 4, 3, 3, 1).is_none());
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+mod stats {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Record {
+        pub won: bool,
+        pub width: usize,
+        pub height: usize,
+        pub mines: usize,
+        pub no_flag: bool,
+        pub assisted: bool,
+        pub seconds: u64,
+        pub seed: u64,
+        pub score: u64,
+    }
+
+    impl Record {
+        pub fn from_board(board: &Board, seconds: u64) -> Record {
+            Record {
+                won: board.won == Some(true),
+                width: board.width(),
+                height: board.height(),
+                mines: board.mine_total,
+                no_flag: board.no_flag,
+                assisted: board.assisted,
+                seconds: seconds,
+                seed: board.seed,
+                score: Score::of(board, seconds).points,
+            }
+        }
+
+        pub fn parse(line: &str) -> Option<Record> {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+
+            // Records from before scores were kept have no score
+            if parts.len() != 6 && parts.len() != 7 {
+                return None;
+            }
+
+            let (width, height) = parts[1].split_once('x')?;
+
+            Some(Record {
+                won: match parts[0] {
+                    "win" => true,
+                    "loss" => false,
+                    _ => return None,
+                },
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+                mines: parts[2].parse().ok()?,
+                no_flag: match parts[3] {
+                    "no-flag" => true,
+                    "flags" | "assisted" => false,
+                    _ => return None,
+                },
+                assisted: parts[3] == "assisted",
+                seconds: parts[4].parse().ok()?,
+                seed: parts[5].parse().ok()?,
+                score: match parts.get(6) {
+                    Some(i) => i.parse().ok()?,
+                    None => 0,
+                },
+            })
+        }
+
+        pub fn mode(&self) -> &'static str {
+            match (self.no_flag, self.assisted) {
+                (_, true) => "assisted",
+                (true, false) => "no-flag",
+                (false, false) => "flags",
+            }
+        }
+
+        fn to_line(&self) -> String {
+            format!(
+                "{} {}x{} {} {} {} {} {}",
+                if self.won { "win" } else { "loss" },
+                self.width,
+                self.height,
+                self.mines,
+                self.mode(),
+                self.seconds,
+                self.seed,
+                self.score,
+            )
+        }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|i| Path::new(&i).join(".local").join("share")))
+            .map(|i| i.join("rs-minesweeper").join("stats"))
+    }
+
+    pub fn append(path: &Path, record: &Record) -> Result<(), String> {
+        if let Some(i) = path.parent() {
+            fs::create_dir_all(i).map_err(|e| e.to_string())?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", record.to_line()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<Record>, String> {
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        text.lines().enumerate()
+            .filter(|i| !i.1.is_empty())
+            .map(|(n, i)| Record::parse(i).ok_or_else(|| format!("invalid record on line {}: {}", n + 1, i)))
+            .collect()
+    }
+
+    // No-flag and assisted games are kept apart from normal ones, since they play so differently
+    pub fn summary(records: &[Record]) -> String {
+        let mut groups: BTreeMap<(usize, usize, usize, &str), Vec<&Record>> = BTreeMap::new();
+
+        for i in records {
+            groups.entry((i.width, i.height, i.mines, i.mode())).or_insert_with(Vec::new).push(i);
+        }
+
+        let mut text = format!("{:<16}{:<9}{:>7}{:>6}{:>8}{:>9}{:>9}\n", "board", "mode", "played", "won", "win%", "best", "average");
+
+        for ((width, height, mines, mode), games) in groups {
+            let wins: Vec<u64> = games.iter().filter(|i| i.won).map(|i| i.seconds).collect();
+            let best = wins.iter().min().map_or(String::from("-"), |i| format_time(*i));
+            let average = match wins.len() {
+                0 => String::from("-"),
+                n => format_time(wins.iter().sum::<u64>() / n as u64),
+            };
+
+            text.push_str(&format!(
+                "{:<16}{:<9}{:>7}{:>6}{:>7.1}%{:>9}{:>9}\n",
+                format!("{}x{}, {} mines", width, height, mines),
+                mode,
+                games.len(),
+                wins.len(),
+                wins.len() as f64 * 100.0 / games.len() as f64,
+                best,
+                average,
+            ));
+        }
+
+        text
+    }
+
+    // The times of the latest wins on each board, oldest first, as a sparkline where a lower bar is a faster
+    // game. Assisted wins are left out, and a board needs two wins before there is anything to compare
+    pub fn trends(records: &[Record], count: usize) -> String {
+        let mut groups: BTreeMap<(usize, usize, usize), Vec<u64>> = BTreeMap::new();
+
+        for i in records.iter().filter(|i| i.won && !i.assisted) {
+            groups.entry((i.width, i.height, i.mines)).or_insert_with(Vec::new).push(i.seconds);
+        }
+
+        groups.retain(|_, i| i.len() > 1);
+
+        if groups.is_empty() {
+            return String::new();
+        }
+
+        let mut text = format!("\n{:<16}{:<w$}{:>9}{:>9}\n", "board", "recent wins", "fastest", "slowest", w = count + 2);
+
+        for ((width, height, mines), times) in groups {
+            let times = &times[times.len().saturating_sub(count)..];
+
+            text.push_str(&format!(
+                "{:<16}{:<w$}{:>9}{:>9}\n",
+                format!("{}x{}, {} mines", width, height, mines),
+                sparkline(times),
+                format_time(*times.iter().min().unwrap()),
+                format_time(*times.iter().max().unwrap()),
+                w = count + 2,
+            ));
+        }
+
+        text
+    }
+
+    fn sparkline(values: &[u64]) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        let min = values.iter().min().copied().unwrap_or(0);
+        let range = values.iter().max().copied().unwrap_or(0) - min;
+
+        values.iter()
+            .map(|i| match range {
+                0 => BARS[3],
+                _ => BARS[((i - min) * 7 + range / 2) as usize / range as usize],
+            })
+            .collect()
+    }
+
+    // The games played since starting the program, given as how each ended and its time
+    pub fn session(games: &[(Option<bool>, u64)]) -> String {
+        let wins: Vec<u64> = games.iter().filter(|i| i.0 == Some(true)).map(|i| i.1).collect();
+        let total: u64 = games.iter().map(|i| i.1).sum();
+        let mut text = format!("This session: {} games played, {} won\n", games.len(), wins.len());
+
+        if let Some(i) = wins.iter().min() {
+            text.push_str(&format!("Best time: {}\n", format_time(*i)));
+        }
+
+        text.push_str(&format!("Total play time: {}:{:02}\n", total / 60, total % 60));
+        text
+    }
+
+    // The columns of an export. These only ever get added to at the end, so scripts reading them keep working
+    pub const COLUMNS: [&str; 8] = ["won", "width", "height", "mines", "mode", "seconds", "seed", "score"];
+
+    pub fn export_csv(records: &[Record]) -> String {
+        let mut text = format!("{}\n", COLUMNS.join(","));
+
+        for i in records {
+            text.push_str(&format!("{},{},{},{},{},{},{},{}\n", i.won, i.width, i.height, i.mines, i.mode(), i.seconds, i.seed, i.score));
+        }
+
+        text
+    }
+
+    pub fn export_json(records: &[Record]) -> String {
+        let games: Vec<serde_json::Value> = records.iter()
+            .map(|i| serde_json::json!({
+                "won": i.won,
+                "width": i.width,
+                "height": i.height,
+                "mines": i.mines,
+                "mode": i.mode(),
+                "seconds": i.seconds,
+                "seed": i.seed,
+                "score": i.score,
+            }))
+            .collect();
+
+        serde_json::to_string_pretty(&games).unwrap()
+    }
+
+    // The best scoring wins, highest first and the faster game first on a tie
+    pub fn leaderboard(records: &[Record], count: usize) -> String {
+        let mut wins: Vec<&Record> = records.iter().filter(|i| i.won && i.score > 0).collect();
+        wins.sort_by_key(|i| (cmp::Reverse(i.score), i.seconds));
+
+        if wins.is_empty() {
+            return String::new();
+        }
+
+        let mut text = format!("\n{:<6}{:<16}{:<9}{:>8}{:>9}  {}\n", "rank", "board", "mode", "score", "time", "seed");
+
+        for (n, i) in wins.iter().take(count).enumerate() {
+            text.push_str(&format!(
+                "{:<6}{:<16}{:<9}{:>8}{:>9}  {}\n",
+                n + 1,
+                format!("{}x{}, {} mines", i.width, i.height, i.mines),
+                i.mode(),
+                i.score,
+                format_time(i.seconds),
+                i.seed,
+            ));
+        }
+
+        text
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn stats_records() {
+            let records: Vec<Record> = ["win 9x9 10 flags 42 7 800", "loss 9x9 10 flags 12 8", "win 9x9 10 flags 30 9 1200", "win 9x9 10 no-flag 90 10", "win 9x9 10 assisted 20 11 900"]
+                .iter()
+                .map(|i| Record::parse(i).unwrap())
+                .collect();
+
+            assert_eq!(records[0].to_line(), "win 9x9 10 flags 42 7 800");
+            assert_eq!(records[1].to_line(), "loss 9x9 10 flags 12 8 0");
+            assert_eq!(Record::parse("win 9x9 10 flags 42"), None);
+
+            let summary = summary(&records);
+            let lines: Vec<&str> = summary.lines().collect();
+
+            assert_eq!(records[4].to_line(), "win 9x9 10 assisted 20 11 900");
+            assert_eq!(lines.len(), 4);
+            assert!(lines[1].starts_with("9x9, 10 mines   assisted       1     1  100.0%      020      020"));
+            assert!(lines[2].starts_with("9x9, 10 mines   flags          3     2   66.7%      030      036"));
+            assert!(lines[3].starts_with("9x9, 10 mines   no-flag        1     1  100.0%      090      090"));
+
+            let board = leaderboard(&records, 2);
+            assert_eq!(board.lines().skip(2).collect::<Vec<&str>>(), ["1     9x9, 10 mines   flags        1200      030  9", "2     9x9, 10 mines   assisted      900      020  11"]);
+
+            assert_eq!(sparkline(&[42, 30, 90, 20, 55]), "▃▂█▁▅");
+            assert_eq!(sparkline(&[30, 30]), "▄▄");
+
+            assert_eq!(session(&[(Some(true), 42), (Some(false), 12), (None, 80), (Some(true), 30)]), "This session: 4 games played, 2 won\nBest time: 030\nTotal play time: 2:44\n");
+            assert_eq!(session(&[(Some(false), 5), (None, 0)]), "This session: 2 games played, 0 won\nTotal play time: 0:05\n");
+
+            let csv = export_csv(&records[..2]);
+            assert_eq!(csv, "won,width,height,mines,mode,seconds,seed,score\ntrue,9,9,10,flags,42,7,800\nfalse,9,9,10,flags,12,8,0\n");
+
+            let json: serde_json::Value = serde_json::from_str(&export_json(&records)).unwrap();
+            assert_eq!(json[4], serde_json::json!({ "won": true, "width": 9, "height": 9, "mines": 10, "mode": "assisted", "seconds": 20, "seed": 11, "score": 900 }));
+
+            let trends = trends(&records, 2);
+            assert_eq!(trends.lines().skip(2).collect::<Vec<&str>>(), ["9x9, 10 mines   ▁█        030      090"]);
+        }
+    }
+}
+
+mod score {
+    use std::fmt;
+
+    use super::*;
+
+    // points = 100 * 3BV * 3BV/s * flag accuracy - 50 * mistakes, and never below zero. Only the 3BV that was
+    // actually cleared counts, flag accuracy is the share of flags that are on mines, and every flag taken
+    // back off counts as a mistake, as does hitting a mine
+    #[derive(Debug, PartialEq)]
+    pub struct Score {
+        pub three_bv: usize,
+        pub cleared: usize,
+        pub rate: f64,
+        pub accuracy: f64,
+        pub mistakes: usize,
+        pub points: u64,
+    }
+
+    impl Score {
+        pub fn of(board: &Board, seconds: u64) -> Score {
+            let (three_bv, cleared) = board.three_bv();
+            let rate = cleared as f64 / cmp::max(seconds, 1) as f64;
+            let accuracy = match board.flag_total {
+                0 => 1.0,
+                n => board.flags_on_mines() as f64 / n as f64,
+            };
+            let mistakes = board.flags_removed + (board.won == Some(false)) as usize;
+
+            let points = 100.0 * cleared as f64 * rate * accuracy - 50.0 * mistakes as f64;
+
+            Score {
+                three_bv: three_bv,
+                cleared: cleared,
+                rate: rate,
+                accuracy: accuracy,
+                mistakes: mistakes,
+                points: points.max(0.0).round() as u64,
+            }
+        }
+    }
+
+    impl fmt::Display for Score {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "Score: {} (3BV {}/{}, {:.2} 3BV/s, {:.0}% flag accuracy, {} mistake{})",
+                self.points,
+                self.cleared,
+                self.three_bv,
+                self.rate,
+                self.accuracy * 100.0,
+                self.mistakes,
+                if self.mistakes == 1 { "" } else { "s" },
+            )
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn score_formula() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n..*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            assert_eq!(test_board.three_bv(), (2, 0));
+
+            test_board.push_state(0, 2, PushState::Uncover);
+            test_board.push_state(2, 2, PushState::Flag);
+            test_board.push_state(1, 0, PushState::Flag);
+            test_board.push_state(1, 0, PushState::Flag);
+
+            let score = Score::of(&test_board, 1);
+            assert_eq!((score.three_bv, score.cleared, score.mistakes), (2, 1, 1));
+            assert_eq!(score.points, 50);
+            assert_eq!(score.to_string(), "Score: 50 (3BV 1/2, 1.00 3BV/s, 100% flag accuracy, 1 mistake)");
+
+            test_board.push_state(2, 0, PushState::Flag);
+            assert_eq!(Score::of(&test_board, 1).accuracy, 0.5);
+        }
+    }
+}
+
+mod rating {
+    use super::*;
+    use stats::Record;
+
+    pub const START: f64 = 800.0;
+    const K: f64 = 32.0;
+
+    // Roughly 830 for beginner boards, 1250 for intermediate and 1750 for expert. Denser boards count for
+    // the most, and bigger ones for a bit more since there is more to get wrong
+    pub fn difficulty(width: usize, height: usize, mines: usize) -> f64 {
+        let area = (width * height) as f64;
+        800.0 + 8000.0 * (mines as f64 / area - 0.12) + 100.0 * (area / 81.0).log2()
+    }
+
+    // Plays the rating forward through every game in the order they were recorded, treating each board
+    // as an opponent of its difficulty. Assisted games don't count
+    pub fn rating(records: &[Record]) -> f64 {
+        records.iter()
+            .filter(|i| !i.assisted)
+            .fold(START, |rating, i| {
+                let expected = 1.0 / (1.0 + 10f64.powf((difficulty(i.width, i.height, i.mines) - rating) / 400.0));
+                rating + K * (i.won as u8 as f64 - expected)
+            })
+    }
+
+    // A board of about the given difficulty, growing through the difficulty sizes as the rating goes up
+    pub fn propose(rating: f64) -> (u16, u16, u16) {
+        let (width, height, _) = if rating < 1100.0 {
+            Difficulty::Beginner.size()
+        } else if rating < 1550.0 {
+            Difficulty::Intermediate.size()
+        } else {
+            Difficulty::Expert.size()
+        };
+
+        let area = (width * height) as f64;
+        let density = 0.12 + (rating - 800.0 - 100.0 * (area / 81.0).log2()) / 8000.0;
+        let mines = (area * density.max(0.08).min(0.3)).round();
+
+        (width, height, mines as u16)
+    }
+
+    mod tests {
+        use super::*;
+
+        fn record(won: bool, size: (u16, u16, u16)) -> Record {
+            Record {
+                won: won,
+                width: size.0 as usize,
+                height: size.1 as usize,
+                mines: size.2 as usize,
+                no_flag: false,
+                assisted: false,
+                seconds: 60,
+                seed: 0,
+                score: 0,
+            }
+        }
+
+        #[test]
+        fn ratings() {
+            let beginner = Difficulty::Beginner.size();
+            let expert = Difficulty::Expert.size();
+
+            assert!(difficulty(beginner.0 as usize, beginner.1 as usize, beginner.2 as usize) < difficulty(expert.0 as usize, expert.1 as usize, expert.2 as usize));
+
+            let win = rating(&[record(true, beginner)]);
+            let expert_win = rating(&[record(true, expert)]);
+            let loss = rating(&[record(false, beginner)]);
+
+            assert!(win > START && expert_win > win && loss < START);
+
+            let mut assisted = record(true, expert);
+            assisted.assisted = true;
+            assert_eq!(rating(&[assisted]), START);
+
+            assert_eq!(propose(START), (9, 9, 10));
+
+            for i in &[900.0, 1300.0, 1800.0] {
+                let (width, height, mines) = propose(*i);
+                assert!((difficulty(width as usize, height as usize, mines as usize) - i).abs() < 50.0);
+            }
+        }
+    }
+}
+
+mod headless {
+    use super::*;
+    use game::Game;
+    use render::Renderer;
+    use serde_json::{json, Value};
+    use std::io::{stdin, BufRead};
+
+    // Reads commands in the same form as the : prompt from stdin, one per line, until the game ends
+    pub fn run(mut game: Game, json: bool) -> Game {
+        let mut renderer = Headless::new(json);
+        renderer.draw_frame(&game);
+
+        let stdin = stdin();
+
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(i) => i,
+                Err(_) => break,
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let running = match command::parse(&line) {
+                Ok(i) => game.handle(i, &mut renderer),
+                Err(e) => {
+                    renderer.flash_message(&game, &e);
+                    true
+                }
+            };
+
+            if !running {
+                return game;
+            }
+        }
+
+        game.handle(Action::Quit, &mut renderer);
+        game
+    }
+
+    pub struct Headless {
+        json: bool,
+        cells: Vec<Cell>,
+        steps: usize,
+        started: bool,
+        over: bool,
+    }
+
+    fn cell_name(cell: Cell) -> &'static str {
+        match cell {
+            Cell::Covered => "covered",
+            Cell::Flagged => "flagged",
+            Cell::Open(_) | Cell::Negative(_) => "open",
+            Cell::Mine => "mine",
+            Cell::WrongFlag => "wrong_flag",
+            Cell::Exploded => "exploded",
+        }
+    }
+
+    fn cell_char(cell: Cell) -> char {
+        match cell {
+            Cell::Covered => '#',
+            Cell::Flagged => 'F',
+            Cell::Open(0) => '.',
+            Cell::Open(i) => std::char::from_digit(i as u32, 36).unwrap().to_ascii_uppercase(),
+            Cell::Negative(_) => '-',
+            Cell::Mine => '*',
+            Cell::WrongFlag => 'X',
+            Cell::Exploded => '!',
+        }
+    }
+
+    impl Headless {
+        pub fn new(json: bool) -> Headless {
+            Headless {
+                json: json,
+                cells: vec![],
+                steps: 0,
+                started: false,
+                over: false,
+            }
+        }
+
+        fn emit(&self, event: Value) {
+            if self.json {
+                println!("{}", event);
+            }
+        }
+
+        fn print_board(&self, board: &Board) {
+            for row in self.cells.chunks(board.width()) {
+                println!("{}", row.iter().map(|i| cell_char(*i)).collect::<String>());
+            }
+        }
+
+        fn sync(&mut self, game: &Game) {
+            let old = self.cells.clone();
+
+            for event in self.events(game) {
+                self.emit(event);
+            }
+
+            if self.cells != old && !old.is_empty() && !self.json {
+                self.print_board(&game.board);
+                println!();
+            }
+        }
+
+        // Everything that changed since the last call
+        fn events(&mut self, game: &Game) -> Vec<Value> {
+            let board = &game.board;
+            let mut events = vec![];
+
+            if !self.started {
+                self.started = true;
+                self.cells = board.visible_state().cells;
+                events.push(json!({
+                    "event": "start",
+                    "width": board.width(),
+                    "height": board.height(),
+                    "mines": board.mines(),
+                    "no_flag": board.no_flag,
+                }));
+            }
+
+            for step in &game.steps[cmp::min(self.steps, game.steps.len())..] {
+                events.push(json!({
+                    "event": "move",
+                    "time": step.time,
+                    "action": match step.update {
+                        PushState::Uncover => "uncover",
+                        PushState::Flag => "flag",
+                    },
+                    "x": step.x,
+                    "y": step.y,
+                }));
+            }
+
+            self.steps = game.steps.len();
+
+            let cells = board.visible_state().cells;
+            let revealed: Vec<Value> = cells.iter().enumerate()
+                .filter(|i| self.cells.get(i.0) != Some(i.1))
+                .filter(|i| !matches!(i.1, Cell::Covered | Cell::Flagged))
+                .map(|(n, i)| match i {
+                    Cell::Open(number) => json!({ "x": n % board.width(), "y": n / board.width(), "cell": "open", "number": number }),
+                    Cell::Negative(number) => json!({ "x": n % board.width(), "y": n / board.width(), "cell": "open", "number": -(*number as i64) }),
+                    _ => json!({ "x": n % board.width(), "y": n / board.width(), "cell": cell_name(*i) }),
+                })
+                .collect();
+
+            self.cells = cells;
+
+            if !revealed.is_empty() {
+                events.push(json!({ "event": "reveal", "tiles": revealed }));
+            }
+
+            // A rewind in casual mode takes the game back from being over
+            if board.won.is_none() {
+                self.over = false;
+            }
+
+            if let (Some(won), false) = (board.won, self.over) {
+                self.over = true;
+                events.push(json!({ "event": "game_over", "won": won, "time": game.clock.seconds(), "seed": board.seed }));
+            }
+
+            events
+        }
+    }
+
+    impl Renderer for Headless {
+        fn draw_frame(&mut self, game: &Game) {
+            let first = !self.started;
+            self.sync(game);
+
+            if first && !self.json {
+                self.print_board(&game.board);
+                println!();
+            }
+        }
+
+        fn draw_header(&mut self, game: &Game) {
+            self.sync(game);
+        }
+
+        fn draw_board(&mut self, game: &Game) {
+            self.sync(game);
+        }
+
+        fn draw_tiles(&mut self, game: &Game, _: &[(u16, u16)]) {
+            self.sync(game);
+        }
+
+        fn flash_message(&mut self, _: &Game, message: &str) {
+            if message.is_empty() {
+                return;
+            }
+
+            if self.json {
+                self.emit(json!({ "event": "message", "text": message }));
+            } else {
+                println!("{}", message);
+            }
+        }
+
+        fn show_prompt(&mut self, game: &Game, message: &str) {
+            self.flash_message(game, message);
+        }
+
+        fn expire_message(&mut self, _: &Game) {}
+
+        fn show_error(&mut self, _: &Game, message: &str) {
+            if self.json {
+                self.emit(json!({ "event": "error", "text": message }));
+            } else {
+                println!("error: {}", message);
+            }
+        }
+
+        fn draw_palette(&mut self, _: &Game, _: &str) {}
+
+        fn move_cursor(&mut self, _: &Game) {}
+
+        fn draw_bot(&mut self, _: &Game) {}
+
+        fn draw_inventory(&mut self, _: &Game) {}
+
+        fn finish(&mut self, game: &Game) {
+            self.sync(game);
+
+            let board = &game.board;
+
+            if self.json {
+                self.emit(json!({
+                    "event": "stats",
+                    "won": board.won,
+                    "seconds": game.clock.seconds(),
+                    "moves": game.steps.len(),
+                    "progress": board.progress(),
+                    "flags": board.flag_total,
+                    "score": Score::of(board, game.clock.seconds()).points,
+                    "deaths": game.deaths,
+                }));
+            } else {
+                let result = match board.won {
+                    Some(true) => "won",
+                    Some(false) => "lost",
+                    None => "quit",
+                };
+
+                println!("{} after {} moves in {}, {}% cleared", result, game.steps.len(), format_time(game.clock.seconds()), board.progress());
+            }
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn headless_events() {
+            let mut game = game::tests::game(false);
+            let mut renderer = Headless::new(true);
+
+            let events = renderer.events(&game);
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0]["event"], "start");
+            assert!(events[0]["seed"].is_null());
+
+            game.pos = (2, 2);
+            game.handle(Action::Uncover, &mut Headless::new(true));
+
+            let events = renderer.events(&game);
+            assert_eq!(events[0]["event"], "move");
+            assert_eq!(events[0]["action"], "uncover");
+            assert_eq!(events[0]["x"], 2);
+            assert_eq!(events[1]["event"], "reveal");
+            assert!(events[1]["tiles"].as_array().unwrap().iter().any(|i| i["x"] == 2 && i["y"] == 2 && i["cell"] == "open"));
+            assert!(renderer.events(&game).is_empty());
+        }
+    }
+}
+
+mod spectate {
+    use super::*;
+    use game::Game;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    // Every frame is the board as the player sees it in plain text with colors, ended with a form feed
+    const END: u8 = b'\x0c';
+
+    // Sends the game to everyone watching. Spectators only ever read, and one that stops reading is dropped
+    pub struct Server {
+        clients: Arc<Mutex<Vec<TcpStream>>>,
+        last: Arc<Mutex<String>>,
+    }
+
+    impl Server {
+        pub fn bind(address: &str, port: u16) -> Result<Server, String> {
+            let listener = TcpListener::bind((address, port)).map_err(|e| e.to_string())?;
+            let clients = Arc::new(Mutex::new(vec![]));
+            let last = Arc::new(Mutex::new(String::new()));
+            let (accepted, shown) = (clients.clone(), last.clone());
+
+            // Newcomers get the latest frame straight away rather than waiting for the next move
+            thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    let frame = shown.lock().unwrap();
+
+                    if stream.set_write_timeout(Some(Duration::from_millis(200))).is_ok() && stream.write_all(frame.as_bytes()).is_ok() {
+                        accepted.lock().unwrap().push(stream);
+                    }
+                }
+            });
+
+            Ok(Server { clients: clients, last: last })
+        }
+
+        pub fn send(&self, game: &Game) {
+            let frame = frame(game);
+            let mut last = self.last.lock().unwrap();
+
+            if *last != frame {
+                self.clients.lock().unwrap().retain(|mut i| i.write_all(frame.as_bytes()).is_ok());
+                *last = frame;
+            }
+        }
+    }
+
+    pub fn frame(game: &Game) -> String {
+        let board = &game.board;
+        let border = &game.settings.theme.border;
+        let horizontal = border.horizontal.to_string().repeat(board.width());
+        let state = match board.won {
+            Some(true) => String::from("YOU WON"),
+            Some(false) => String::from("YOU LOST"),
+            None => format!("{}%", board.progress()),
+        };
+        let view = board.view(&game.settings.theme).layer(game.layer()).cursor(game.pos.0 as usize, game.pos.1 as usize);
+
+        format!(
+            "{} mines left   {}   {}\r\n{}{}{}\r\n{}\r\n{}{}{}{}",
+            board.mines_left(),
+            format_time(game.clock.seconds()),
+            state,
+            border.top_left,
+            horizontal,
+            border.top_right,
+            view,
+            border.bottom_left,
+            horizontal,
+            border.bottom_right,
+            END as char,
+        )
+    }
+
+    // Draws every frame sent by the game until it ends
+    pub fn watch(address: &str) -> Result<(), String> {
+        let mut reader = BufReader::new(TcpStream::connect(address).map_err(|e| e.to_string())?);
+        let mut frame = vec![];
+
+        loop {
+            frame.clear();
+
+            if reader.read_until(END, &mut frame).map_err(|e| e.to_string())? == 0 {
+                return Ok(());
+            }
+
+            frame.retain(|i| *i != END);
+
+            execute!(stdout(), cursor::MoveTo(0, 0), Clear(ClearType::All));
+            print!("{}", String::from_utf8_lossy(&frame));
+            std::io::stdout().flush().map_err(|e| e.to_string())?;
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn spectate_frames() {
+            let mut game = game::tests::game(false);
+            game.board.push_state(0, 0, PushState::Uncover);
+
+            let server = Server::bind("127.0.0.1", 0).unwrap();
+            server.send(&game);
+
+            let frame = frame(&game);
+            assert!(frame.starts_with(&format!("{} mines left   000   ", game.board.mines_left())));
+            assert!(frame.ends_with('\x0c'));
+            assert_eq!(frame.matches("\r\n").count(), 7);
+            assert_eq!(*server.last.lock().unwrap(), frame);
+        }
+    }
+}
+
+mod cast {
+    use super::*;
+    use serde_json::json;
+
+    // A recording in asciinema's v2 format: a header line, then one line per write to the terminal with the
+    // seconds since the start
+    pub struct Cast {
+        size: (u16, u16),
+        started: Instant,
+        events: Vec<(f64, String)>,
+    }
+
+    impl Cast {
+        pub fn new(size: (u16, u16)) -> Cast {
+            Cast {
+                size: size,
+                started: Instant::now(),
+                events: vec![],
+            }
+        }
+
+        pub fn push(&mut self, output: &[u8]) {
+            self.events.push((self.started.elapsed().as_secs_f64(), String::from_utf8_lossy(output).into_owned()));
+        }
+
+        pub fn to_text(&self) -> String {
+            let header = json!({ "version": 2, "width": self.size.0, "height": self.size.1, "title": "rs-minesweeper" });
+            let mut text = format!("{}\n", header);
+
+            for (time, output) in &self.events {
+                text.push_str(&format!("{}\n", json!([(time * 1000.0).round() / 1000.0, "o", output])));
+            }
+
+            text
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cast_text() {
+            let mut cast = Cast::new((80, 24));
+            cast.push(b"\x1b[2J1\r\n");
+            cast.events[0].0 = 0.12345;
+            cast.push("░".as_bytes());
+
+            let text = cast.to_text();
+            let lines: Vec<&str> = text.lines().collect();
+
+            assert_eq!(lines[0], r#"{"height":24,"title":"rs-minesweeper","version":2,"width":80}"#);
+            assert_eq!(lines[1], r#"[0.123,"o","\u001b[2J1\r\n"]"#);
+            assert!(lines[2].ends_with(r#","o","░"]"#));
+            assert_eq!(lines.len(), 3);
+        }
+    }
+}
+
+mod config {
+    use serde::Deserialize;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // Settings kept between runs, in $XDG_CONFIG_HOME/rs-minesweeper/config.toml
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    pub struct Config {
+        // false turns off --submit for good
+        pub submit: Option<bool>,
+        // How many tiles each repeat of a held move key goes, the last one carrying on for as long as it is held
+        pub acceleration: Option<Vec<u16>>,
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|i| Path::new(&i).join(".config")))
+            .map(|i| i.join("rs-minesweeper").join("config.toml"))
+    }
+
+    // A missing config file is the same as an empty one
+    pub fn load() -> Result<Config, String> {
+        match default_path() {
+            Some(path) if path.exists() => {
+                let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+            },
+            _ => Ok(Config::default()),
+        }
+    }
+}
+
+mod leaderboard {
+    use super::*;
+    use game::Game;
+    use serde_json::json;
+    use std::io::{Read, Write};
+    use std::net::{TcpStream, ToSocketAddrs};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    // A win as sent to a leaderboard. The replay itself stays here, and only its checksum is sent, so a
+    // server can ask for the replay later and check it is the same game
+    pub struct Submission {
+        seed: u64,
+        width: usize,
+        height: usize,
+        mines: usize,
+        seconds: u64,
+        replay: String,
+    }
+
+    impl Submission {
+        pub fn of(game: &Game) -> Submission {
+            Submission {
+                seed: game.board.seed,
+                width: game.board.width(),
+                height: game.board.height(),
+                mines: game.board.mine_total,
+                seconds: game.clock.seconds(),
+                replay: seal::checksum(&game.replay().to_text()),
+            }
+        }
+
+        pub fn describe(&self) -> String {
+            format!(
+                "seed {}, {}x{} with {} mines, time {}, replay checksum {}",
+                self.seed, self.width, self.height, self.mines, format_time(self.seconds), self.replay,
+            )
+        }
+
+        // Signed with the same key as saves and replays, which catches results edited by hand but not a
+        // determined forger
+        pub fn to_json(&self) -> String {
+            let fields = format!("{} {}x{} {} {} {}", self.seed, self.width, self.height, self.mines, self.seconds, self.replay);
+
+            json!({
+                "seed": self.seed,
+                "width": self.width,
+                "height": self.height,
+                "mines": self.mines,
+                "time": self.seconds,
+                "replay": self.replay,
+                "signature": seal::checksum(&fields),
+            }).to_string()
+        }
+    }
+
+    // Splits an http:// url into the host, the port and the path
+    fn parse_url(url: &str) -> Result<(&str, u16, &str), String> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| String::from("only http:// urls are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+
+        match authority.rsplit_once(':') {
+            Some((host, port)) => Ok((host, port.parse().map_err(|_| format!("invalid port: {}", port))?, path)),
+            None if !authority.is_empty() => Ok((authority, 80, path)),
+            None => Err(String::from("the url has no host")),
+        }
+    }
+
+    fn request(host: &str, port: u16, path: &str, body: &str) -> String {
+        format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path, host, port, body.len(), body,
+        )
+    }
+
+    pub fn post(url: &str, body: &str) -> Result<(), String> {
+        let (host, port, path) = parse_url(url)?;
+        let address = (host, port).to_socket_addrs().map_err(|e| e.to_string())?
+            .next().ok_or_else(|| format!("could not find {}", host))?;
+
+        let mut stream = TcpStream::connect_timeout(&address, TIMEOUT).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.write_all(request(host, port, path, body).as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+        let status = response.lines().next().unwrap_or_default();
+
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(format!("the server answered {:?}", status)),
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn leaderboard_requests() {
+            assert_eq!(parse_url("http://example.com"), Ok(("example.com", 80, "/")));
+            assert_eq!(parse_url("http://localhost:8080/scores"), Ok(("localhost", 8080, "/scores")));
+            assert!(parse_url("https://example.com/").is_err());
+            assert!(parse_url("http://example.com:port/").is_err());
+
+            let result = Submission { seed: 7, width: 9, height: 9, mines: 10, seconds: 42, replay: String::from("00ff") };
+            let body = result.to_json();
+            assert_eq!(result.describe(), "seed 7, 9x9 with 10 mines, time 042, replay checksum 00ff");
+            assert!(body.contains(&format!(r#""signature":"{}""#, seal::checksum("7 9x9 10 42 00ff"))));
+
+            let text = request("localhost", 8080, "/scores", &body);
+            assert!(text.starts_with("POST /scores HTTP/1.1\r\nHost: localhost:8080\r\n"));
+            assert!(text.ends_with(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)));
+        }
+    }
+}
+
+mod infinite {
+    use crate::board::Cell;
+
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use std::collections::HashMap;
+
+    pub const CHUNK: i64 = 16;
+
+    // An opening on a sparse chunk could in theory go on forever, so a single uncover stops spreading after this many tiles
+    const MAX_OPENING: usize = 4096;
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    enum State {
+        Covered,
+        Open,
+        Flagged,
+    }
+
+    struct Chunk {
+        density: f64,
+        mines: Vec<bool>,
+        states: Vec<State>,
+    }
+
+    // A board without edges. Chunks are generated from the seed and their position the first time anything looks at
+    // them, so the same seed always gives the same field no matter where the player goes first
+    pub struct Field {
+        pub seed: u64,
+        density: f64,
+        chunks: HashMap<(i64, i64), Chunk>,
+        pub opened: usize,
+        pub flags: usize,
+        pub lost: Option<(i64, i64)>,
+    }
+
+    impl Field {
+        pub fn new(seed: u64, density: f64) -> Field {
+            Field {
+                seed: seed,
+                density: density,
+                chunks: HashMap::new(),
+                opened: 0,
+                flags: 0,
+                lost: None,
+            }
+        }
+
+        pub fn chunk_count(&self) -> usize {
+            self.chunks.len()
+        }
+
+        // Every chunk picks its own density around the average, so some areas are calm and others are packed. The
+        // tiles around the origin never hold a mine, which makes the first uncover there an opening
+        fn chunk(&mut self, pos: (i64, i64)) -> &mut Chunk {
+            let (seed, density) = (self.seed, self.density);
+
+            self.chunks.entry(pos).or_insert_with(|| {
+                let mixed = seed
+                    ^ (pos.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                    ^ (pos.1 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+                let mut rng = StdRng::seed_from_u64(mixed);
+                let density = (density * rng.gen_range(0.5..1.5)).min(0.9);
+
+                let mines = (0..CHUNK * CHUNK)
+                    .map(|i| {
+                        let x = pos.0 * CHUNK + i % CHUNK;
+                        let y = pos.1 * CHUNK + i / CHUNK;
+
+                        rng.gen_bool(density) && (x.abs() > 1 || y.abs() > 1)
+                    })
+                    .collect();
+
+                Chunk {
+                    density: density,
+                    mines: mines,
+                    states: vec![State::Covered; (CHUNK * CHUNK) as usize],
+                }
+            })
+        }
+
+        fn tile(&mut self, x: i64, y: i64) -> (&mut Chunk, usize) {
+            let i = (y.rem_euclid(CHUNK) * CHUNK + x.rem_euclid(CHUNK)) as usize;
+            (self.chunk((x.div_euclid(CHUNK), y.div_euclid(CHUNK))), i)
+        }
+
+        fn mine(&mut self, x: i64, y: i64) -> bool {
+            let (chunk, i) = self.tile(x, y);
+            chunk.mines[i]
+        }
+
+        fn state(&mut self, x: i64, y: i64) -> State {
+            let (chunk, i) = self.tile(x, y);
+            chunk.states[i]
+        }
+
+        fn set_state(&mut self, x: i64, y: i64, state: State) {
+            let (chunk, i) = self.tile(x, y);
+            chunk.states[i] = state;
+        }
+
+        // Counts the generated, opened and flagged tiles in a rectangle without generating anything new
+        pub fn block(&self, x: i64, y: i64, width: i64, height: i64) -> (usize, usize, usize) {
+            let mut counts = (0, 0, 0);
+
+            for y in y..y + height {
+                for x in x..x + width {
+                    let chunk = self.chunks.get(&(x.div_euclid(CHUNK), y.div_euclid(CHUNK)));
+
+                    if let Some(i) = chunk {
+                        counts.0 += 1;
+
+                        match i.states[(y.rem_euclid(CHUNK) * CHUNK + x.rem_euclid(CHUNK)) as usize] {
+                            State::Open => counts.1 += 1,
+                            State::Flagged => counts.2 += 1,
+                            State::Covered => (),
+                        }
+                    }
+                }
+            }
+
+            counts
+        }
+
+        pub fn density(&mut self, x: i64, y: i64) -> f64 {
+            self.tile(x, y).0.density
+        }
+
+        pub fn count(&mut self, x: i64, y: i64) -> usize {
+            neighbors(x, y).filter(|&(x, y)| self.mine(x, y)).count()
+        }
+
+        pub fn cell(&mut self, x: i64, y: i64) -> Cell {
+            let lost = self.lost.is_some();
+
+            match (self.state(x, y), self.mine(x, y)) {
+                (State::Covered, true) if self.lost == Some((x, y)) => Cell::Exploded,
+                (State::Covered, true) if lost => Cell::Mine,
+                (State::Covered, _) => Cell::Covered,
+                (State::Flagged, false) if lost => Cell::WrongFlag,
+                (State::Flagged, _) => Cell::Flagged,
+                (State::Open, _) => Cell::Open(self.count(x, y)),
+            }
+        }
+
+        // Uncovering an open number with as many flags around it as its count uncovers its other neighbors, the same as
+        // on a normal board
+        pub fn uncover(&mut self, x: i64, y: i64) {
+            if self.lost.is_some() {
+                return;
+            }
+
+            match self.state(x, y) {
+                State::Covered => self.open(x, y),
+                State::Open => {
+                    let flags = neighbors(x, y).filter(|&(x, y)| self.state(x, y) == State::Flagged).count();
+
+                    if flags == self.count(x, y) {
+                        for (x, y) in neighbors(x, y) {
+                            if self.lost.is_none() && self.state(x, y) == State::Covered {
+                                self.open(x, y);
+                            }
+                        }
+                    }
+                },
+                State::Flagged => (),
+            }
+        }
+
+        fn open(&mut self, x: i64, y: i64) {
+            if self.mine(x, y) {
+                self.lost = Some((x, y));
+                return;
+            }
+
+            let mut stack = vec![(x, y)];
+            let mut opened = 0;
+
+            while let Some((x, y)) = stack.pop() {
+                if self.state(x, y) != State::Covered {
+                    continue;
+                }
+
+                self.set_state(x, y, State::Open);
+                self.opened += 1;
+                opened += 1;
+
+                if opened < MAX_OPENING && self.count(x, y) == 0 {
+                    stack.extend(neighbors(x, y));
+                }
+            }
+        }
+
+        pub fn flag(&mut self, x: i64, y: i64) {
+            if self.lost.is_some() {
+                return;
+            }
+
+            match self.state(x, y) {
+                State::Covered => {
+                    self.set_state(x, y, State::Flagged);
+                    self.flags += 1;
+                },
+                State::Flagged => {
+                    self.set_state(x, y, State::Covered);
+                    self.flags -= 1;
+                },
+                State::Open => (),
+            }
+        }
+    }
+
+    fn neighbors(x: i64, y: i64) -> impl Iterator<Item = (i64, i64)> {
+        (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy))).filter(move |&i| i != (x, y))
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn infinite_field() {
+            let mut a = Field::new(7, 0.2);
+            let mut b = Field::new(7, 0.2);
+
+            let far = (-1000, 517);
+            let near: Vec<_> = (-40..40).map(|i| b.cell(i, i / 2)).collect();
+            b.cell(far.0, far.1);
+
+            assert_eq!(a.mine(far.0, far.1), b.mine(far.0, far.1));
+            assert!((-40..40).all(|i| a.mine(i, i / 2) == b.mine(i, i / 2)));
+            assert!(near.iter().all(|i| *i == Cell::Covered));
+
+            a.uncover(0, 0);
+            assert_eq!(a.cell(0, 0), Cell::Open(0));
+            assert!(a.opened >= 9);
+            assert!(a.chunk_count() >= 3);
+            assert_eq!(a.block(-1, -1, 3, 3), (9, 9, 0));
+            assert_eq!(a.block(5000, 5000, 4, 4), (0, 0, 0));
+
+            let mine = (2..).map(|i| (i, -i)).find(|&(x, y)| a.mine(x, y) && a.state(x, y) == State::Covered).unwrap();
+            a.flag(mine.0, mine.1);
+            assert_eq!((a.cell(mine.0, mine.1), a.flags), (Cell::Flagged, 1));
+            a.flag(mine.0, mine.1);
+            a.uncover(mine.0, mine.1);
+            assert_eq!(a.lost, Some(mine));
+            assert_eq!(a.cell(mine.0, mine.1), Cell::Exploded);
+        }
+    }
+}
+
+mod keymap {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::collections::HashMap;
+
+    use super::ThemeName;
+
+    arg_enum! {
+        #[derive(Debug, Clone, Copy)]
+        pub enum KeyPreset {
+            Default,
+            Vim,
+        }
+    }
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum Motion {
+        Left,
+        Right,
+        Up,
+        Down,
+        Top,
+        Bottom,
+        RowStart,
+        RowEnd,
+        To(u16, u16),
+        NextCovered,
+        // To the same place on the layer above or below
+        LayerUp,
+        LayerDown,
+    }
+
+    #[derive(PartialEq, Debug, Clone)]
+    pub enum Action {
+        Move(Motion),
+        Uncover,
+        Flag,
+        ToggleFlagMode,
+        ToggleDeductions,
+        Hint,
+        Rewind,
+        ToggleHistory,
+        ToggleOverview,
+        Recording(Option<char>),
+        PlayMacro(char),
+        OpenPalette,
+        Restart(Option<u64>),
+        ShowSeed,
+        Pencil,
+        Tag,
+        NextTag,
+        ClearTags,
+        Save(String),
+        Theme(ThemeName),
+        UsePowerUp(usize),
+        Quit,
+    }
+
+    // Shift is ignored since it is already reflected in the character itself
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    struct Key {
+        code: KeyCode,
+        ctrl: bool,
+    }
+
+    impl From<KeyEvent> for Key {
+        fn from(event: KeyEvent) -> Key {
+            Key {
+                code: event.code,
+                ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            }
+        }
+    }
+
+    fn key(c: char) -> Vec<Key> {
+        vec![Key { code: KeyCode::Char(c), ctrl: false }]
+    }
+
+    fn ctrl(c: char) -> Vec<Key> {
+        vec![Key { code: KeyCode::Char(c), ctrl: true }]
+    }
+
+    fn code(code: KeyCode) -> Vec<Key> {
+        vec![Key { code: code, ctrl: false }]
+    }
+
+    pub struct Keymap {
+        bindings: Vec<(Vec<Key>, Action)>,
+        pending: Vec<Key>,
+        macros_enabled: bool,
+        macros: HashMap<char, Vec<KeyEvent>>,
+        recording: Option<(char, Vec<KeyEvent>)>,
+        register: Option<char>,
+        last_played: Option<char>,
+        pub replaying: bool,
+    }
+
+    impl Keymap {
+        pub fn new(preset: KeyPreset) -> Keymap {
+            let mut bindings = vec![
+                (code(KeyCode::Left), Action::Move(Motion::Left)),
+                (code(KeyCode::Right), Action::Move(Motion::Right)),
+                (code(KeyCode::Up), Action::Move(Motion::Up)),
+                (code(KeyCode::Down), Action::Move(Motion::Down)),
+                (code(KeyCode::PageUp), Action::Move(Motion::LayerUp)),
+                (code(KeyCode::PageDown), Action::Move(Motion::LayerDown)),
+                (key('n'), Action::Move(Motion::NextCovered)),
+                (key('m'), Action::ToggleFlagMode),
+                (key('i'), Action::ToggleDeductions),
+                (key('?'), Action::Hint),
+                (key('H'), Action::ToggleHistory),
+                (key('z'), Action::ToggleOverview),
+                (key('p'), Action::Pencil),
+                (key('c'), Action::Tag),
+                (key('C'), Action::NextTag),
+                (key(':'), Action::OpenPalette),
+                (key('1'), Action::UsePowerUp(0)),
+                (key('2'), Action::UsePowerUp(1)),
+                (key('3'), Action::UsePowerUp(2)),
+                (code(KeyCode::Esc), Action::Quit),
+                (ctrl('q'), Action::Quit),
+            ];
+
+            bindings.extend(match preset {
+                KeyPreset::Default => vec![
+                    (key('a'), Action::Move(Motion::Left)),
+                    (key('d'), Action::Move(Motion::Right)),
+                    (key('w'), Action::Move(Motion::Up)),
+                    (key('s'), Action::Move(Motion::Down)),
+                    (key('q'), Action::Uncover),
+                    (key('e'), Action::Flag),
+                ],
+                KeyPreset::Vim => vec![
+                    (key('h'), Action::Move(Motion::Left)),
+                    (key('l'), Action::Move(Motion::Right)),
+                    (key('k'), Action::Move(Motion::Up)),
+                    (key('j'), Action::Move(Motion::Down)),
+                    ([key('g'), key('g')].concat(), Action::Move(Motion::Top)),
+                    (key('G'), Action::Move(Motion::Bottom)),
+                    (key('0'), Action::Move(Motion::RowStart)),
+                    (key('$'), Action::Move(Motion::RowEnd)),
+                    (key(' '), Action::Uncover),
+                    (key('f'), Action::Flag),
+                ],
+            });
+
+            Keymap {
+                bindings: bindings,
+                pending: Vec::new(),
+                macros_enabled: matches!(preset, KeyPreset::Vim),
+                macros: HashMap::new(),
+                recording: None,
+                register: None,
+                last_played: None,
+                replaying: false,
+            }
+        }
+
+        pub fn macro_keys(&self, register: char) -> Option<Vec<KeyEvent>> {
+            self.macros.get(&register).cloned()
+        }
+
+        // Keys that never reach feed, like those typed into the command line, still belong in a macro
+        pub fn record(&mut self, event: KeyEvent) {
+            if let (Some(i), false) = (self.recording.as_mut(), self.replaying) {
+                i.1.push(event);
+            }
+        }
+
+        pub fn feed(&mut self, event: KeyEvent) -> Option<Action> {
+            self.record(event);
+
+            if self.macros_enabled && self.pending.is_empty() {
+                if let Some(i) = self.feed_macro(event) {
+                    return i;
+                }
+            }
+
+            let key = Key::from(event);
+            self.pending.push(key);
+
+            // If the sequence so far leads nowhere, start a fresh one from the latest key
+            if !self.is_prefix() {
+                self.pending = vec![key];
+
+                if !self.is_prefix() {
+                    self.pending.clear();
+                    return None;
+                }
+            }
+
+            let action = self.bindings.iter()
+                .find(|i| i.0 == self.pending)
+                .map(|i| i.1.clone());
+
+            if action.is_some() {
+                self.pending.clear();
+            }
+
+            action
+        }
+
+        fn is_prefix(&self) -> bool {
+            self.bindings.iter().any(|i| i.0.starts_with(&self.pending))
+        }
+
+        // q followed by a register records keys until the next q, and @ followed by a register plays them
+        // back, with @@ repeating the last one played. Returns None for keys that go to the bindings
+        fn feed_macro(&mut self, event: KeyEvent) -> Option<Option<Action>> {
+            let c = match event.code {
+                KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => c,
+                _ => return self.register.take().map(|_| None),
+            };
+
+            match (self.register.take(), c) {
+                (Some('q'), c) if c.is_ascii_alphanumeric() => {
+                    self.recording = Some((c, Vec::new()));
+                    Some(Some(Action::Recording(Some(c))))
+                },
+                (Some('@'), '@') => Some(self.last_played.map(Action::PlayMacro)),
+                (Some('@'), c) if c.is_ascii_alphanumeric() => {
+                    self.last_played = Some(c);
+                    Some(Some(Action::PlayMacro(c)))
+                },
+                (Some(_), _) => Some(None),
+                (None, 'q') if self.recording.is_some() && !self.replaying => {
+                    let (register, mut keys) = self.recording.take().unwrap();
+                    keys.pop();
+                    self.macros.insert(register, keys);
+                    Some(Some(Action::Recording(None)))
+                },
+                (None, 'q') | (None, '@') => {
+                    self.register = Some(c);
+                    Some(None)
+                },
+                _ => None,
+            }
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        fn press(c: char) -> KeyEvent {
+            KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+        }
+
+        #[test]
+        fn vim_sequences() {
+            let mut keymap = Keymap::new(KeyPreset::Vim);
+
+            assert_eq!(keymap.feed(press('g')), None);
+            assert_eq!(keymap.feed(press('g')), Some(Action::Move(Motion::Top)));
+
+            assert_eq!(keymap.feed(press('g')), None);
+            assert_eq!(keymap.feed(press('j')), Some(Action::Move(Motion::Down)));
+
+            assert_eq!(keymap.feed(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)), Some(Action::Move(Motion::Bottom)));
+            assert_eq!(keymap.feed(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL)), Some(Action::Quit));
+        }
+
+        #[test]
+        fn macros() {
+            let mut keymap = Keymap::new(KeyPreset::Vim);
+
+            assert_eq!(keymap.feed(press('q')), None);
+            assert_eq!(keymap.feed(press('a')), Some(Action::Recording(Some('a'))));
+            assert_eq!(keymap.feed(press('f')), Some(Action::Flag));
+            assert_eq!(keymap.feed(press('l')), Some(Action::Move(Motion::Right)));
+            assert_eq!(keymap.feed(press('q')), Some(Action::Recording(None)));
+
+            assert_eq!(keymap.macro_keys('a'), Some(vec![press('f'), press('l')]));
+            assert_eq!(keymap.macro_keys('b'), None);
+
+            assert_eq!(keymap.feed(press('@')), None);
+            assert_eq!(keymap.feed(press('@')), None);
+            assert_eq!(keymap.feed(press('@')), None);
+            assert_eq!(keymap.feed(press('a')), Some(Action::PlayMacro('a')));
+            assert_eq!(keymap.feed(press('@')), None);
+            assert_eq!(keymap.feed(press('@')), Some(Action::PlayMacro('a')));
+
+            assert_eq!(Keymap::new(KeyPreset::Default).feed(press('q')), Some(Action::Uncover));
+        }
+    }
+}
+
+mod theme {
+    use crossterm::style::{style, Color, StyledContent, Styler};
+    use serde::Deserialize;
+
+    use std::env;
+    use std::fmt::{self, Display};
+    use std::fs;
+    use std::str::FromStr;
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum ThemeName {
+        Default,
+        Dark,
+        HighContrast,
+    }
+
+    impl ThemeName {
+        pub fn variants() -> [&'static str; 3] {
+            ["default", "dark", "high-contrast"]
+        }
+    }
+
+    impl fmt::Display for ThemeName {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match *self {
+                ThemeName::Default => "default",
+                ThemeName::Dark => "dark",
+                ThemeName::HighContrast => "high-contrast",
+            };
+
+            write!(f, "{}", name)
+        }
+    }
+
+    impl FromStr for ThemeName {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<ThemeName, String> {
+            match s.to_lowercase().as_str() {
+                "default" => Ok(ThemeName::Default),
+                "dark" => Ok(ThemeName::Dark),
+                "high-contrast" => Ok(ThemeName::HighContrast),
+                _ => Err(format!("unknown theme: {}", s)),
+            }
+        }
+    }
+
+    pub fn paint<D: Display + Clone>(content: D, color: Option<Color>) -> StyledContent<D> {
+        match color {
+            Some(c) => style(content).with(c),
+            None => style(content),
+        }
+    }
+
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Glyph {
+        symbol: String,
+        color: Option<Color>,
+        background: Option<Color>,
+        bold: bool,
+    }
+
+    impl Glyph {
+        fn new(symbol: &str, color: Option<Color>) -> Glyph {
+            Glyph {
+                symbol: String::from(symbol),
+                color: color,
+                background: None,
+                bold: false,
+            }
+        }
+
+        fn on(mut self, background: Color) -> Glyph {
+            self.background = Some(background);
+            self
+        }
+
+        fn bold(mut self) -> Glyph {
+            self.bold = true;
+            self
+        }
+
+        fn plain(&mut self) {
+            self.color = None;
+            self.background = None;
+            self.bold = false;
+        }
+
+        pub fn styled(&self) -> StyledContent<&str> {
+            let content = paint(self.symbol.as_str(), self.color);
+            let content = if self.bold { content.bold() } else { content };
+
+            match self.background {
+                Some(c) => content.on(c),
+                None => content,
+            }
+        }
+
+        // A count from 1 to 3 in the glyph's style, for tiles holding more than one mine or flag
+        pub fn count(&self, n: usize) -> StyledContent<&'static str> {
+            const COUNTS: [&str; 3] = ["1", "2", "3"];
+
+            StyledContent::new(*self.styled().style(), COUNTS[n - 1])
+        }
+
+        fn apply(&mut self, key: &str, file: &GlyphFile) -> Result<(), String> {
+            if let Some(glyph) = &file.glyph {
+                self.symbol = parse_char(&format!("{}.glyph", key), glyph)?.to_string();
+            }
+
+            if let Some(color) = &file.color {
+                self.color = Some(parse_color(&format!("{}.color", key), color)?);
+            }
+
+            if let Some(background) = &file.background {
+                self.background = Some(parse_color(&format!("{}.background", key), background)?);
+            }
+
+            if let Some(bold) = file.bold {
+                self.bold = bold;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Border {
+        pub horizontal: char,
+        pub vertical: char,
+        pub top_left: char,
+        pub top_right: char,
+        pub bottom_left: char,
+        pub bottom_right: char,
+        pub top_tee: char,
+        pub bottom_tee: char,
+        pub left_tee: char,
+        pub right_tee: char,
+    }
+
+    #[derive(PartialEq, Debug, Clone)]
+    pub struct Theme {
+        pub covered: Glyph,
+        pub empty: Glyph,
+        pub numbers: [Option<Color>; 8],
+        pub mine: Glyph,
+        pub flag: Glyph,
+        pub wrong_flag: Glyph,
+        pub exploded: Glyph,
+        pub treasure: Glyph,
+        pub anti_mine: Glyph,
+        pub anti_flag: Glyph,
+        // Numbers below zero, which are drawn without their sign
+        pub negative: Option<Color>,
+        pub border: Border,
+        pub counter: Option<Color>,
+        pub timer: Option<Color>,
+        pub progress: Option<Color>,
+        pub flag_mode: Glyph,
+        // Numbers are drawn in bold, on the same background as empty tiles
+        pub bold_numbers: bool,
+        // What the theme has been fitted to. Without colors, the few things that are only told apart by color use
+        // attributes like underline instead
+        pub capabilities: Capabilities,
+    }
+
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub enum ColorDepth {
+        None,
+        Basic,
+        Ansi256,
+        TrueColor,
+    }
+
+    // What the terminal can draw. Terminals can't be asked for this reliably, so it is worked out from the
+    // environment, assuming the best wherever it says nothing
+    #[derive(PartialEq, Debug, Clone, Copy)]
+    pub struct Capabilities {
+        pub unicode: bool,
+        pub colors: ColorDepth,
+    }
+
+    impl Capabilities {
+        pub fn full() -> Capabilities {
+            Capabilities {
+                unicode: true,
+                colors: ColorDepth::TrueColor,
+            }
+        }
+
+        pub fn detect() -> Capabilities {
+            Capabilities::from_env(|key| env::var(key).ok().filter(|i| !i.is_empty()))
+        }
+
+        fn from_env<F: Fn(&str) -> Option<String>>(var: F) -> Capabilities {
+            let term = var("TERM");
+            let simple = term.as_deref().map_or(false, |i| matches!(i, "dumb" | "vt52" | "vt100" | "vt102" | "vt220"));
+            let locale = var("LC_ALL").or_else(|| var("LC_CTYPE")).or_else(|| var("LANG"));
+            let utf8 = locale.map_or(true, |i| {
+                let i = i.to_lowercase();
+                i.contains("utf-8") || i.contains("utf8")
+            });
+
+            let colors = match (term.as_deref(), var("COLORTERM").as_deref()) {
+                _ if simple => ColorDepth::None,
+                (_, Some("truecolor")) | (_, Some("24bit")) => ColorDepth::TrueColor,
+                (Some(i), _) if i.contains("256color") => ColorDepth::Ansi256,
+                // The Windows console doesn't set TERM, but has had full colors since Windows 10
+                (None, _) if cfg!(windows) => ColorDepth::TrueColor,
+                _ => ColorDepth::Basic,
+            };
+
+            Capabilities {
+                unicode: utf8 && !simple,
+                colors: colors,
+            }
+        }
+    }
+
+    impl Theme {
+        pub fn builtin(name: ThemeName) -> Theme {
+            let border = Border {
+                horizontal: '═',
+                vertical: '║',
+                top_left: '╔',
+                top_right: '╗',
+                bottom_left: '╚',
+                bottom_right: '╝',
+                top_tee: '╦',
+                bottom_tee: '╩',
+                left_tee: '╠',
+                right_tee: '╣',
+            };
+
+            match name {
+                ThemeName::Default => Theme {
+                    covered: Glyph::new("░", None),
+                    empty: Glyph::new(" ", None),
+                    numbers: classic_numbers(),
+                    mine: Glyph::new("Ø", Some(Color::Red)),
+                    flag: Glyph::new("Þ", Some(Color::Green)),
+                    wrong_flag: Glyph::new("X", Some(Color::Red)),
+                    exploded: Glyph::new("Ø", Some(Color::White)).on(Color::Red),
+                    treasure: Glyph::new("$", Some(Color::Yellow)),
+                    anti_mine: Glyph::new("ø", Some(Color::Blue)),
+                    anti_flag: Glyph::new("þ", Some(Color::Blue)),
+                    negative: Some(Color::Magenta),
+                    border: border,
+                    counter: None,
+                    timer: None,
+                    progress: None,
+                    flag_mode: Glyph::new("Þ", Some(Color::Green)),
+                    bold_numbers: false,
+                    capabilities: Capabilities::full(),
+                },
+                ThemeName::Dark => Theme {
+                    covered: Glyph::new("▒", Some(Color::DarkGrey)),
+                    empty: Glyph::new(" ", None),
+                    numbers: [Some(Color::Grey); 8],
+                    mine: Glyph::new("Ø", Some(Color::DarkRed)),
+                    flag: Glyph::new("Þ", Some(Color::DarkGreen)),
+                    wrong_flag: Glyph::new("X", Some(Color::DarkRed)),
+                    exploded: Glyph::new("Ø", Some(Color::Grey)).on(Color::DarkRed),
+                    treasure: Glyph::new("$", Some(Color::DarkYellow)),
+                    anti_mine: Glyph::new("ø", Some(Color::DarkBlue)),
+                    anti_flag: Glyph::new("þ", Some(Color::DarkBlue)),
+                    negative: Some(Color::DarkMagenta),
+                    border: border,
+                    counter: Some(Color::Grey),
+                    timer: Some(Color::Grey),
+                    progress: Some(Color::DarkGrey),
+                    flag_mode: Glyph::new("Þ", Some(Color::DarkGreen)),
+                    bold_numbers: false,
+                    capabilities: Capabilities::full(),
+                },
+                // Only bright colors on black, with a different symbol for every kind of tile so nothing relies on
+                // color alone
+                ThemeName::HighContrast => Theme {
+                    covered: Glyph::new("▓", Some(Color::White)).on(Color::Black),
+                    empty: Glyph::new(" ", None).on(Color::Black),
+                    numbers: [
+                        Some(Color::Cyan),
+                        Some(Color::Green),
+                        Some(Color::Yellow),
+                        Some(Color::Magenta),
+                        Some(Color::Red),
+                        Some(Color::White),
+                        Some(Color::Cyan),
+                        Some(Color::White),
+                    ],
+                    mine: Glyph::new("*", Some(Color::White)).on(Color::Black).bold(),
+                    flag: Glyph::new("F", Some(Color::Yellow)).on(Color::Black).bold(),
+                    wrong_flag: Glyph::new("X", Some(Color::Black)).on(Color::Yellow).bold(),
+                    exploded: Glyph::new("*", Some(Color::White)).on(Color::Red).bold(),
+                    treasure: Glyph::new("$", Some(Color::Yellow)).on(Color::Black).bold(),
+                    anti_mine: Glyph::new("o", Some(Color::White)).on(Color::Black).bold(),
+                    anti_flag: Glyph::new("f", Some(Color::Yellow)).on(Color::Black).bold(),
+                    negative: Some(Color::Blue),
+                    border: border,
+                    counter: Some(Color::White),
+                    timer: Some(Color::White),
+                    progress: Some(Color::White),
+                    flag_mode: Glyph::new("F", Some(Color::Yellow)).bold(),
+                    bold_numbers: true,
+                    capabilities: Capabilities::full(),
+                },
+            }
+        }
+
+        // Strips every color and bold glyph, keeping the symbols
+        pub fn plain(mut self) -> Theme {
+            for glyph in vec![&mut self.covered, &mut self.empty, &mut self.mine, &mut self.flag, &mut self.wrong_flag,
+                &mut self.exploded, &mut self.treasure, &mut self.anti_mine, &mut self.anti_flag, &mut self.flag_mode] {
+                glyph.plain();
+            }
+
+            self.numbers = [None; 8];
+            self.negative = None;
+            self.counter = None;
+            self.timer = None;
+            self.progress = None;
+            self.bold_numbers = false;
+            self.capabilities.colors = ColorDepth::None;
+            self
+        }
+
+        // Swaps out whatever the terminal can't draw, using ASCII glyphs instead of unicode ones and the closest
+        // colors it has
+        pub fn fit(mut self, capabilities: Capabilities) -> Theme {
+            if !capabilities.unicode {
+                for glyph in vec![&mut self.covered, &mut self.empty, &mut self.mine, &mut self.flag,
+                    &mut self.wrong_flag, &mut self.exploded, &mut self.treasure, &mut self.anti_mine, &mut self.anti_flag, &mut self.flag_mode] {
+                    glyph.symbol = glyph.symbol.chars().map(ascii).collect();
+                }
+
+                let border = &mut self.border;
+                for piece in vec![&mut border.horizontal, &mut border.vertical, &mut border.top_left,
+                    &mut border.top_right, &mut border.bottom_left, &mut border.bottom_right, &mut border.top_tee,
+                    &mut border.bottom_tee, &mut border.left_tee, &mut border.right_tee] {
+                    *piece = ascii(*piece);
+                }
+
+                self.capabilities.unicode = false;
+            }
+
+            if capabilities.colors == ColorDepth::None {
+                return self.plain();
+            }
+
+            let depth = capabilities.colors;
+            for glyph in vec![&mut self.covered, &mut self.empty, &mut self.mine, &mut self.flag, &mut self.wrong_flag,
+                &mut self.exploded, &mut self.treasure, &mut self.anti_mine, &mut self.anti_flag, &mut self.flag_mode] {
+                glyph.color = glyph.color.map(|i| reduce(i, depth));
+                glyph.background = glyph.background.map(|i| reduce(i, depth));
+            }
+
+            for color in self.numbers.iter_mut().chain(vec![&mut self.negative, &mut self.counter, &mut self.timer, &mut self.progress]) {
+                *color = color.map(|i| reduce(i, depth));
+            }
+
+            self.capabilities.colors = depth;
+            self
+        }
+
+        pub fn colored(&self) -> bool {
+            self.capabilities.colors != ColorDepth::None
+        }
+
+        // Numbers past 9 only come up with stacked mines or layers, and go on as letters so they still take one
+        // character
+        pub fn number(&self, n: usize) -> StyledContent<&'static str> {
+            const NUMBERS: [&str; 26] = [
+                "1", "2", "3", "4", "5", "6", "7", "8", "9", "A", "B", "C", "D",
+                "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q",
+            ];
+
+            let content = paint(NUMBERS[n - 1], self.numbers[n.min(8) - 1]);
+            let content = if self.bold_numbers { content.bold() } else { content };
+
+            match self.empty.background {
+                Some(c) => content.on(c),
+                None => content,
+            }
+        }
+
+        // Numbers around anti-mines, which can be zero or below without being blank
+        pub fn net(&self, n: i64) -> StyledContent<&'static str> {
+            const DIGITS: [&str; 9] = ["0", "1", "2", "3", "4", "5", "6", "7", "8"];
+
+            if n > 0 {
+                return self.number(n as usize);
+            }
+
+            let content = paint(DIGITS[(-n) as usize], self.negative);
+            let content = if self.colored() { content } else { content.underlined() };
+
+            match self.empty.background {
+                Some(c) => content.on(c),
+                None => content,
+            }
+        }
+
+        pub fn from_file(path: &str) -> Result<Theme, String> {
+            let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+            Theme::from_toml(&contents)
+        }
+
+        pub fn from_toml(contents: &str) -> Result<Theme, String> {
+            let file: ThemeFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+            let mut theme = match &file.base {
+                Some(name) => match name.parse() {
+                    Ok(i) => Theme::builtin(i),
+                    Err(_) => return Err(format!("unknown base theme: {}", name)),
+                },
+                None => Theme::default(),
+            };
+
+            let tiles = &file.tiles;
+            let glyphs = vec![
+                ("tiles.covered", &tiles.covered, &mut theme.covered),
+                ("tiles.empty", &tiles.empty, &mut theme.empty),
+                ("tiles.mine", &tiles.mine, &mut theme.mine),
+                ("tiles.flag", &tiles.flag, &mut theme.flag),
+                ("tiles.wrong_flag", &tiles.wrong_flag, &mut theme.wrong_flag),
+                ("tiles.exploded", &tiles.exploded, &mut theme.exploded),
+                ("tiles.treasure", &tiles.treasure, &mut theme.treasure),
+                ("tiles.anti_mine", &tiles.anti_mine, &mut theme.anti_mine),
+                ("tiles.anti_flag", &tiles.anti_flag, &mut theme.anti_flag),
+                ("header.flag_mode", &file.header.flag_mode, &mut theme.flag_mode),
+            ];
+
+            for (key, file_glyph, glyph) in glyphs {
+                if let Some(i) = file_glyph {
+                    glyph.apply(key, i)?;
+                }
+            }
+
+            if let Some(i) = &tiles.number {
+                theme.numbers = [Some(parse_color("tiles.number", i)?); 8];
+            }
+
+            if let Some(i) = &tiles.negative {
+                theme.negative = Some(parse_color("tiles.negative", i)?);
+            }
+
+            if let Some(list) = &tiles.numbers {
+                if list.len() > 8 {
+                    return Err(format!("tiles.numbers has {} colors, but there are only 8 numbers", list.len()));
+                }
+
+                for (n, i) in list.iter().enumerate() {
+                    theme.numbers[n] = Some(parse_color(&format!("tiles.numbers[{}]", n), i)?);
+                }
+            }
+
+            let colors = vec![
+                ("header.counter", &file.header.counter, &mut theme.counter),
+                ("header.timer", &file.header.timer, &mut theme.timer),
+                ("header.progress", &file.header.progress, &mut theme.progress),
+            ];
+
+            for (key, file_color, color) in colors {
+                if let Some(i) = file_color {
+                    *color = Some(parse_color(key, i)?);
+                }
+            }
+
+            let border_file = &file.border;
+            let border = &mut theme.border;
+            let pieces = vec![
+                ("border.horizontal", &border_file.horizontal, &mut border.horizontal),
+                ("border.vertical", &border_file.vertical, &mut border.vertical),
+                ("border.top_left", &border_file.top_left, &mut border.top_left),
+                ("border.top_right", &border_file.top_right, &mut border.top_right),
+                ("border.bottom_left", &border_file.bottom_left, &mut border.bottom_left),
+                ("border.bottom_right", &border_file.bottom_right, &mut border.bottom_right),
+                ("border.top_tee", &border_file.top_tee, &mut border.top_tee),
+                ("border.bottom_tee", &border_file.bottom_tee, &mut border.bottom_tee),
+                ("border.left_tee", &border_file.left_tee, &mut border.left_tee),
+                ("border.right_tee", &border_file.right_tee, &mut border.right_tee),
+            ];
+
+            for (key, file_piece, piece) in pieces {
+                if let Some(i) = file_piece {
+                    *piece = parse_char(key, i)?;
+                }
+            }
+
+            Ok(theme)
+        }
+    }
+
+    pub fn ascii(c: char) -> char {
+        match c {
+            '░' | '▒' | '▓' | '█' => '#',
+            '═' => '-',
+            '║' => '|',
+            '╔' | '╗' | '╚' | '╝' | '╦' | '╩' | '╠' | '╣' => '+',
+            'Ø' => '*',
+            'Þ' => 'F',
+            'ø' => 'o',
+            'þ' => 'f',
+            '·' => '.',
+            c if c.is_ascii() => c,
+            _ => '?',
+        }
+    }
+
+    // The colors the basic 16 stand for in xterm, in palette order
+    const BASIC: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::DarkRed, (128, 0, 0)),
+        (Color::DarkGreen, (0, 128, 0)),
+        (Color::DarkYellow, (128, 128, 0)),
+        (Color::DarkBlue, (0, 0, 128)),
+        (Color::DarkMagenta, (128, 0, 128)),
+        (Color::DarkCyan, (0, 128, 128)),
+        (Color::Grey, (192, 192, 192)),
+        (Color::DarkGrey, (128, 128, 128)),
+        (Color::Red, (255, 0, 0)),
+        (Color::Green, (0, 255, 0)),
+        (Color::Yellow, (255, 255, 0)),
+        (Color::Blue, (0, 0, 255)),
+        (Color::Magenta, (255, 0, 255)),
+        (Color::Cyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+        match color {
+            Color::Rgb { r, g, b } => Some((r, g, b)),
+            Color::AnsiValue(n) if n < 16 => Some(BASIC[n as usize].1),
+            Color::AnsiValue(n) if n < 232 => {
+                let n = n as usize - 16;
+                Some((CUBE[n / 36], CUBE[n / 6 % 6], CUBE[n % 6]))
+            },
+            Color::AnsiValue(n) => {
+                let grey = 8 + 10 * (n - 232);
+                Some((grey, grey, grey))
+            },
+            _ => None,
+        }
+    }
+
+    fn nearest<T: Copy>(options: &[(T, u8)], value: u8) -> T {
+        options.iter().min_by_key(|i| (i.1 as i32 - value as i32).abs()).unwrap().0
+    }
+
+    // Turns a color into the closest one the terminal can show
+    fn reduce(color: Color, depth: ColorDepth) -> Color {
+        let (r, g, b) = match rgb(color) {
+            Some(i) => i,
+            None => return color,
+        };
+
+        match depth {
+            ColorDepth::Basic => {
+                let distance = |c: (u8, u8, u8)| {
+                    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+                    d(r, c.0) + d(g, c.1) + d(b, c.2)
+                };
+
+                BASIC.iter().min_by_key(|i| distance(i.1)).unwrap().0
+            },
+            ColorDepth::Ansi256 if matches!(color, Color::Rgb { .. }) => {
+                let levels: Vec<(u8, u8)> = CUBE.iter().enumerate().map(|(n, i)| (n as u8, *i)).collect();
+                Color::AnsiValue(16 + 36 * nearest(&levels, r) + 6 * nearest(&levels, g) + nearest(&levels, b))
+            },
+            _ => color,
+        }
+    }
+
+    fn truecolor_supported() -> bool {
+        match env::var("COLORTERM") {
+            Ok(i) => i == "truecolor" || i == "24bit",
+            Err(_) => false,
+        }
+    }
+
+    // 7 is black in the original game, which would vanish on a dark terminal
+    fn classic_numbers() -> [Option<Color>; 8] {
+        let rgb = |r, g, b| Some(Color::Rgb { r: r, g: g, b: b });
+
+        if truecolor_supported() {
+            [
+                rgb(0x00, 0x00, 0xff),
+                rgb(0x00, 0x80, 0x00),
+                rgb(0xff, 0x00, 0x00),
+                rgb(0x00, 0x00, 0x80),
+                rgb(0x80, 0x00, 0x00),
+                rgb(0x00, 0x80, 0x80),
+                rgb(0x80, 0x00, 0x80),
+                rgb(0x80, 0x80, 0x80),
+            ]
+        } else {
+            [
+                Some(Color::Blue),
+                Some(Color::DarkGreen),
+                Some(Color::Red),
+                Some(Color::DarkBlue),
+                Some(Color::DarkRed),
+                Some(Color::DarkCyan),
+                Some(Color::DarkMagenta),
+                Some(Color::Grey),
+            ]
+        }
+    }
+
+    impl Default for Theme {
+        fn default() -> Theme {
+            Theme::builtin(ThemeName::Default)
+        }
+    }
+
+    fn parse_char(key: &str, value: &str) -> Result<char, String> {
+        let mut chars = value.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!("{} must be a single character, found \"{}\"", key, value)),
+        }
+    }
+
+    fn parse_color(key: &str, value: &str) -> Result<Color, String> {
+        let color = match value.to_lowercase().as_str() {
+            "black" => Color::Black,
+            "dark_grey" => Color::DarkGrey,
+            "red" => Color::Red,
+            "dark_red" => Color::DarkRed,
+            "green" => Color::Green,
+            "dark_green" => Color::DarkGreen,
+            "yellow" => Color::Yellow,
+            "dark_yellow" => Color::DarkYellow,
+            "blue" => Color::Blue,
+            "dark_blue" => Color::DarkBlue,
+            "magenta" => Color::Magenta,
+            "dark_magenta" => Color::DarkMagenta,
+            "cyan" => Color::Cyan,
+            "dark_cyan" => Color::DarkCyan,
+            "white" => Color::White,
+            "grey" => Color::Grey,
+            // The length is in bytes, so the digits are checked before the channels are sliced out
+            i if i.starts_with('#') && i.len() == 7 => {
+                if !i[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("{} has an invalid hex color \"{}\"", key, value));
+                }
+
+                let channel = |n: usize| u8::from_str_radix(&i[n..n + 2], 16).unwrap();
+                Color::Rgb { r: channel(1), g: channel(3), b: channel(5) }
+            },
+            i => match i.parse::<u8>() {
+                Ok(n) => Color::AnsiValue(n),
+                Err(_) => return Err(format!(
+                    "{} has an unknown color \"{}\" (expected a color name like dark_red, a hex color like #ff8800, or a number from 0 to 255)",
+                    key, value
+                )),
+            },
+        };
+
+        Ok(color)
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct ThemeFile {
+        base: Option<String>,
+        #[serde(default)]
+        tiles: TilesFile,
+        #[serde(default)]
+        border: BorderFile,
+        #[serde(default)]
+        header: HeaderFile,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct GlyphFile {
+        glyph: Option<String>,
+        color: Option<String>,
+        background: Option<String>,
+        bold: Option<bool>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct TilesFile {
+        covered: Option<GlyphFile>,
+        empty: Option<GlyphFile>,
+        number: Option<String>,
+        numbers: Option<Vec<String>>,
+        mine: Option<GlyphFile>,
+        flag: Option<GlyphFile>,
+        wrong_flag: Option<GlyphFile>,
+        exploded: Option<GlyphFile>,
+        treasure: Option<GlyphFile>,
+        anti_mine: Option<GlyphFile>,
+        anti_flag: Option<GlyphFile>,
+        negative: Option<String>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct BorderFile {
+        horizontal: Option<String>,
+        vertical: Option<String>,
+        top_left: Option<String>,
+        top_right: Option<String>,
+        bottom_left: Option<String>,
+        bottom_right: Option<String>,
+        top_tee: Option<String>,
+        bottom_tee: Option<String>,
+        left_tee: Option<String>,
+        right_tee: Option<String>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(deny_unknown_fields)]
+    struct HeaderFile {
+        counter: Option<String>,
+        timer: Option<String>,
+        progress: Option<String>,
+        flag_mode: Option<GlyphFile>,
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn theme_file() {
+            let theme = Theme::from_toml(r##"
+                base = "dark"
+
+                [tiles]
+                covered = { glyph = "#", color = "#336699" }
+                number = "14"
+
+                [border]
+                vertical = "|"
+            "##).unwrap();
+
+            assert_eq!(theme.covered, Glyph::new("#", Some(Color::Rgb { r: 0x33, g: 0x66, b: 0x99 })));
+            assert_eq!(theme.numbers, [Some(Color::AnsiValue(14)); 8]);
+            assert_eq!(theme.border.vertical, '|');
+            assert_eq!(theme.mine, Theme::builtin(ThemeName::Dark).mine);
+
+            let theme = Theme::from_toml("[tiles]\nnumbers = [\"red\", \"blue\"]").unwrap();
+            assert_eq!(theme.numbers[..3], [Some(Color::Red), Some(Color::Blue), classic_numbers()[2]]);
+
+            assert_eq!(
+                Theme::from_toml("[border]\nvertical = \"||\""),
+                Err(String::from("border.vertical must be a single character, found \"||\""))
+            );
+            assert!(Theme::from_toml("[tiles]\nmine = { color = \"purpleish\" }").unwrap_err().starts_with("tiles.mine.color has an unknown color"));
+            assert!(Theme::from_toml("[tiles]\nmines = { color = \"red\" }").is_err());
+            assert!(Theme::from_toml("[tiles]\nmine = { color = \"#aé€\" }").unwrap_err().starts_with("tiles.mine.color has an invalid hex color"));
+
+            let theme = Theme::from_toml("base = \"high-contrast\"\n[tiles]\nflag = { bold = false }").unwrap();
+            assert_eq!(theme.flag, Glyph::new("F", Some(Color::Yellow)).on(Color::Black));
+            assert_eq!(theme.mine, Theme::builtin(ThemeName::HighContrast).mine);
+        }
+
+        #[test]
+        fn plain_theme() {
+            let theme = Theme::builtin(ThemeName::HighContrast).plain();
+
+            assert_eq!(theme.exploded, Glyph::new("*", None));
+            assert_eq!(theme.numbers, [None; 8]);
+            assert_eq!(theme.timer, None);
+            assert_eq!(theme.number(3).to_string(), "3");
+            assert_eq!(theme.flag.styled().to_string(), "F");
+            assert!(!theme.colored());
+        }
+
+        #[test]
+        fn capabilities() {
+            let detect = |vars: &[(&str, &str)]| {
+                let vars: Vec<(String, String)> = vars.iter().map(|i| (i.0.to_string(), i.1.to_string())).collect();
+                Capabilities::from_env(|key| vars.iter().find(|i| i.0 == key).map(|i| i.1.clone()))
+            };
+
+            assert_eq!(detect(&[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")]), Capabilities {
+                unicode: true,
+                colors: ColorDepth::Ansi256,
+            });
+            assert_eq!(detect(&[("TERM", "xterm"), ("COLORTERM", "truecolor")]).colors, ColorDepth::TrueColor);
+            assert_eq!(detect(&[("TERM", "xterm"), ("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]).unicode, false);
+            assert_eq!(detect(&[("TERM", "dumb")]), Capabilities {
+                unicode: false,
+                colors: ColorDepth::None,
+            });
+
+            let theme = Theme::builtin(ThemeName::Default).fit(Capabilities {
+                unicode: false,
+                colors: ColorDepth::Basic,
+            });
+
+            assert_eq!(theme.covered.symbol, "#");
+            assert_eq!(theme.flag.symbol, "F");
+            assert_eq!(theme.border.top_left, '+');
+            assert_eq!(reduce(Color::Rgb { r: 0x00, g: 0x80, b: 0x00 }, ColorDepth::Basic), Color::DarkGreen);
+            assert_eq!(reduce(Color::AnsiValue(196), ColorDepth::Basic), Color::Red);
+            assert_eq!(reduce(Color::Rgb { r: 0xff, g: 0x88, b: 0x00 }, ColorDepth::Ansi256), Color::AnsiValue(208));
+            assert_eq!(Theme::builtin(ThemeName::Dark).fit(detect(&[("TERM", "dumb")])), Theme::builtin(ThemeName::Dark).fit(Capabilities {
+                unicode: false,
+                colors: ColorDepth::TrueColor,
+            }).plain());
+        }
+    }
+}
+
+mod command {
+    use super::{Action, Motion, ThemeName};
+
+    // Coordinates are 1-based on the command line, like the rest of the user facing text
+    fn parse_coord(value: &str) -> Option<u16> {
+        value.parse::<u16>().ok()
+            .and_then(|i| i.checked_sub(1))
+    }
+
+    pub fn parse(line: &str) -> Result<Action, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let usage = match words.first() {
+            Some(&"seed") => "usage: seed [SEED]",
+            Some(&"save") => "usage: save NAME",
+            Some(&"theme") => "usage: theme NAME",
+            Some(&"goto") => "usage: goto X Y",
+            _ => "",
+        };
+
+        match &words[..] {
+            ["restart"] => Ok(Action::Restart(None)),
+            ["seed"] => Ok(Action::ShowSeed),
+            ["seed", seed] => match seed.parse() {
+                Ok(i) => Ok(Action::Restart(Some(i))),
+                Err(_) => Err(format!("invalid seed: {}", seed)),
+            },
+            ["save", name] => Ok(Action::Save(name.to_string())),
+            ["theme", name] => match name.parse() {
+                Ok(i) => Ok(Action::Theme(i)),
+                Err(_) => Err(format!("unknown theme: {} (expected one of: {})", name, ThemeName::variants().join(", ").to_lowercase())),
+            },
+            ["goto", x, y] => match (parse_coord(x), parse_coord(y)) {
+                (Some(x), Some(y)) => Ok(Action::Move(Motion::To(x, y))),
+                _ => Err(String::from(usage)),
+            },
+            ["uncover"] => Ok(Action::Uncover),
+            ["flag"] => Ok(Action::Flag),
+            ["flagmode"] => Ok(Action::ToggleFlagMode),
+            ["deductions"] => Ok(Action::ToggleDeductions),
+            ["hint"] => Ok(Action::Hint),
+            ["rewind"] => Ok(Action::Rewind),
+            ["history"] => Ok(Action::ToggleHistory),
+            ["overview"] => Ok(Action::ToggleOverview),
+            ["untag"] => Ok(Action::ClearTags),
+            ["next"] => Ok(Action::Move(Motion::NextCovered)),
+            ["q"] | ["quit"] => Ok(Action::Quit),
+            [] => Err(String::new()),
+            _ if !usage.is_empty() => Err(String::from(usage)),
+            _ => Err(format!("unknown command: {}", words[0])),
+        }
+    }
+
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn commands() {
+            assert_eq!(parse("goto 10 4"), Ok(Action::Move(Motion::To(9, 3))));
+            assert_eq!(parse("  seed 42 "), Ok(Action::Restart(Some(42))));
+            assert_eq!(parse("theme DARK"), Ok(Action::Theme(ThemeName::Dark)));
+            assert_eq!(parse("theme high-contrast"), Ok(Action::Theme(ThemeName::HighContrast)));
+            assert_eq!(parse("goto 0 4"), Err(String::from("usage: goto X Y")));
+            assert_eq!(parse("save"), Err(String::from("usage: save NAME")));
+            assert_eq!(parse("explode"), Err(String::from("unknown command: explode")));
+        }
+    }
+}
+
+mod board {
+    use rand::{thread_rng, Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use std::collections::VecDeque;
+    use std::fmt;
+    use std::mem;
+    use std::str::FromStr;
+
+    use crossterm::style::{style, Colorize, StyledContent, Styler};
+
+    use super::*;
+
+    const MANHATTAN: [(i32, i32); 8] = [
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+        (0, -1),
+        (0, 1),
+        (1, -1),
+        (1, 0),
+        (1, 1)
+    ];
+
+    fn get_2d(i: usize, width: usize) -> (usize, usize) {
+        (i % width, i / width)
+    }
+
+    fn get_1d(x: usize, y: usize, width: usize) -> usize {
+        y * width + x
+    }
+
+    fn get_1d_manhattan(i: usize, width: usize) -> Vec<usize> {
+        let (x, y) = get_2d(i, width);
+
+        MANHATTAN.iter()
+            .map(|i| (i.0 + x as i32, i.1 + y as i32))
+            .filter_map(|i| match i {
+                (x, y) if width as i32 > x && x >= 0 && y >= 0 => Some(
+                    get_1d(x as usize, y as usize, width)
+                ),
+                _ => None,
+            }).collect()
+    }
+
+    #[derive(PartialEq, Hash, Debug, Clone)]
+    enum State {
+        Uncovered,
+        Covered,
+        Flagged,
+        FlagRevealed,
+        WrongFlag,
+        Exploded,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum PushState {
+        Uncover,
+        Flag,
+    }
+
+    // What the player can see of a tile, for frontends that don't draw through a Theme
+    #[derive(PartialEq, Clone, Copy, Debug)]
+    pub enum Cell {
+        Covered,
+        Flagged,
+        Open(usize),
+        // A number below zero from anti-mines, without its sign
+        Negative(usize),
+        Mine,
+        WrongFlag,
+        Exploded,
+    }
+
+    // Everything a player could see of a board, and nothing else, for the code that has to play fair with it. Mines
+    // only show up in it once the game is over
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Visible {
+        pub width: usize,
+        pub height: usize,
+        pub cells: Vec<Cell>,
+        pub mines: usize,
+        pub flags: usize,
+        pub layers: usize,
+        pub lie_chance: usize,
+        // Anti-mines, stacked mines and rows still to scroll in all make numbers say less than they seem to
+        pub exact: bool,
+        pub won: Option<bool>,
+    }
+
+    impl Visible {
+        pub fn cell(&self, x: usize, y: usize) -> Cell {
+            self.cells[get_1d(x, y, self.width)]
+        }
+
+        // The same tiles Board::neighbors gives, as coordinates
+        pub fn neighbors(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+            let rows = self.height / self.layers;
+            let (layer, y) = (y / rows, y % rows);
+            let mut out = vec![];
+
+            for nl in layer.saturating_sub(1)..cmp::min(layer + 2, self.layers) {
+                for ny in y.saturating_sub(1)..cmp::min(y + 2, rows) {
+                    for nx in x.saturating_sub(1)..cmp::min(x + 2, self.width) {
+                        if (nx, ny, nl) != (x, y, layer) {
+                            out.push((nx, nl * rows + ny));
+                        }
+                    }
+                }
+            }
+
+            out
+        }
+
+        pub fn number_range(&self, n: usize) -> (usize, usize) {
+            number_range(self.lie_chance, n)
+        }
+    }
+
+    // The fewest and most mines that can be around a number showing n
+    fn number_range(lie_chance: usize, n: usize) -> (usize, usize) {
+        if lie_chance == 0 || n == 0 {
+            (n, n)
+        } else {
+            (cmp::max(n - 1, 1), n + 1)
+        }
+    }
+
+    // Marks pencilled onto covered tiles while working through a frontier. Nothing in the game reads them
+    #[derive(PartialEq, Hash, Debug, Clone, Copy)]
+    pub enum Pencil {
+        Mine,
+        Safe,
+        Guess(u8),
+    }
+
+    const PENCIL_GUESSES: [&str; 3] = ["1", "2", "3"];
+
+    const TENTHS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+    impl Pencil {
+        // The mark after this one when cycling, where the last guess goes back to no mark
+        pub fn next(mark: Option<Pencil>) -> Option<Pencil> {
+            match mark {
+                None => Some(Pencil::Mine),
+                Some(Pencil::Mine) => Some(Pencil::Safe),
+                Some(Pencil::Safe) => Some(Pencil::Guess(1)),
+                Some(Pencil::Guess(n)) if (n as usize) < PENCIL_GUESSES.len() => Some(Pencil::Guess(n + 1)),
+                Some(Pencil::Guess(_)) => None,
+            }
+        }
+
+        // Drawn in colors the deductions and numbers don't use, or with attributes in their place
+        fn glyph(self, colored: bool) -> StyledContent<&'static str> {
+            match self {
+                Pencil::Mine if colored => paint("!", Some(Color::Magenta)),
+                Pencil::Mine => style("!").bold(),
+                Pencil::Safe if colored => paint("-", Some(Color::Cyan)),
+                Pencil::Safe => style("-"),
+                Pencil::Guess(n) if colored => paint(PENCIL_GUESSES[n as usize - 1], Some(Color::Yellow)).underlined(),
+                Pencil::Guess(n) => style(PENCIL_GUESSES[n as usize - 1]).underlined(),
+            }
+        }
+    }
+
+    // Colors covered tiles can be tagged with, to group them the way regions get shaded in on paper
+    pub const TAGS: [(Color, &str); 4] = [
+        (Color::DarkBlue, "blue"),
+        (Color::DarkGreen, "green"),
+        (Color::DarkMagenta, "purple"),
+        (Color::DarkYellow, "yellow"),
+    ];
+
+    #[derive(PartialEq, Hash, Debug, Clone)]
+    pub struct Tile {
+        state: State,
+        mine: bool,
+        mines_surrounding: usize,
+        treasure: bool,
+        // How far off the number shown in liar mode is
+        lie: i8,
+        // Anti-mines are mines that take one away from the numbers around them
+        anti: bool,
+        anti_surrounding: usize,
+        // Flagged as an anti-mine
+        anti_flag: bool,
+        // Mines and flags past the first, when mines are stacked
+        extra: u8,
+        extra_flags: u8,
+        pencil: Option<Pencil>,
+        // An index into TAGS
+        tag: Option<usize>,
+    }
+
+    impl fmt::Display for Tile {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.glyph(&Theme::default()))
+        }
+    }
+
+    impl Tile {
+        fn glyph<'a>(&self, theme: &'a Theme) -> StyledContent<&'a str> {
+            match self.state {
+                State::Covered => theme.covered.styled(),
+                State::Uncovered if self.mine && self.anti => theme.anti_mine.styled(),
+                State::Uncovered if self.mine && self.extra > 0 => theme.mine.count(1 + self.extra as usize),
+                State::Uncovered if self.mine => theme.mine.styled(),
+                State::Uncovered if self.anti_surrounding > 0 => theme.net(self.net()),
+                State::Uncovered if self.mines_surrounding > 0 && self.treasure => {
+                    theme.number(self.shown()).underlined()
+                },
+                State::Uncovered if self.mines_surrounding > 0 => {
+                    theme.number(self.shown())
+                },
+                State::Uncovered if self.treasure => theme.treasure.styled(),
+                State::Uncovered => theme.empty.styled(),
+                State::Flagged | State::FlagRevealed if self.anti_flag => theme.anti_flag.styled(),
+                State::Flagged | State::FlagRevealed if self.extra_flags > 0 => theme.flag.count(1 + self.extra_flags as usize),
+                State::Flagged => theme.flag.styled(),
+                State::FlagRevealed => theme.flag.styled(),
+                State::WrongFlag => theme.wrong_flag.styled(),
+                State::Exploded => theme.exploded.styled(),
+            }
+        }
+
+        fn cell(&self) -> Cell {
+            match self.state {
+                State::Covered => Cell::Covered,
+                State::Uncovered if self.mine => Cell::Mine,
+                State::Uncovered if self.net() < 0 => Cell::Negative(-self.net() as usize),
+                State::Uncovered if self.anti_surrounding > 0 => Cell::Open(self.net() as usize),
+                State::Uncovered => Cell::Open(self.shown()),
+                State::Flagged | State::FlagRevealed => Cell::Flagged,
+                State::WrongFlag => Cell::WrongFlag,
+                State::Exploded => Cell::Exploded,
+            }
+        }
+
+        fn new(mine: &bool, mines_surrounding: &usize) -> Tile {
+            Tile {
+                state: State::Covered,
+                mine: *mine,
+                mines_surrounding: *mines_surrounding,
+                treasure: false,
+                lie: 0,
+                anti: false,
+                anti_surrounding: 0,
+                anti_flag: false,
+                extra: 0,
+                extra_flags: 0,
+                pencil: None,
+                tag: None,
+            }
+        }
+
+        // Mines minus anti-mines around the tile, which can go below zero
+        fn net(&self) -> i64 {
+            self.mines_surrounding as i64 - 2 * self.anti_surrounding as i64
+        }
+
+        // What the number asks the flags around it to add up to before chording
+        fn value(&self) -> i64 {
+            if self.anti_surrounding > 0 { self.net() } else { self.shown() as i64 }
+        }
+
+        // Whether a flag here is on a mine of the kind it marks, stacked as high as the mines are
+        fn flag_fits(&self) -> bool {
+            self.mine && self.anti == self.anti_flag && self.extra == self.extra_flags
+        }
+
+        // How much a flag here counts towards the numbers around it
+        fn flag_value(&self) -> i64 {
+            if self.anti_flag { -1 } else { 1 + self.extra_flags as i64 }
+        }
+
+        // The number the player sees. Blanks never lie and numbers never lie down to a blank, so openings still
+        // open on their own
+        fn shown(&self) -> usize {
+            if self.mines_surrounding == 0 || self.lie == 0 {
+                self.mines_surrounding
+            } else {
+                (self.mines_surrounding as i64 + self.lie as i64).max(1).min(8) as usize
+            }
+        }
+    }
+
+    #[derive(PartialEq, Clone)]
+    pub struct Board {
+        pub tiles: Vec<Tile>,
+        pub won: Option<bool>,
+        width: usize,
+        pub mine_total: usize,
+        pub flag_total: usize,
+        pub no_flag: bool,
+        pub auto_flag: bool,
+        pub hardcore: bool,
+        pub strict_flags: bool,
+        pub assisted: bool,
+        pub auto_flagged: usize,
+        pub flags_removed: usize,
+        final_bv: Option<usize>,
+        final_treasure: Option<usize>,
+        // Numbers next to treasure that hasn't been found are drawn in the treasure color
+        pub treasure_hints: bool,
+        // The percent of numbers that are off by one in liar mode
+        pub lie_chance: usize,
+        pub anti_total: usize,
+        // Mines stacked on tiles that already have one, on top of mine_total
+        pub stacked: usize,
+        flag_correct: usize,
+        first_uncover: bool,
+        pub seed: u64,
+        pub losing_tile: Option<(usize, usize)>,
+        rng: StdRng,
+        edges: Option<Edges>,
+        // Seconds already played when the board was saved, for the clock to carry on from
+        pub elapsed: u64,
+        // How many layers the rows are split into, with tiles touching the ones in the same place on the layers
+        // above and below
+        layers: usize,
+        // Shows how many mines are left in every row and column, picross style
+        pub line_hints: bool,
+        pub generation: Generation,
+    }
+
+    // Rules for where mines can go when a board is generated, which a new board of the same kind keeps
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Generation {
+        // Keeps the outermost ring of tiles free of mines
+        pub no_border_mines: bool,
+        // Mirrors every mine onto the other side of the board
+        pub symmetry: Option<Symmetry>,
+        // The fewest blank tiles in one patch the board has to have, with the first uncover landing in it
+        pub min_opening: usize,
+    }
+
+    // How many layouts are tried for one with a big enough opening before giving up
+    const OPENING_ATTEMPTS: usize = 1000;
+
+    impl Generation {
+        fn allows_mine(&self, i: usize, width: usize, height: usize) -> bool {
+            let (x, y) = get_2d(i, width);
+
+            !self.no_border_mines || (x > 0 && y > 0 && x + 1 < width && y + 1 < height)
+        }
+
+        // The tile a mine on tile i is mirrored onto, which is i itself without symmetry or on the middle line
+        fn mirror(&self, i: usize, width: usize, height: usize) -> usize {
+            let (x, y) = get_2d(i, width);
+
+            match self.symmetry {
+                None => i,
+                Some(Symmetry::Horizontal) => get_1d(width - 1 - x, y, width),
+                Some(Symmetry::Vertical) => get_1d(x, height - 1 - y, width),
+                Some(Symmetry::Rotational) => get_1d(width - 1 - x, height - 1 - y, width),
+            }
+        }
+
+        // The tiles that have to hold a mine together: a tile and its mirror, or just the tile when it is its own
+        fn orbit(&self, i: usize, width: usize, height: usize) -> Vec<usize> {
+            let mirror = self.mirror(i, width, height);
+
+            if mirror == i { vec![i] } else { vec![i, mirror] }
+        }
+
+        // Picks mine_num of the candidate tiles for the mines
+        fn place(&self, candidates: &[usize], mine_num: usize, width: usize, height: usize, rng: &mut StdRng) -> Result<Vec<bool>, String> {
+            let mut mine_values = vec![false; width * height];
+
+            if let Some(symmetry) = self.symmetry {
+                let (mut singles, mut pairs): (Vec<_>, Vec<_>) = candidates.iter()
+                    .map(|i| self.orbit(*i, width, height))
+                    .filter(|orbit| orbit[0] <= *orbit.last().unwrap())
+                    .partition(|orbit| orbit.len() == 1);
+
+                // Mines come in mirrored pairs, so an odd count needs one on the middle line, and any that don't fit
+                // in pairs have to go there too. Past that the middle line gets about its share of the mines
+                let odd = mine_num % 2;
+                let least = cmp::max(odd, mine_num.saturating_sub(pairs.len() * 2));
+                let most = cmp::min(singles.len(), mine_num);
+                let most = if most % 2 == odd { most } else { most.saturating_sub(1) };
+
+                if least > most {
+                    return Err(format!("An odd number of mines needs a middle {} to put the unmirrored mine on", symmetry.middle()));
+                }
+
+                let share = mine_num * singles.len() / candidates.len();
+                let share = if share % 2 == odd { share } else { share + 1 };
+                let on_middle = cmp::max(least, cmp::min(most, share));
+
+                singles.shuffle(rng);
+                pairs.shuffle(rng);
+
+                for i in singles.iter().take(on_middle).chain(pairs.iter().take((mine_num - on_middle) / 2)).flatten() {
+                    mine_values[*i] = true;
+                }
+
+            } else {
+                let mut placed = vec![true; mine_num];
+                placed.extend(vec![false; candidates.len() - mine_num]);
+                placed.shuffle(rng);
+
+                for (i, mine) in candidates.iter().zip(placed) {
+                    mine_values[*i] = mine;
+                }
+            }
+
+            Ok(mine_values)
+        }
+    }
+
+    // Horizontal mirrors the left half of the board onto the right, vertical the top half onto the bottom, and
+    // rotational turns the board half way round
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Symmetry {
+        Horizontal,
+        Vertical,
+        Rotational,
+    }
+
+    impl Symmetry {
+        pub fn variants() -> [&'static str; 3] {
+            ["horizontal", "vertical", "rotational"]
+        }
+
+        // Where a lone mine can go when there is an odd number of them
+        fn middle(&self) -> &'static str {
+            match *self {
+                Symmetry::Horizontal => "column",
+                Symmetry::Vertical => "row",
+                Symmetry::Rotational => "tile",
+            }
+        }
+    }
+
+    impl fmt::Display for Symmetry {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let name = match *self {
+                Symmetry::Horizontal => "horizontal",
+                Symmetry::Vertical => "vertical",
+                Symmetry::Rotational => "rotational",
+            };
+
+            write!(f, "{}", name)
+        }
+    }
+
+    impl FromStr for Symmetry {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Symmetry, String> {
+            match s.to_lowercase().as_str() {
+                "horizontal" => Ok(Symmetry::Horizontal),
+                "vertical" => Ok(Symmetry::Vertical),
+                "rotational" => Ok(Symmetry::Rotational),
+                _ => Err(format!("unknown symmetry: {}", s)),
+            }
+        }
+    }
+
+    // Boards end up in logs and test failures, so until the game is over their Debug output only has what the
+    // player can see. The seed is left out too, since the mines can be made again from it
+    impl fmt::Debug for Board {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.won.is_some() {
+                return self.spoilers().fmt(f);
+            }
+
+            f.debug_struct("Board")
+                .field("visible", &self.visible_state())
+                .finish_non_exhaustive()
+        }
+    }
+
+    // Everything on a board, mines included, for when that is really what is wanted
+    pub struct Spoilers<'a>(&'a Board);
+
+    impl fmt::Debug for Spoilers<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let board = self.0;
+
+            f.debug_struct("Board")
+                .field("tiles", &board.tiles)
+                .field("won", &board.won)
+                .field("width", &board.width)
+                .field("mine_total", &board.mine_total)
+                .field("flag_total", &board.flag_total)
+                .field("no_flag", &board.no_flag)
+                .field("auto_flag", &board.auto_flag)
+                .field("hardcore", &board.hardcore)
+                .field("strict_flags", &board.strict_flags)
+                .field("assisted", &board.assisted)
+                .field("auto_flagged", &board.auto_flagged)
+                .field("flags_removed", &board.flags_removed)
+                .field("final_bv", &board.final_bv)
+                .field("final_treasure", &board.final_treasure)
+                .field("treasure_hints", &board.treasure_hints)
+                .field("lie_chance", &board.lie_chance)
+                .field("anti_total", &board.anti_total)
+                .field("stacked", &board.stacked)
+                .field("flag_correct", &board.flag_correct)
+                .field("first_uncover", &board.first_uncover)
+                .field("seed", &board.seed)
+                .field("losing_tile", &board.losing_tile)
+                .field("rng", &board.rng)
+                .field("edges", &board.edges)
+                .field("elapsed", &board.elapsed)
+                .field("layers", &board.layers)
+                .field("line_hints", &board.line_hints)
+                .field("generation", &board.generation)
+                .finish()
+        }
+    }
+
+    // The rows just past the top and bottom of a board that scrolls in rush mode. They are counted in the numbers
+    // next to them, so numbers stay the same as rows come and go
+    #[derive(PartialEq, Debug, Clone)]
+    struct Edges {
+        above: Vec<bool>,
+        below: Vec<bool>,
+        density: f64,
+    }
+
+    // Adds every mine to the tiles around it, which only touches the neighbors of mines instead of looking around
+    // every tile on the board
+    fn count_surrounding(mine_values: &[bool], width: usize) -> Vec<usize> {
+        let mut counts = vec![0; mine_values.len()];
+
+        for i in (0..mine_values.len()).filter(|i| mine_values[*i]) {
+            let (x, y) = get_2d(i, width);
+
+            for (dx, dy) in MANHATTAN.iter() {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+
+                if nx >= 0 && nx < width as i32 && ny >= 0 {
+                    if let Some(n) = counts.get_mut(get_1d(nx as usize, ny as usize, width)) {
+                        *n += 1;
+                    }
+                }
+            }
+        }
+
+        counts
+    }
+
+    // How many blank tiles are in the patch each tile is part of, or 0 for numbers and mines
+    fn opening_sizes(mines: &[bool], width: usize) -> Vec<usize> {
+        let counts = count_surrounding(mines, width);
+        let blank = |i: usize| !mines[i] && counts[i] == 0;
+        let mut sizes = vec![0; mines.len()];
+
+        for start in 0..mines.len() {
+            if sizes[start] > 0 || !blank(start) {
+                continue;
+            }
+
+            let mut patch = vec![start];
+            sizes[start] = 1;
+            let mut next = 0;
+
+            while let Some(&i) = patch.get(next) {
+                for n in get_1d_manhattan(i, width) {
+                    if n < mines.len() && sizes[n] == 0 && blank(n) {
+                        sizes[n] = 1;
+                        patch.push(n);
+                    }
+                }
+
+                next += 1;
+            }
+
+            for i in &patch {
+                sizes[*i] = patch.len();
+            }
+        }
+
+        sizes
+    }
+
+    impl Board {
+        pub fn new(width: usize, height: usize, mine_num: usize) -> Result<Board, String> {
+            Board::with_seed(width, height, mine_num, thread_rng().gen())
+        }
+
+        pub fn with_seed(width: usize, height: usize, mine_num: usize, seed: u64) -> Result<Board, String> {
+            Board::generate(width, height, mine_num, seed, Generation::default())
+        }
+
+        // Mines are shuffled over the tiles the rules allow them on, which is every tile by default, so the same seed
+        // always gives the same board
+        pub fn generate(width: usize, height: usize, mine_num: usize, seed: u64, generation: Generation) -> Result<Board, String> {
+            let total = width * height;
+
+            if total < mine_num {
+                return Err(String::from("There cannot be more mines then there are tiles"));
+
+            } else if total == mine_num {
+                return Err(String::from("At least one tile must be safe"));
+            }
+
+            let candidates: Vec<usize> = (0..total).filter(|i| generation.allows_mine(*i, width, height)).collect();
+
+            if candidates.len() < mine_num {
+                return Err(format!("Keeping mines off the border leaves room for {} of the {} mines", candidates.len(), mine_num));
+            }
+
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut mine_values = generation.place(&candidates, mine_num, width, height, &mut rng)?;
+            let mut attempts = 1;
+
+            // Layouts without a big enough opening are thrown away for the next one from the same generator, so the
+            // seed still decides the board
+            while generation.min_opening > 0 && opening_sizes(&mine_values, width).into_iter().max() < Some(generation.min_opening) {
+                if attempts == OPENING_ATTEMPTS {
+                    return Err(format!("No board with an opening of {} tiles turned up in {} tries, try fewer mines or a smaller opening", generation.min_opening, attempts));
+                }
+
+                mine_values = generation.place(&candidates, mine_num, width, height, &mut rng)?;
+                attempts += 1;
+            }
+
+            let mut board = Board::from_layout(width, &mine_values)?;
+            board.first_uncover = true;
+            board.seed = seed;
+            board.rng = rng;
+            board.generation = generation;
+
+            Ok(board)
+        }
+
+        // A board with the mines exactly where they are given, row by row, for puzzles, imports and tests. Nothing
+        // is moved out of the way of the first uncover
+        pub fn from_layout(width: usize, mines: &[bool]) -> Result<Board, String> {
+            if width == 0 || mines.is_empty() {
+                return Err(String::from("the board is empty"));
+            } else if mines.len() % width != 0 {
+                return Err(format!("{} tiles don't make rows of {}", mines.len(), width));
+            } else if mines.iter().all(|i| *i) {
+                return Err(String::from("At least one tile must be safe"));
+            }
+
+            let mine_totals = count_surrounding(mines, width);
+            let tiles: Vec<_> = mines.iter().zip(mine_totals.iter()).map(|i| Tile::new(i.0, i.1)).collect();
+
+            Ok(Board {
+                tiles: tiles,
+                width: width,
+                mine_total: mines.iter().filter(|i| **i).count(),
+                flag_total: 0,
+                no_flag: false,
+                auto_flag: false,
+                hardcore: false,
+                strict_flags: false,
+                assisted: false,
+                auto_flagged: 0,
+                flags_removed: 0,
+                final_bv: None,
+                final_treasure: None,
+                treasure_hints: false,
+                lie_chance: 0,
+                anti_total: 0,
+                stacked: 0,
+                flag_correct: 0,
+                won: None,
+                first_uncover: false,
+                seed: 0,
+                losing_tile: None,
+                rng: StdRng::seed_from_u64(0),
+                edges: None,
+                elapsed: 0,
+                layers: 1,
+                line_hints: false,
+                generation: Generation::default(),
+            })
+        }
+
+        pub fn from_save(save: &str) -> Result<Board, String> {
+            let mut lines = seal::strip(save).lines();
+
+            if lines.next() != Some("rs-minesweeper save") {
+                return Err(String::from("not a save file"));
+            }
+
+            let mut width = None;
+            let mut seed = 0;
+            let mut no_flag = false;
+            let mut hardcore = false;
+            let mut strict_flags = false;
+            let mut first_uncover = true;
+            let mut lie_chance = 0;
+            let mut lies = None;
+            let mut anti = None;
+            let mut stacks = None;
+            let mut layers = 1;
+            let mut elapsed = 0;
+            let mut line_hints = false;
+            let mut generation = Generation::default();
+
+            for line in lines.by_ref() {
+                if line.is_empty() {
+                    break;
+                }
+
+                let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                let invalid = || format!("invalid value for {}: {}", key, value);
+
+                match key {
+                    "width" => width = Some(value.parse::<usize>().map_err(|_| invalid())?),
+                    "seed" => seed = value.parse().map_err(|_| invalid())?,
+                    "no-flag" => no_flag = value.parse().map_err(|_| invalid())?,
+                    "hardcore" => hardcore = value.parse().map_err(|_| invalid())?,
+                    "strict-flags" => strict_flags = value.parse().map_err(|_| invalid())?,
+                    "first-uncover" => first_uncover = value.parse().map_err(|_| invalid())?,
+                    "lie-chance" => lie_chance = value.parse().map_err(|_| invalid())?,
+                    "layers" => layers = value.parse().map_err(|_| invalid())?,
+                    "time" => elapsed = value.parse().map_err(|_| invalid())?,
+                    "line-hints" => line_hints = value.parse().map_err(|_| invalid())?,
+                    "no-border-mines" => generation.no_border_mines = value.parse().map_err(|_| invalid())?,
+                    "symmetry" => generation.symmetry = Some(value.parse().map_err(|_| invalid())?),
+                    "min-opening" => generation.min_opening = value.parse().map_err(|_| invalid())?,
+                    "lies" => lies = Some(value.chars().map(|c| match c {
+                        '-' => Ok(-1),
+                        '.' => Ok(0),
+                        '+' => Ok(1),
+                        _ => Err(invalid()),
+                    }).collect::<Result<Vec<i8>, String>>()?),
+                    // Which mines are anti-mines and which flags mark anti-mines
+                    "anti" => anti = Some(value.chars().map(|c| match c {
+                        '.' => Ok((false, false)),
+                        'a' => Ok((true, false)),
+                        'f' => Ok((false, true)),
+                        'A' => Ok((true, true)),
+                        _ => Err(invalid()),
+                    }).collect::<Result<Vec<(bool, bool)>, String>>()?),
+                    // How many mines and flags each tile has past the first, as two digits
+                    "stacks" => stacks = Some(value.split(',').map(|i| {
+                        let digits: Vec<u8> = i.bytes().map(|b| b.wrapping_sub(b'0')).collect();
+
+                        match digits[..] {
+                            [mines, flags] if mines < 3 && flags < 3 => Ok((mines, flags)),
+                            _ => Err(invalid()),
+                        }
+                    }).collect::<Result<Vec<(u8, u8)>, String>>()?),
+                    _ => return Err(format!("unknown field: {}", key)),
+                }
+            }
+
+            let width = width.ok_or_else(|| String::from("missing field: width"))?;
+            let mut mine_values = Vec::new();
+            let mut states = Vec::new();
+            let mut treasure = Vec::new();
+
+            for (row, line) in lines.enumerate() {
+                if line.chars().count() != width {
+                    return Err(format!("row {} is not {} tiles wide", row + 1, width));
+                }
+
+                for c in line.chars() {
+                    let (state, mine) = match c {
+                        '.' | '$' => (State::Covered, false),
+                        '*' => (State::Covered, true),
+                        'f' | 't' => (State::Flagged, false),
+                        'F' => (State::Flagged, true),
+                        'o' | 'O' => (State::Uncovered, false),
+                        _ => return Err(format!("unknown tile '{}' in row {}", c, row + 1)),
+                    };
+
+                    mine_values.push(mine);
+                    states.push(state);
+                    treasure.push(matches!(c, '$' | 't' | 'O'));
+                }
+            }
+
+            if width == 0 || mine_values.is_empty() {
+                return Err(String::from("the board is empty"));
+            } else if mine_values.iter().all(|i| *i) {
+                return Err(String::from("At least one tile must be safe"));
+            } else if layers == 0 || (mine_values.len() / width) % layers != 0 {
+                return Err(format!("the rows don't split evenly into {} layers", layers));
+            }
+
+            let mine_totals = count_surrounding(&mine_values, width);
+
+            let mut tiles: Vec<_> = mine_values.iter().zip(mine_totals.iter()).zip(states).zip(treasure)
+                .map(|i| Tile {
+                    state: (i.0).1,
+                    mine: *((i.0).0).0,
+                    mines_surrounding: *((i.0).0).1,
+                    treasure: i.1,
+                    lie: 0,
+                    anti: false,
+                    anti_surrounding: 0,
+                    anti_flag: false,
+                    extra: 0,
+                    extra_flags: 0,
+                    pencil: None,
+                    tag: None,
+                }).collect();
+
+            if let Some(lies) = lies {
+                if lies.len() != tiles.len() {
+                    return Err(format!("there are {} lies for {} tiles", lies.len(), tiles.len()));
+                }
+
+                for (tile, lie) in tiles.iter_mut().zip(lies) {
+                    tile.lie = lie;
+                }
+            }
+
+            if let Some(anti) = anti {
+                if anti.len() != tiles.len() {
+                    return Err(format!("there are {} anti-mine markings for {} tiles", anti.len(), tiles.len()));
+                }
+
+                for (i, (anti, anti_flag)) in anti.into_iter().enumerate() {
+                    if anti && !tiles[i].mine {
+                        return Err(format!("anti-mine without a mine in row {}", i / width + 1));
+                    }
+
+                    tiles[i].anti = anti;
+                    tiles[i].anti_flag = anti_flag && tiles[i].state == State::Flagged;
+
+                    if anti {
+                        for n in get_1d_manhattan(i, width) {
+                            if let Some(t) = tiles.get_mut(n) {
+                                t.anti_surrounding += 1;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(stacks) = stacks {
+                if stacks.len() != tiles.len() {
+                    return Err(format!("there are {} stacks for {} tiles", stacks.len(), tiles.len()));
+                }
+
+                for (i, (extra, extra_flags)) in stacks.into_iter().enumerate() {
+                    if extra > 0 && !tiles[i].mine {
+                        return Err(format!("stacked mines without a mine in row {}", i / width + 1));
+                    }
+
+                    tiles[i].extra = extra;
+                    tiles[i].extra_flags = if tiles[i].state == State::Flagged { extra_flags } else { 0 };
+
+                    for n in get_1d_manhattan(i, width) {
+                        if let Some(t) = tiles.get_mut(n) {
+                            t.mines_surrounding += extra as usize;
+                        }
+                    }
+                }
+            }
+
+            let flag_total = tiles.iter().filter(|i| i.state == State::Flagged).map(|i| 1 + i.extra_flags as usize).sum();
+            let flag_correct = tiles.iter().filter(|i| i.state == State::Flagged && i.flag_fits()).count();
+            let anti_total = tiles.iter().filter(|i| i.anti).count();
+            let stacked = tiles.iter().map(|i| i.extra as usize).sum();
+
+            let mut board = Board {
+                width: width,
+                mine_total: mine_values.iter().filter(|i| **i).count(),
+                flag_total: flag_total,
+                no_flag: no_flag,
+                auto_flag: false,
+                hardcore: hardcore,
+                strict_flags: strict_flags,
+                assisted: false,
+                auto_flagged: 0,
+                flags_removed: 0,
+                final_bv: None,
+                final_treasure: None,
+                treasure_hints: false,
+                lie_chance: lie_chance,
+                anti_total: anti_total,
+                stacked: stacked,
+                flag_correct: flag_correct,
+                won: None,
+                first_uncover: first_uncover,
+                seed: seed,
+                losing_tile: None,
+                rng: StdRng::seed_from_u64(seed),
+                edges: None,
+                elapsed: elapsed,
+                layers: 1,
+                line_hints: line_hints,
+                generation: generation,
+                tiles: tiles,
+            };
+
+            if layers > 1 {
+                board.split_layers(layers);
+            }
+
+            Ok(board)
+        }
+
+        pub fn to_save(&self) -> String {
+            let mut save = format!(
+                "rs-minesweeper save\nwidth {}\nseed {}\nno-flag {}\nhardcore {}\nstrict-flags {}\nfirst-uncover {}\n\n",
+                self.width, self.seed, self.no_flag, self.hardcore, self.strict_flags, self.first_uncover,
+            );
+
+            if self.lie_chance > 0 {
+                let lies: String = self.tiles.iter().map(|i| match i.lie {
+                    -1 => '-',
+                    1 => '+',
+                    _ => '.',
+                }).collect();
+
+                save.insert_str(save.len() - 1, &format!("lie-chance {}\nlies {}\n", self.lie_chance, lies));
+            }
+
+            if self.anti_total > 0 {
+                let anti: String = self.tiles.iter().map(|i| match (i.anti, i.anti_flag) {
+                    (false, false) => '.',
+                    (true, false) => 'a',
+                    (false, true) => 'f',
+                    (true, true) => 'A',
+                }).collect();
+
+                save.insert_str(save.len() - 1, &format!("anti {}\n", anti));
+            }
+
+            if self.layers > 1 {
+                save.insert_str(save.len() - 1, &format!("layers {}\n", self.layers));
+            }
+
+            if self.elapsed > 0 {
+                save.insert_str(save.len() - 1, &format!("time {}\n", self.elapsed));
+            }
+
+            if self.line_hints {
+                save.insert_str(save.len() - 1, "line-hints true\n");
+            }
+
+            if self.generation.no_border_mines {
+                save.insert_str(save.len() - 1, "no-border-mines true\n");
+            }
+
+            if let Some(symmetry) = self.generation.symmetry {
+                save.insert_str(save.len() - 1, &format!("symmetry {}\n", symmetry));
+            }
+
+            if self.generation.min_opening > 0 {
+                save.insert_str(save.len() - 1, &format!("min-opening {}\n", self.generation.min_opening));
+            }
+
+            if self.stacked > 0 {
+                let stacks: Vec<String> = self.tiles.iter().map(|i| format!("{}{}", i.extra, i.extra_flags)).collect();
+                save.insert_str(save.len() - 1, &format!("stacks {}\n", stacks.join(",")));
+            }
+
+            for row in self.tiles.chunks(self.width) {
+                save.extend(row.iter().map(|i| match (&i.state, i.mine) {
+                    (State::Covered, false) if i.treasure => '$',
+                    (State::Flagged, false) if i.treasure => 't',
+                    (State::Uncovered, false) if i.treasure => 'O',
+                    (State::Covered, false) => '.',
+                    (State::Covered, true) => '*',
+                    (State::Flagged, false) | (State::WrongFlag, _) => 'f',
+                    (State::Flagged, true) | (State::FlagRevealed, _) => 'F',
+                    (State::Uncovered, _) | (State::Exploded, _) => 'o',
+                }));
+                save.push('\n');
+            }
+
+            save
+        }
+
+        // A new board of the same size and rules
+        pub fn regenerate(&self, seed: u64) -> Result<Board, String> {
+            let mut board = Board::generate(self.width, self.height(), self.mine_total, seed, self.generation)?;
+            board.split_layers(self.layers);
+            board.no_flag = self.no_flag;
+            board.auto_flag = self.auto_flag;
+            board.hardcore = self.hardcore;
+            board.strict_flags = self.strict_flags;
+            board.treasure_hints = self.treasure_hints;
+            board.place_treasure(self.treasure().1);
+            board.add_lies(self.lie_chance);
+            board.add_anti_mines(self.anti_total);
+            board.stack_mines(self.stacked);
+            board.line_hints = self.line_hints;
+
+            if self.edges.is_some() {
+                board.start_rush();
+            }
+
+            Ok(board)
+        }
+
+        // Splits the rows into layers stacked on top of each other, so every tile also touches the 9 tiles around the
+        // same place on the layers above and below. The rows have to split evenly
+        pub fn split_layers(&mut self, layers: usize) {
+            self.layers = layers;
+
+            for i in 0..self.tiles.len() {
+                let around = self.neighbors(i);
+                let mines = around.iter().map(|n| &self.tiles[*n]).filter(|t| t.mine).map(|t| 1 + t.extra as usize).sum();
+                let anti = around.iter().filter(|n| self.tiles[**n].anti).count();
+
+                self.tiles[i].mines_surrounding = mines;
+                self.tiles[i].anti_surrounding = anti;
+            }
+        }
+
+        pub fn layers(&self) -> usize {
+            self.layers
+        }
+
+        // Rows in every layer, which is every row on a flat board
+        pub fn layer_height(&self) -> usize {
+            self.height() / self.layers
+        }
+
+        // The tiles touching a tile, which on a board with layers includes the ones on the layers above and below
+        pub fn neighbors(&self, i: usize) -> Vec<usize> {
+            if self.layers == 1 {
+                return get_1d_manhattan(i, self.width).into_iter().filter(|n| *n < self.tiles.len()).collect();
+            }
+
+            let rows = self.layer_height();
+            let (x, y) = get_2d(i, self.width);
+            let (layer, y) = (y / rows, y % rows);
+            let mut out = vec![];
+
+            for nl in layer.saturating_sub(1)..cmp::min(layer + 2, self.layers) {
+                for ny in y.saturating_sub(1)..cmp::min(y + 2, rows) {
+                    for nx in x.saturating_sub(1)..cmp::min(x + 2, self.width) {
+                        if (nx, ny, nl) != (x, y, layer) {
+                            out.push(get_1d(nx, nl * rows + ny, self.width));
+                        }
+                    }
+                }
+            }
+
+            out
+        }
+
+        // Makes the board scroll in rush mode, with new rows as dense with mines as the board is now
+        pub fn start_rush(&mut self) {
+            let density = self.mine_total as f64 / self.tiles.len() as f64;
+            let below = self.random_row(density);
+
+            self.edges = Some(Edges {
+                above: vec![false; self.width],
+                below: below,
+                density: density,
+            });
+            self.recount();
+        }
+
+        pub fn rushing(&self) -> bool {
+            self.edges.is_some()
+        }
+
+        fn random_row(&mut self, density: f64) -> Vec<bool> {
+            let rng = &mut self.rng;
+            (0..self.width).map(|_| rng.gen_bool(density)).collect()
+        }
+
+        // Drops the top row and brings in a covered one at the bottom. Returns how many mines went off the top
+        // without a flag on them
+        pub fn scroll(&mut self) -> usize {
+            let density = match &self.edges {
+                Some(i) => i.density,
+                None => return 0,
+            };
+
+            let below = self.random_row(density);
+            let top: Vec<Tile> = self.tiles.drain(..self.width).collect();
+            let edges = self.edges.as_mut().unwrap();
+
+            edges.above = top.iter().map(|i| i.mine).collect();
+            let incoming = mem::replace(&mut edges.below, below);
+            self.tiles.extend(incoming.iter().map(|i| Tile::new(i, &0)));
+
+            self.mine_total = self.tiles.iter().filter(|i| i.mine).count();
+            self.flag_total = self.tiles.iter().filter(|i| i.state == State::Flagged).count();
+            self.flag_correct = self.tiles.iter().filter(|i| i.state == State::Flagged && i.flag_fits()).count();
+            self.recount();
+
+            top.iter().filter(|i| i.mine && i.state != State::Flagged).count()
+        }
+
+        // Counts the mines around every tile again, including the rows past the edges
+        fn recount(&mut self) {
+            let edges = match &self.edges {
+                Some(i) => i,
+                None => return,
+            };
+
+            let mines: Vec<bool> = edges.above.iter().cloned()
+                .chain(self.tiles.iter().map(|i| i.mine))
+                .chain(edges.below.iter().cloned())
+                .collect();
+
+            let counts = count_surrounding(&mines, self.width);
+
+            for (tile, count) in self.tiles.iter_mut().zip(&counts[self.width..]) {
+                tile.mines_surrounding = *count;
+            }
+        }
+
+        // Ends the game as lost without a tile to blame, for running out of lives in rush mode
+        pub fn lose(&mut self) {
+            self.end_game(false);
+        }
+
+        // Adds mines on top of tiles that already have one, up to 3 on a tile and as many as there is room for.
+        // Has to come before the first uncover
+        pub fn stack_mines(&mut self, count: usize) {
+            for _ in 0..count {
+                let mines: Vec<usize> = self.tiles.iter().enumerate()
+                    .filter(|i| i.1.mine && i.1.extra < 2)
+                    .map(|i| i.0)
+                    .collect();
+
+                let i = match mines.choose(&mut self.rng) {
+                    Some(i) => *i,
+                    None => return,
+                };
+
+                self.tiles[i].extra += 1;
+                self.stacked += 1;
+
+                for n in self.neighbors(i) {
+                    if let Some(t) = self.tiles.get_mut(n) {
+                        t.mines_surrounding += 1;
+                    }
+                }
+            }
+        }
+
+        // Every mine, counting each one on a stacked tile
+        pub fn mines(&self) -> usize {
+            self.mine_total + self.stacked
+        }
+
+        // Turns some of the mines into anti-mines. Has to come before the first uncover
+        pub fn add_anti_mines(&mut self, count: usize) {
+            let mut mines: Vec<usize> = self.tiles.iter().enumerate()
+                .filter(|i| i.1.mine && !i.1.anti)
+                .map(|i| i.0)
+                .collect();
+            mines.shuffle(&mut self.rng);
+
+            for i in mines.into_iter().take(count) {
+                self.tiles[i].anti = true;
+                self.anti_total += 1;
+
+                for n in self.neighbors(i) {
+                    if let Some(t) = self.tiles.get_mut(n) {
+                        t.anti_surrounding += 1;
+                    }
+                }
+            }
+        }
+
+        // Makes the given percent of numbers show one more or one less than they should
+        pub fn add_lies(&mut self, chance: usize) {
+            self.lie_chance = chance;
+
+            if chance == 0 {
+                return;
+            }
+
+            for tile in self.tiles.iter_mut().filter(|i| !i.mine) {
+                if self.rng.gen_range(0..100) < chance {
+                    tile.lie = if self.rng.gen() { 1 } else { -1 };
+                }
+            }
+        }
+
+        // How many of the opened numbers are off
+        pub fn lies(&self) -> usize {
+            self.tiles.iter()
+                .filter(|i| i.state == State::Uncovered && !i.mine && i.shown() != i.mines_surrounding)
+                .count()
+        }
+
+        pub fn number_range(&self, n: usize) -> (usize, usize) {
+            number_range(self.lie_chance, n)
+        }
+
+        // Hides treasure under covered tiles without mines, as many as there is room for
+        pub fn place_treasure(&mut self, count: usize) {
+            let mut spots: Vec<usize> = self.tiles.iter().enumerate()
+                .filter(|i| i.1.state == State::Covered && !i.1.mine && !i.1.treasure)
+                .map(|i| i.0)
+                .collect();
+            spots.shuffle(&mut self.rng);
+
+            for i in spots.into_iter().take(count) {
+                self.tiles[i].treasure = true;
+            }
+        }
+
+        // How much treasure has been found, out of how much there is
+        pub fn treasure(&self) -> (usize, usize) {
+            let total = self.tiles.iter().filter(|i| i.treasure).count();
+            let found = self.tiles.iter().filter(|i| i.treasure && i.state == State::Uncovered).count();
+
+            (self.final_treasure.unwrap_or(found), total)
+        }
+
+        // Whether an opened number borders treasure that is still covered
+        fn near_treasure(&self, i: usize) -> bool {
+            self.neighbors(i).into_iter()
+                .filter_map(|n| self.tiles.get(n))
+                .any(|t| t.treasure && t.state != State::Uncovered)
+        }
+
+        pub fn mines_left(&self) -> i64 {
+            self.mines() as i64 - self.flag_total as i64
+        }
+
+        pub fn flags_on_mines(&self) -> usize {
+            self.flag_correct
+        }
+
+        // The fewest clicks that clear the board, one for each opening and one for each number no opening
+        // reaches, along with how many of those have been cleared so far
+        pub fn three_bv(&self) -> (usize, usize) {
+            let zero = |i: usize| !self.tiles[i].mine && self.tiles[i].mines_surrounding == 0;
+            let uncovered = |i: usize| self.tiles[i].state == State::Uncovered;
+            let mut reached = vec![false; self.tiles.len()];
+            let mut total = 0;
+            let mut cleared = 0;
+
+            for i in 0..self.tiles.len() {
+                if reached[i] || !zero(i) {
+                    continue;
+                }
+
+                let mut working = vec![i];
+                reached[i] = true;
+
+                while let Some(t) = working.pop() {
+                    for n in self.neighbors(t) {
+                        if n >= self.tiles.len() || reached[n] || self.tiles[n].mine {
+                            continue;
+                        }
+
+                        reached[n] = true;
+
+                        if zero(n) {
+                            working.push(n);
+                        }
+                    }
+                }
+
+                total += 1;
+                cleared += uncovered(i) as usize;
+            }
+
+            for i in 0..self.tiles.len() {
+                if !reached[i] && !self.tiles[i].mine {
+                    total += 1;
+                    cleared += uncovered(i) as usize;
+                }
+            }
+
+            (total, self.final_bv.unwrap_or(cleared))
+        }
+
+        // Mines left in every row and then in every column, taking one off for every flag. A row or column with more
+        // flags than mines goes below zero
+        pub fn line_counts(&self) -> (Vec<i64>, Vec<i64>) {
+            let mut rows = vec![0; self.height()];
+            let mut columns = vec![0; self.width];
+
+            for (i, tile) in self.tiles.iter().enumerate() {
+                let count = match tile.state {
+                    State::Flagged | State::FlagRevealed | State::WrongFlag => tile.mine as i64 - 1,
+                    _ => tile.mine as i64,
+                };
+
+                rows[i / self.width] += count;
+                columns[i % self.width] += count;
+            }
+
+            (rows, columns)
+        }
+
+        pub fn can_flag(&self) -> bool {
+            !self.strict_flags || self.flag_total < self.mines()
+        }
+
+        pub fn width(&self) -> usize {
+            self.width
+        }
+
+        pub fn height(&self) -> usize {
+            self.tiles.len() / self.width
+        }
+        
+        pub fn push_state(&mut self, x: usize, y: usize, update: PushState) {
+            if self.won.is_some() || (self.no_flag && matches!(update, PushState::Flag)) {
+                return
+            }
+
+            let old_tile = self.get_tile(x, y).unwrap();
+
+            match (&old_tile.state, update) {
+                (State::Covered, PushState::Uncover) => {
+                    self.uncover_tile(x, y);
+                },
+                (State::Uncovered, _) => {
+                    let manhattan_tile_coords = self.neighbors(get_1d(x, y, self.width));
+
+                    let flags_surrounding = manhattan_tile_coords.iter()
+                        .filter_map(|i| self.tiles.get(*i))
+                        .filter(|i| i.state == State::Flagged)
+                        .fold(0, |t, i| t + i.flag_value());
+                    
+                    if flags_surrounding == old_tile.value() {
+                        for coord in manhattan_tile_coords {
+                            if let Some(t) = self.tiles.get(coord) {
+                                if t.state == State::Covered {
+                                    let coords = get_2d(coord, self.width);
+                                    self.uncover_tile(coords.0, coords.1);
+                                }
+                            }
+                        }
+                    }
+                },
+                (State::Flagged, PushState::Flag) if self.hardcore => (),
+                // With anti-mines around, flags go from a flag to an anti-mine flag before coming off
+                (State::Flagged, PushState::Flag) if self.anti_total > 0 && !old_tile.anti_flag => {
+                    let tile = &mut self.tiles[get_1d(x, y, self.width)];
+                    let fitted = tile.flag_fits();
+                    tile.anti_flag = true;
+
+                    self.flag_correct = self.flag_correct + tile.flag_fits() as usize - fitted as usize;
+                },
+                // With stacked mines, flags stack up to 3 before coming off
+                (State::Flagged, PushState::Flag) if self.stacked > 0 && old_tile.extra_flags < 2 && self.can_flag() => {
+                    let tile = &mut self.tiles[get_1d(x, y, self.width)];
+                    let fitted = tile.flag_fits();
+                    tile.extra_flags += 1;
+
+                    self.flag_total += 1;
+                    self.flag_correct = self.flag_correct + tile.flag_fits() as usize - fitted as usize;
+                },
+                (State::Flagged, PushState::Flag) => {
+                    let tile = &mut self.tiles[get_1d(x, y, self.width)];
+                    let fitted = tile.flag_fits();
+
+                    self.flag_total -= 1 + tile.extra_flags as usize;
+                    self.flags_removed += 1;
+                    self.flag_correct -= fitted as usize;
+
+                    tile.state = State::Covered;
+                    tile.anti_flag = false;
+                    tile.extra_flags = 0;
+                },
+                (State::Covered, PushState::Flag) => {
+                    if self.can_flag() {
+                        self.flag_total += 1;
+                        self.set_tile_state(x, y, State::Flagged);
+
+                        if self.get_tile(x, y).unwrap().flag_fits() {
+                            self.flag_correct += 1;
+                        } else if self.hardcore && !self.get_tile(x, y).unwrap().mine {
+                            self.losing_tile = Some((x, y));
+                            self.end_game(false);
+                        }
+                    }
+                },
+                _ => (),
+            };
+
+            if self.auto_flag && !self.no_flag && self.won.is_none() {
+                self.assisted = true;
+                self.flag_forced();
+            }
+
+            if self.won.is_none() && self.edges.is_none() {
+                if (!self.no_flag && self.flag_correct == self.mine_total && self.flag_total == self.mines()) || self.uncover_correct() == self.tiles.len() - self.mine_total {
+                    self.end_game(true);
+                }
+            }
+        }
+
+        // Flags the mines the single point solver finds, the covered neighbors of every number that has exactly as
+        // many covered and flagged neighbors as it has mines. Placing a flag can't change that for any other number,
+        // so one pass is enough
+        fn flag_forced(&mut self) {
+            for (x, y) in solver::single_point(&self.visible_state()).mines {
+                let n = get_1d(x, y, self.width);
+
+                if self.tiles[n].state == State::Covered && self.can_flag() {
+                    self.tiles[n].state = State::Flagged;
+                    self.flag_total += 1;
+                    self.auto_flagged += 1;
+
+                    if self.tiles[n].mine {
+                        self.flag_correct += 1;
+                    }
+                }
+            }
+        }
+
+        pub fn opened(&self) -> usize {
+            self.uncover_correct()
+        }
+
+        pub fn progress(&self) -> usize {
+            self.uncover_correct() * 100 / (self.tiles.len() - self.mine_total)
+        }
+
+        fn uncover_correct(&self) -> usize {
+            self.tiles.iter()
+                .filter(|i| i.state == State::Uncovered && !i.mine)
+                .count()
+        }
+
+        pub fn view<'a>(&'a self, theme: &'a Theme) -> View<'a> {
+            View {
+                board: self,
+                theme: theme,
+                cursor: None,
+                partner: None,
+                cell: (1, 1),
+                highlighted: Vec::new(),
+                safe: Vec::new(),
+                mines: Vec::new(),
+                chances: None,
+                faded: None,
+                layer: 0,
+                spaced: false,
+                overview: false,
+            }
+        }
+
+        pub fn nearest_covered(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+            let distance = |i: &(usize, usize)| {
+                let dx = (i.0 as i64 - x as i64).abs();
+                let dy = (i.1 as i64 - y as i64).abs();
+
+                (cmp::max(dx, dy), dx * dx + dy * dy)
+            };
+
+            self.tiles.iter().enumerate()
+                .filter(|i| i.1.state == State::Covered)
+                .map(|i| get_2d(i.0, self.width))
+                .filter(|i| *i != (x, y))
+                .min_by_key(distance)
+        }
+
+        // A covered tile without a mine, for the arcade reveal power-up
+        pub fn random_safe(&mut self) -> Option<(usize, usize)> {
+            let safe: Vec<usize> = self.tiles.iter().enumerate()
+                .filter(|i| i.1.state == State::Covered && !i.1.mine)
+                .map(|i| i.0)
+                .collect();
+
+            safe.choose(&mut self.rng).map(|i| get_2d(*i, self.width))
+        }
+
+        // Takes the mine out from under a flag and opens the tile. Returns false if the flag was wrong
+        pub fn defuse(&mut self, x: usize, y: usize) -> bool {
+            let i = get_1d(x, y, self.width);
+
+            if self.tiles[i].state != State::Flagged || !self.tiles[i].mine {
+                return false;
+            }
+
+            self.tiles[i].mine = false;
+            self.tiles[i].state = State::Covered;
+            self.mine_total -= 1;
+            self.flag_total -= 1;
+            self.flag_correct -= 1;
+
+            for n in self.neighbors(i) {
+                if let Some(t) = self.tiles.get_mut(n) {
+                    t.mines_surrounding -= 1;
+                }
+            }
+
+            self.push_state(x, y, PushState::Uncover);
+            true
+        }
 
         fn set_tile_state(&mut self, x: usize, y: usize, update: State) {
             self.tiles[get_1d(x, y, self.width)].state = update;
         }
 
-        fn get_tile(&self, x: usize, y: usize) -> Option<&Tile> {
-            self.tiles.get(get_1d(x, y, self.width))
+        fn get_tile(&self, x: usize, y: usize) -> Option<&Tile> {
+            self.tiles.get(get_1d(x, y, self.width))
+        }
+
+        pub fn cell(&self, x: usize, y: usize) -> Cell {
+            self.tiles[get_1d(x, y, self.width)].cell()
+        }
+
+        pub fn spoilers(&self) -> Spoilers {
+            Spoilers(self)
+        }
+
+        pub fn visible_state(&self) -> Visible {
+            Visible {
+                width: self.width,
+                height: self.height(),
+                cells: self.tiles.iter().map(|i| i.cell()).collect(),
+                mines: self.mines(),
+                flags: self.flag_total,
+                layers: self.layers,
+                lie_chance: self.lie_chance,
+                exact: self.anti_total == 0 && self.stacked == 0 && !self.rushing(),
+                won: self.won,
+            }
+        }
+
+        // What a tile would show once uncovered, for tools that look over a whole board instead of playing it
+        pub fn answer(&self, x: usize, y: usize) -> Cell {
+            let mut tile = self.tiles[get_1d(x, y, self.width)].clone();
+            tile.state = State::Uncovered;
+            tile.cell()
+        }
+
+        pub fn pencil(&self, x: usize, y: usize) -> Option<Pencil> {
+            self.tiles[get_1d(x, y, self.width)].pencil
+        }
+
+        // Moves a covered tile on to its next pencil mark, returning whether it could have one
+        pub fn cycle_pencil(&mut self, x: usize, y: usize) -> bool {
+            let tile = &mut self.tiles[get_1d(x, y, self.width)];
+
+            if tile.state != State::Covered {
+                return false;
+            }
+
+            tile.pencil = Pencil::next(tile.pencil);
+            true
+        }
+
+        pub fn tag(&self, x: usize, y: usize) -> Option<usize> {
+            self.tiles[get_1d(x, y, self.width)].tag
+        }
+
+        // Tags a covered tile with one of TAGS, or takes its tag off if it already has that one. Returns whether
+        // the tile could be tagged
+        pub fn toggle_tag(&mut self, x: usize, y: usize, tag: usize) -> bool {
+            let tile = &mut self.tiles[get_1d(x, y, self.width)];
+
+            if tile.state != State::Covered {
+                return false;
+            }
+
+            tile.tag = if tile.tag == Some(tag) { None } else { Some(tag) };
+            true
+        }
+
+        pub fn clear_tags(&mut self) {
+            for tile in &mut self.tiles {
+                tile.tag = None;
+            }
+        }
+
+        // Where the mine under the first uncover goes. On a symmetric board its mirror moves with it onto another
+        // free tile and mirror, and only if there is no such pair left is the symmetry given up
+        fn relocations(&mut self, tile_pos: usize) -> Vec<(usize, usize)> {
+            let (width, height) = (self.width, self.height());
+            let generation = self.generation;
+
+            if generation.symmetry.is_some() {
+                let from = generation.orbit(tile_pos, width, height);
+                let mut free: Vec<_> = (0..self.tiles.len())
+                    .filter(|i| *i <= generation.mirror(*i, width, height) && generation.allows_mine(*i, width, height))
+                    .map(|i| generation.orbit(i, width, height))
+                    .filter(|orbit| orbit.len() == from.len() && orbit.iter().all(|i| !self.tiles[*i].mine))
+                    .collect();
+                free.shuffle(&mut self.rng);
+
+                if let Some(to) = free.pop() {
+                    return from.into_iter().zip(to).collect();
+                }
+            }
+
+            let mut possible_replacements: Vec<_> = self.tiles.iter().enumerate()
+                .filter(|i| (!i.1.mine || i.0 == tile_pos) && generation.allows_mine(i.0, width, height))
+                .map(|i| i.0)
+                .collect();
+            possible_replacements.shuffle(&mut self.rng);
+
+            vec![(tile_pos, possible_replacements[0])]
+        }
+
+        // Moves the mines out of a patch around the first uncover so it opens at least min_opening blank tiles. The
+        // patch is the tiles nearest the uncover, square by square, along with the tiles touching them, and on a
+        // symmetric board its mirror too. Nothing is moved if there isn't room for the mines elsewhere
+        fn make_opening(&mut self, tile_pos: usize) -> bool {
+            let (width, height) = (self.width, self.height());
+            let generation = self.generation;
+            let mines: Vec<bool> = self.tiles.iter().map(|i| i.mine).collect();
+
+            if generation.min_opening == 0 || generation.min_opening > mines.len() {
+                return false;
+            } else if opening_sizes(&mines, width)[tile_pos] >= generation.min_opening {
+                return true;
+            }
+
+            let (x, y) = get_2d(tile_pos, width);
+            let mut nearest: Vec<usize> = (0..mines.len()).collect();
+            nearest.sort_by_key(|i| {
+                let (nx, ny) = get_2d(*i, width);
+                cmp::max((nx as i64 - x as i64).abs(), (ny as i64 - y as i64).abs())
+            });
+
+            let mut patch = vec![false; mines.len()];
+
+            for i in nearest.into_iter().take(generation.min_opening) {
+                for n in self.neighbors(i).into_iter().chain(Some(i)) {
+                    patch[n] = true;
+                    patch[generation.mirror(n, width, height)] = true;
+                }
+            }
+
+            let orbits = |wanted: &dyn Fn(usize) -> bool| -> Vec<Vec<usize>> {
+                (0..mines.len())
+                    .filter(|i| *i <= generation.mirror(*i, width, height) && wanted(*i))
+                    .map(|i| generation.orbit(i, width, height))
+                    .collect()
+            };
+            let moving = orbits(&|i| patch[i] && mines[i]);
+            let (mut singles, mut pairs): (Vec<_>, Vec<_>) = orbits(&|i| !patch[i] && !mines[i] && generation.allows_mine(i, width, height))
+                .into_iter()
+                .partition(|orbit| orbit.len() == 1);
+
+            if moving.iter().filter(|orbit| orbit.len() == 1).count() > singles.len() || moving.iter().filter(|orbit| orbit.len() == 2).count() > pairs.len() {
+                return false;
+            }
+
+            singles.shuffle(&mut self.rng);
+            pairs.shuffle(&mut self.rng);
+
+            for from in moving {
+                let to = if from.len() == 1 { singles.pop() } else { pairs.pop() };
+
+                for (from, to) in from.into_iter().zip(to.unwrap()) {
+                    self.move_mine(from, to);
+                }
+            }
+
+            true
+        }
+
+        fn move_mine(&mut self, from: usize, to: usize) {
+            let tile = &mut self.tiles[from];
+            let anti = tile.anti as usize;
+            let extra = tile.extra;
+            tile.mine = false;
+            tile.anti = false;
+            tile.extra = 0;
+
+            for s in self.neighbors(from) {
+                if let Some(i) = self.tiles.get_mut(s) {
+                    i.mines_surrounding -= 1 + extra as usize;
+                    i.anti_surrounding -= anti;
+                }
+            }
+
+            let mut swap_tile = &mut self.tiles[to];
+            swap_tile.mine = true;
+            swap_tile.anti = anti > 0;
+            swap_tile.extra = extra;
+
+            // Treasure can't sit under a mine, so it trades places with the mine
+            if swap_tile.treasure {
+                swap_tile.treasure = false;
+                self.tiles[from].treasure = true;
+            }
+
+            for s in self.neighbors(to) {
+                if let Some(i) = self.tiles.get_mut(s) {
+                    i.mines_surrounding += 1 + extra as usize;
+                    i.anti_surrounding += anti;
+                }
+            }
+        }
+
+        fn uncover_tile(&mut self, x: usize, y: usize) {
+            let tile_pos = get_1d(x, y, self.width);
+            let mine = self.tiles[tile_pos].mine;
+
+            if self.first_uncover && self.make_opening(tile_pos) {
+                // The mines around the uncover, and any under it, have already been moved away
+
+            } else if mine && self.first_uncover {
+                for (from, to) in self.relocations(tile_pos) {
+                    self.move_mine(from, to);
+                }
+
+            } else if mine {
+                self.losing_tile = Some((x, y));
+                self.end_game(false);
+                return;
+            }
+
+            self.first_uncover = false;
+
+            let mut tile = &mut self.tiles[tile_pos];
+
+            tile.state = State::Uncovered;
+
+            if tile.mines_surrounding == 0 {
+                self.clear_zeros((x, y));
+            }
+        }
+
+        fn end_game(&mut self, won: bool) {
+            self.won = Some(won);
+            self.final_bv = Some(self.three_bv().1);
+            self.final_treasure = Some(self.treasure().0);
+
+            let losing_tile = self.losing_tile.map(|i| get_1d(i.0, i.1, self.width));
+
+            for (i, t) in self.tiles.iter_mut().enumerate() {
+                t.state = match t.state {
+                    State::Flagged if t.flag_fits() => State::FlagRevealed,
+                    State::Flagged => State::WrongFlag,
+                    _ if Some(i) == losing_tile => State::Exploded,
+                    _ => State::Uncovered,
+                }
+            }
+        }
+
+        // Uncovers every zero joined to the starting tile and the numbers around them, one tile at a time off a
+        // queue, with a bit for every tile already reached so none is looked at twice
+        fn clear_zeros(&mut self, starting_pos: (usize, usize)) {
+            let start = get_1d(starting_pos.0, starting_pos.1, self.width);
+            let mut seen = vec![0u64; self.tiles.len() / 64 + 1];
+            let mut queue = VecDeque::new();
+
+            seen[start / 64] |= 1 << (start % 64);
+            queue.push_back(start);
+
+            while let Some(t) = queue.pop_front() {
+                self.tiles[t].state = State::Uncovered;
+
+                for n in self.neighbors(t) {
+                    if seen[n / 64] & 1 << (n % 64) != 0 || self.tiles[n].state != State::Covered {
+                        continue;
+                    }
+
+                    seen[n / 64] |= 1 << (n % 64);
+
+                    if self.tiles[n].mines_surrounding == 0 {
+                        queue.push_back(n);
+                    } else {
+                        self.tiles[n].state = State::Uncovered;
+                    }
+                }
+            }
+        }
+    }
+
+    impl fmt::Display for Board {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.view(&Theme::default()))
         }
+    }
 
-        fn uncover_tile(&mut self, x: usize, y: usize) {
-            let tile_pos = get_1d(x, y, self.width);
-            let mut tile = &mut self.tiles[tile_pos];
+    #[derive(Clone)]
+    pub struct View<'a> {
+        board: &'a Board,
+        theme: &'a Theme,
+        cursor: Option<usize>,
+        partner: Option<usize>,
+        cell: (usize, usize),
+        highlighted: Vec<usize>,
+        safe: Vec<usize>,
+        mines: Vec<usize>,
+        chances: Option<&'a [Option<f64>]>,
+        faded: Option<&'a [bool]>,
+        layer: usize,
+        spaced: bool,
+        overview: bool,
+    }
 
-            if tile.mine && self.first_uncover {
-                tile.mine = false;
+    impl<'a> View<'a> {
+        // Draws only one layer of a board with layers
+        pub fn layer(mut self, layer: usize) -> View<'a> {
+            self.layer = layer;
+            self
+        }
 
-                for s in get_1d_manhattan(tile_pos, self.width) {
-                    if let Some(i) = self.tiles.get_mut(s) {
-                        i.mines_surrounding -= 1;
-                    }
+        pub fn cursor(mut self, x: usize, y: usize) -> View<'a> {
+            self.cursor = Some(get_1d(x, y, self.board.width));
+            self
+        }
+
+        // The second player's cursor in co-op, drawn in a different color
+        pub fn partner(mut self, x: usize, y: usize) -> View<'a> {
+            self.partner = Some(get_1d(x, y, self.board.width));
+            self
+        }
+
+        // Draws every tile as a block of characters instead of a single one
+        pub fn cell(mut self, width: u16, height: u16) -> View<'a> {
+            self.cell = (width as usize, height as usize);
+            self
+        }
+
+        // Leaves the rest of the block blank on covered tiles too, so there is a gap between every column
+        pub fn spaced(mut self) -> View<'a> {
+            self.spaced = true;
+            self
+        }
+
+        // Draws every 2 by 4 block of tiles as one braille character, with a dot for each tile that is still covered,
+        // so all of a big board fits in a corner of the space it usually takes
+        pub fn overview(mut self) -> View<'a> {
+            self.overview = true;
+            self
+        }
+
+        // Blocks with flags take the flag's style, and once the game is lost those with mines take the mine's.
+        // Terminals without unicode get a character from OVERVIEW_SHADES for how much of the block is covered
+        fn overview_block(&self, x: usize, y: usize, bottom: usize) -> StyledContent<char> {
+            const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+            let mut dots = 0;
+            let (mut covered, mut total) = (0, 0);
+            let mut style = paint(' ', None).style().clone();
+
+            for (dx, dy) in (0..2).cartesian_product(0..4) {
+                if x + dx >= self.board.width || y + dy >= bottom {
+                    continue;
                 }
-                
-                let mut possible_replacements: Vec<_> = self.tiles.iter().enumerate()
-                    .filter(|i| !i.1.mine)
-                    .map(|i| i.0)
-                    .collect();
-                possible_replacements.shuffle(&mut thread_rng());
-                let replacement = possible_replacements[0];
 
-                let mut swap_tile = &mut self.tiles[replacement];
-                swap_tile.mine = true;
+                let cell = self.board.cell(x + dx, y + dy);
+                total += 1;
 
-                for s in get_1d_manhattan(replacement, self.width) {
-                    if let Some(i) = self.tiles.get_mut(s) {
-                        i.mines_surrounding += 1;
-                    }
+                if matches!(cell, Cell::Covered | Cell::Flagged) {
+                    dots |= DOTS[dx][dy];
+                    covered += 1;
                 }
 
-            } else if tile.mine {
-                self.end_game(false);
-                return;
+                match cell {
+                    Cell::Mine | Cell::Exploded => style = *self.theme.mine.styled().style(),
+                    Cell::Flagged | Cell::WrongFlag => style = *self.theme.flag.styled().style(),
+                    _ => (),
+                }
             }
 
-            self.first_uncover = false;
+            let symbol = if self.theme.capabilities.unicode {
+                std::char::from_u32(0x2800 + dots).unwrap()
+            } else {
+                OVERVIEW_SHADES[(covered * 3 + total - 1) / total]
+            };
 
-            let mut tile = &mut self.tiles[tile_pos];
+            StyledContent::new(style, symbol)
+        }
 
-            tile.state = State::Uncovered;
+        fn fmt_overview(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let vertical = self.theme.border.vertical;
+            let rows = self.board.layer_height();
+            let top = self.layer * rows;
+            let blocks = (self.board.width + 1) / 2;
 
-            if tile.mines_surrounding == 0 {
-                self.clear_zeros((x, y));
+            for line in 0..rows * self.cell.1 {
+                if line > 0 {
+                    f.write_str("\r\n")?;
+                }
+
+                write!(f, "{}", vertical)?;
+
+                let drawn = if line * 4 < rows {
+                    for x in 0..blocks {
+                        write!(f, "{}", self.overview_block(x * 2, top + line * 4, top + rows))?;
+                    }
+
+                    blocks
+                } else {
+                    0
+                };
+
+                write!(f, "{:1$}{2}", "", self.board.width * self.cell.0 - drawn, vertical)?;
             }
+
+            Ok(())
         }
 
-        fn end_game(&mut self, won: bool) {
-            self.won = Some(won);
+        // One row of a tile. Covered tiles repeat their glyph across the whole block, while everything else has its
+        // symbol in the middle and is padded with spaces in the same style
+        pub fn tile_row(&self, x: usize, y: usize, row: usize) -> StyledContent<Block<'a>> {
+            self.styled_row(get_1d(x, y, self.board.width), row)
+        }
 
-            for t in &mut self.tiles {
-                t.state = match t.state {
-                    State::Flagged => State::FlagRevealed,
-                    _ => State::Uncovered,
+        fn styled_row(&self, i: usize, row: usize) -> StyledContent<Block<'a>> {
+            let glyph = self.styled_tile(i);
+            let block = Block {
+                symbol: glyph.content(),
+                width: self.cell.0,
+                fill: self.board.tiles[i].state == State::Covered && self.board.tiles[i].pencil.is_none() && !self.spaced,
+                middle: row == self.cell.1 / 2,
+            };
+
+            StyledContent::new(*glyph.style(), block)
+        }
+
+        // Colors covered tiles that are known to be safe or mines
+        pub fn mark(mut self, safe: &[(usize, usize)], mines: &[(usize, usize)]) -> View<'a> {
+            let width = self.board.width;
+
+            self.safe = safe.iter().map(|i| get_1d(i.0, i.1, width)).collect();
+            self.mines = mines.iter().map(|i| get_1d(i.0, i.1, width)).collect();
+            self
+        }
+
+        // Shows every covered tile's chance of being a mine in tenths, rounded up so only a tile that is certainly
+        // safe shows 0
+        pub fn chances(mut self, chances: &'a [Option<f64>]) -> View<'a> {
+            self.chances = Some(chances);
+            self
+        }
+
+        // Draws the numbers of the given tiles as blank until the game ends, one flag for every tile of the board
+        pub fn fade(mut self, faded: &'a [bool]) -> View<'a> {
+            self.faded = Some(faded);
+            self
+        }
+
+        fn styled_tile(&self, i: usize) -> StyledContent<&'a str> {
+            let mut glyph = self.board.tiles[i].glyph(self.theme);
+
+            if self.faded.map_or(false, |f| f[i]) && self.board.won.is_none() {
+                glyph = self.theme.empty.styled();
+            }
+
+            let colored = self.theme.colored();
+
+            let tile = &self.board.tiles[i];
+
+            if self.board.treasure_hints && tile.state == State::Uncovered && !tile.mine && tile.mines_surrounding > 0
+                && self.board.won.is_none() && self.board.near_treasure(i) {
+                glyph = if colored { paint(*glyph.content(), self.theme.treasure.styled().style().foreground_color) } else { glyph.bold() };
+            }
+
+            if self.board.tiles[i].state == State::Covered {
+                if self.safe.contains(&i) {
+                    glyph = if colored { paint(*glyph.content(), Some(Color::Green)) } else { glyph.underlined() };
+                } else if self.mines.contains(&i) {
+                    glyph = if colored { paint(*glyph.content(), Some(Color::Red)) } else { glyph.bold() };
+                } else if let Some(p) = self.chances.and_then(|c| c[i]) {
+                    let digit = TENTHS[cmp::min((p * 10.0).ceil() as usize, 9)];
+                    glyph = if colored { paint(digit, Some(Color::Magenta)) } else { style(digit).underlined() };
+                }
+
+                if let Some(mark) = tile.pencil {
+                    glyph = mark.glyph(colored);
+                }
+
+                if let Some(tag) = tile.tag {
+                    glyph = if colored { glyph.on(TAGS[tag].0) } else { glyph.underlined() };
                 }
             }
-        }
 
-        fn clear_zeros(&mut self, starting_pos: (usize, usize)) {
-            let starting_pos = get_1d(starting_pos.0, starting_pos.1, self.width);
+            if self.cursor == Some(i) {
+                glyph.reverse()
+            } else if self.partner == Some(i) {
+                if colored { glyph.on_dark_cyan() } else { glyph.reverse().underlined() }
+            } else if self.highlighted.contains(&i) && colored {
+                glyph.on_dark_grey()
+            } else {
+                glyph
+            }
+        }
 
-            let mut working = vec!(starting_pos);
+        pub fn highlight_neighbors(mut self, x: usize, y: usize) -> View<'a> {
+            let tile_pos = get_1d(x, y, self.board.width);
 
-            while !working.is_empty() {
-                for t in &working {
-                    self.tiles.get_mut(*t).unwrap().state = State::Uncovered;
+            if let Some(t) = self.board.tiles.get(tile_pos) {
+                if t.state == State::Uncovered && t.mines_surrounding > 0 {
+                    self.highlighted.extend(self.board.neighbors(tile_pos));
                 }
+            }
 
-                let surroundings: Vec<usize> = working.iter()
-                    .map(|i| get_1d_manhattan(*i, self.width))
-                    .flatten()
-                    .unique()
-                    .filter(|i| match self.tiles.get(*i) {
-                        Some(n) if n.state == State::Covered => true,
-                        _ => false,
-                    })
-                    .collect();
+            self
+        }
 
-                working.clear();
+        // Draws the same lines Display writes, starting at the left edge of the given line. Every tile starts at its
+        // own column, so a glyph too wide for its cell can't push the rest of the line out of place
+        pub fn draw(&self, screen: &mut Screen, top: u16) {
+            let width = self.board.width;
+            let vertical = self.theme.border.vertical;
+            let plain = ContentStyle::default();
+            let rows = self.board.layer_height();
+            let first = self.layer * rows;
+            let right = (width * self.cell.0) as u16 + 1;
 
-                for t in surroundings {
-                    if self.tiles.get(t).unwrap().mines_surrounding == 0 {
-                        working.push(t);
-                    } else {
-                        self.tiles.get_mut(t).unwrap().state = State::Uncovered;
+            for line in 0..rows * self.cell.1 {
+                let y = top + line as u16;
+                screen.put(0, y, vertical, plain);
+
+                if self.overview {
+                    let drawn = if line * 4 < rows { (width + 1) / 2 } else { 0 };
+
+                    for x in 0..drawn {
+                        screen.print_styled(x as u16 + 1, y, &self.overview_block(x * 2, first + line * 4, first + rows));
+                    }
+
+                    screen.print(drawn as u16 + 1, y, format_args!("{:1$}", "", right as usize - drawn - 1), plain);
+                } else {
+                    let (tile, row) = (first + line / self.cell.1, line % self.cell.1);
+
+                    for x in 0..width {
+                        screen.print_styled((x * self.cell.0) as u16 + 1, y, &self.styled_row(tile * width + x, row));
                     }
                 }
+
+                screen.put(right, y, vertical, plain);
             }
         }
     }
 
-    impl fmt::Display for Board {
+    const OVERVIEW_SHADES: [char; 4] = [' ', '.', ':', '#'];
+
+    impl<'a> fmt::Display for View<'a> {
         fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.overview {
+                return self.fmt_overview(f);
+            }
 
-            let mut formatted = String::from("║");
+            let width = self.board.width;
+            let vertical = self.theme.border.vertical;
+            let rows = self.board.layer_height();
+            let top = self.layer * rows;
 
-            for (count, tile) in self.tiles.iter().enumerate() {
-                formatted.push_str(&tile.to_string());
+            // Written tile by tile straight into the output, since this runs on every frame
+            for y in top..top + rows {
+                for row in 0..self.cell.1 {
+                    if y > top || row > 0 {
+                        f.write_str("\r\n")?;
+                    }
 
-                if count == self.tiles.len() - 1 {
-                    formatted.push_str("║")
-                } else if count % self.width == self.width - 1 {
-                    formatted.push_str("║\r\n║");
+                    write!(f, "{}", vertical)?;
+                    for x in 0..width {
+                        write!(f, "{}", self.styled_row(y * width + x, row))?;
+                    }
+                    write!(f, "{}", vertical)?;
                 }
             }
 
-            write!(f, "{}", formatted)
+            Ok(())
+        }
+    }
+
+    // One row of a tile, padded out to the cell width as it is written instead of in a new String
+    #[derive(Clone, Copy)]
+    pub struct Block<'a> {
+        symbol: &'a str,
+        width: usize,
+        fill: bool,
+        middle: bool,
+    }
+
+    impl<'a> fmt::Display for Block<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.fill {
+                for _ in 0..self.width {
+                    f.write_str(self.symbol)?;
+                }
+                Ok(())
+            } else if self.middle {
+                write!(f, "{:^1$}", self.symbol, self.width)
+            } else {
+                write!(f, "{:1$}", "", self.width)
+            }
         }
     }
 
@@ -698,5 +12303,459 @@ mod board {
                 .fold(true, |t, i| t && i.state == State::Uncovered)
             );
         }
+
+        #[test]
+        fn flood_fill() {
+            let layout = "\
+                .....
+                .....
+                *****
+                .....";
+            let mines: Vec<_> = layout.chars().filter(|i| !i.is_whitespace()).map(|i| i == '*').collect();
+            let mut test_board = Board::from_layout(5, &mines).unwrap();
+            test_board.push_state(4, 0, PushState::Flag);
+            test_board.push_state(0, 0, PushState::Uncover);
+
+            let uncovered: Vec<_> = test_board.tiles.iter().map(|i| i.state == State::Uncovered).collect();
+
+            // The zeros open up to the numbers under them, while the flag and everything past the mines stay put
+            assert_eq!(uncovered[..10], [true, true, true, true, false, true, true, true, true, true]);
+            assert!(uncovered[10..].iter().all(|i| !i));
+            assert_eq!(test_board.tiles[4].state, State::Flagged);
+        }
+
+        #[test]
+        fn surrounding_counts() {
+            let mines: Vec<_> = "*...*..*.*..".chars().map(|i| i == '*').collect();
+
+            // Three rows of four, with the counts not running over from the end of one row into the next
+            assert_eq!(count_surrounding(&mines, 4), vec![1, 2, 1, 1, 2, 3, 2, 0, 2, 1, 2, 1]);
+        }
+
+        #[test]
+        fn no_border_mines() {
+            let generation = Generation { no_border_mines: true, ..Generation::default() };
+            let on_border = |board: &Board| (0..81).any(|i| board.tiles[i].mine && !generation.allows_mine(i, 9, 9));
+
+            for seed in 0..20 {
+                let mut test_board = Board::generate(9, 9, 10, seed, generation).unwrap();
+                assert!(!on_border(&test_board));
+
+                // A mine under the first uncover moves somewhere else off the border
+                let mine = test_board.tiles.iter().position(|i| i.mine).unwrap();
+                test_board.push_state(mine % 9, mine / 9, PushState::Uncover);
+                assert!(!on_border(&test_board));
+                assert_eq!(test_board.regenerate(seed + 1).unwrap().generation, generation);
+                assert_eq!(Board::from_save(&test_board.to_save()).unwrap().generation, generation);
+            }
+
+            assert!(Board::generate(9, 9, 49, 1, generation).is_ok());
+            assert!(Board::generate(9, 9, 50, 1, generation).is_err());
+        }
+
+        #[test]
+        fn symmetry() {
+            for symmetry in &[Symmetry::Horizontal, Symmetry::Vertical, Symmetry::Rotational] {
+                let generation = Generation { symmetry: Some(*symmetry), ..Generation::default() };
+                let symmetric = |board: &Board| (0..81).all(|i| board.tiles[i].mine == board.tiles[generation.mirror(i, 9, 9)].mine);
+
+                for seed in 0..20 {
+                    // An odd count puts one mine on the middle line
+                    let mut test_board = Board::generate(9, 9, 11, seed, generation).unwrap();
+                    assert!(symmetric(&test_board));
+                    assert_eq!(test_board.tiles.iter().filter(|i| i.mine).count(), 11);
+
+                    // The mine under the first uncover takes its mirror along with it
+                    let mine = test_board.tiles.iter().position(|i| i.mine).unwrap();
+                    test_board.push_state(mine % 9, mine / 9, PushState::Uncover);
+                    assert!(symmetric(&test_board));
+                    assert!(!test_board.tiles[mine].mine);
+                    assert_eq!(Board::from_save(&test_board.to_save()).unwrap().generation, generation);
+                }
+
+                // Without a middle line only even counts can be mirrored
+                assert!(Board::generate(8, 8, 10, 1, generation).is_ok());
+                assert!(Board::generate(8, 8, 11, 1, generation).is_err());
+            }
+        }
+
+        #[test]
+        fn min_opening() {
+            let generation = Generation { min_opening: 15, ..Generation::default() };
+            let mines = |board: &Board| -> Vec<bool> { board.tiles.iter().map(|i| i.mine).collect() };
+
+            for seed in 0..20 {
+                let mut test_board = Board::generate(9, 9, 20, seed, generation).unwrap();
+                assert!(opening_sizes(&mines(&test_board), 9).into_iter().max().unwrap() >= 15);
+
+                // Wherever the first uncover lands, mine or not, it opens a big enough patch
+                let start = test_board.tiles.iter().position(|i| i.mine).unwrap();
+                test_board.push_state(start % 9, start / 9, PushState::Uncover);
+                assert!(opening_sizes(&mines(&test_board), 9)[start] >= 15);
+                assert_eq!(mines(&test_board).into_iter().filter(|i| *i).count(), 20);
+                assert_eq!(test_board.won, None);
+
+                // and stays symmetric when that was asked for
+                let generation = Generation { symmetry: Some(Symmetry::Rotational), ..generation };
+                let mut test_board = Board::generate(9, 9, 20, seed, generation).unwrap();
+                test_board.push_state(0, (seed % 9) as usize, PushState::Uncover);
+                assert!((0..81).all(|i| test_board.tiles[i].mine == test_board.tiles[80 - i].mine));
+            }
+
+            assert_eq!(opening_sizes(&[false, false, true, false, false, false], 3), vec![2, 0, 0, 2, 0, 0]);
+            assert!(Board::generate(9, 9, 70, 1, generation).is_err());
+        }
+
+        #[test]
+        fn no_flag() {
+            let mut test_board = Board::new(5, 5, 3).unwrap();
+            test_board.no_flag = true;
+            test_board.push_state(2, 2, PushState::Flag);
+
+            assert_eq!(test_board.flag_total, 0);
+            assert!(test_board.tiles.iter().all(|i| i.state == State::Covered));
+        }
+
+        #[test]
+        fn over_flagging() {
+            let mut test_board = Board::with_seed(3, 3, 1, 5).unwrap();
+
+            for i in 0..9 {
+                test_board.push_state(i % 3, i / 3, PushState::Flag);
+            }
+
+            assert_eq!(test_board.flag_total, 9);
+            assert_eq!(test_board.mines_left(), -8);
+            assert_eq!(test_board.won, None);
+
+            let mut test_board = Board::with_seed(3, 3, 1, 5).unwrap();
+            test_board.strict_flags = true;
+
+            for i in 0..9 {
+                test_board.push_state(i % 3, i / 3, PushState::Flag);
+            }
+
+            assert_eq!(test_board.flag_total, 1);
+        }
+
+        #[test]
+        fn hardcore() {
+            let save = "rs-minesweeper save\nwidth 3\nhardcore true\nfirst-uncover false\n\n*..\n...\n..*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            test_board.push_state(0, 0, PushState::Flag);
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!(test_board.cell(0, 0), Cell::Flagged);
+            assert_eq!(test_board.won, None);
+
+            test_board.push_state(1, 1, PushState::Flag);
+            assert_eq!(test_board.won, Some(false));
+            assert_eq!(test_board.losing_tile, Some((1, 1)));
+            assert_eq!(test_board.cell(1, 1), Cell::WrongFlag);
+        }
+
+        #[test]
+        fn layout() {
+            let test_board = Board::from_layout(4, &[true, false, false, false, false, false, true, true]).unwrap();
+            assert_eq!((test_board.width(), test_board.height(), test_board.mine_total), (4, 2, 3));
+            assert_eq!(test_board.tiles.iter().map(|i| i.mines_surrounding).collect::<Vec<usize>>(), [0, 2, 2, 2, 1, 2, 1, 1]);
+            assert!(!test_board.first_uncover);
+
+            assert!(Board::from_layout(3, &[true, false]).is_err());
+            assert!(Board::from_layout(2, &[true, true]).is_err());
+            assert!(Board::from_layout(0, &[]).is_err());
+        }
+
+        #[test]
+        fn visible_state() {
+            let mut test_board = Board::from_layout(3, &[true, false, false, false, false, false, false, false, true]).unwrap();
+            test_board.push_state(1, 0, PushState::Uncover);
+
+            let visible = test_board.visible_state();
+            assert_eq!(visible.cells, [Cell::Covered, Cell::Open(1), Cell::Covered, Cell::Covered, Cell::Covered, Cell::Covered, Cell::Covered, Cell::Covered, Cell::Covered]);
+            assert_eq!((visible.mines, visible.flags, visible.exact, visible.won), (2, 0, true, None));
+            assert_eq!(visible.neighbors(0, 0), [(1, 0), (0, 1), (1, 1)]);
+
+            test_board.push_state(0, 0, PushState::Uncover);
+            let visible = test_board.visible_state();
+            assert_eq!((visible.cell(0, 0), visible.cell(2, 2), visible.won), (Cell::Exploded, Cell::Mine, Some(false)));
+        }
+
+        #[test]
+        fn spaced_view() {
+            let test_board = Board::from_layout(2, &[true, false]).unwrap();
+            let theme = Theme::default();
+            let row = |view: View| view.tile_row(0, 0, 0).content().to_string();
+
+            let filled = row(test_board.view(&theme).cell(2, 1));
+            let spaced = row(test_board.view(&theme).cell(2, 1).spaced());
+
+            assert_eq!(filled.chars().count(), 2);
+            assert!(!filled.ends_with(' '));
+            assert_eq!(spaced, format!("{} ", filled.chars().next().unwrap()));
+        }
+
+        #[test]
+        fn overview_view() {
+            let mut test_board = Board::from_layout(2, &[false, false, false, false, false, false, false, true]).unwrap();
+            test_board.push_state(0, 0, PushState::Uncover);
+
+            // Only the mine and the 1 next to it in the bottom row are still covered
+            let theme = Theme::default();
+            let shown = test_board.view(&theme).overview().to_string();
+
+            assert_eq!(shown.split("\r\n").count(), 4);
+            assert!(shown.starts_with(&format!("{}\u{28c0} {}", theme.border.vertical, theme.border.vertical)));
+        }
+
+        #[test]
+        fn debug_output() {
+            let mut test_board = Board::with_seed(5, 5, 5, 7).unwrap();
+            test_board.push_state(2, 2, PushState::Uncover);
+
+            let hidden = format!("{:?}", test_board);
+            assert!(!hidden.contains("mine: true") && !hidden.contains("seed"));
+            assert!(format!("{:?}", test_board.spoilers()).contains("mine: true"));
+
+            test_board.won = Some(false);
+            assert!(format!("{:?}", test_board).contains("seed: 7"));
+        }
+
+        #[test]
+        fn auto_flag() {
+            let mut test_board = Board::from_layout(3, &[true, false, false, false, false, false, false, false, true]).unwrap();
+            test_board.push_state(1, 0, PushState::Uncover);
+            assert_eq!(test_board.flag_total, 0);
+
+            test_board.auto_flag = true;
+            test_board.push_state(0, 2, PushState::Uncover);
+
+            assert_eq!(test_board.cell(0, 0), Cell::Flagged);
+            assert_eq!(test_board.cell(2, 2), Cell::Covered);
+            assert_eq!(test_board.flag_total, 1);
+            assert_eq!(test_board.auto_flagged, 1);
+            assert_eq!(test_board.won, None);
+            assert!(test_board.assisted);
+        }
+
+        #[test]
+        fn save_round_trip() {
+            let mut test_board = Board::with_seed(9, 9, 10, 1234).unwrap();
+            test_board.push_state(4, 4, PushState::Uncover);
+
+            let loaded = Board::from_save(&test_board.to_save()).unwrap();
+            assert_eq!(loaded.tiles, test_board.tiles);
+            assert_eq!(loaded.seed, 1234);
+            assert_eq!(Board::with_seed(9, 9, 10, 1234).unwrap().tiles, Board::with_seed(9, 9, 10, 1234).unwrap().tiles);
+        }
+
+        #[test]
+        fn loss_markings() {
+            let mut test_board = Board::with_seed(6, 6, 12, 99).unwrap();
+            test_board.push_state(0, 0, PushState::Uncover);
+
+            let mine = test_board.tiles.iter().position(|i| i.mine).unwrap();
+            let safe = test_board.tiles.iter().position(|i| !i.mine && i.state == State::Covered).unwrap();
+            let other_mine = test_board.tiles.iter().rposition(|i| i.mine).unwrap();
+
+            test_board.push_state(safe % 6, safe / 6, PushState::Flag);
+            test_board.push_state(other_mine % 6, other_mine / 6, PushState::Flag);
+            test_board.push_state(mine % 6, mine / 6, PushState::Uncover);
+
+            assert_eq!(test_board.won, Some(false));
+            assert_eq!(test_board.losing_tile, Some((mine % 6, mine / 6)));
+            assert_eq!(test_board.tiles[mine].state, State::Exploded);
+            assert_eq!(test_board.tiles[safe].state, State::WrongFlag);
+            assert_eq!(test_board.tiles[other_mine].state, State::FlagRevealed);
+
+            assert_eq!(test_board.cell(mine % 6, mine / 6), Cell::Exploded);
+            assert_eq!(test_board.cell(safe % 6, safe / 6), Cell::WrongFlag);
+            assert_eq!(test_board.cell(other_mine % 6, other_mine / 6), Cell::Flagged);
+        }
+
+        #[test]
+        fn nearest_covered() {
+            let mut test_board = Board::new(5, 5, 0).unwrap();
+            assert_eq!(test_board.nearest_covered(2, 2), Some((2, 1)));
+            assert_eq!(test_board.nearest_covered(4, 4), Some((4, 3)));
+
+            test_board.push_state(0, 0, PushState::Uncover);
+            assert_eq!(test_board.nearest_covered(2, 2), None);
+        }
+
+        #[test]
+        fn treasure() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*$.\n...\n.$*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            assert_eq!(test_board.treasure(), (0, 2));
+            assert!(test_board.to_save().ends_with("*$.\n...\n.$*\n"));
+
+            test_board.push_state(1, 0, PushState::Uncover);
+            assert_eq!(test_board.treasure(), (1, 2));
+            assert!(test_board.to_save().ends_with("*O.\n...\n.$*\n"));
+
+            test_board.push_state(2, 0, PushState::Uncover);
+            test_board.push_state(2, 2, PushState::Uncover);
+            assert_eq!(test_board.won, Some(false));
+            assert_eq!(test_board.treasure(), (1, 2));
+
+            let mut test_board = Board::with_seed(9, 9, 10, 1234).unwrap();
+            test_board.place_treasure(100);
+            assert_eq!(test_board.treasure(), (0, 71));
+            assert!(test_board.tiles.iter().all(|i| !(i.mine && i.treasure)));
+        }
+
+        #[test]
+        fn anti_mines() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\nanti a........\n\n*..\n...\n..*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            assert_eq!(test_board.anti_total, 1);
+
+            test_board.push_state(1, 0, PushState::Uncover);
+            assert_eq!(test_board.cell(1, 0), Cell::Negative(1));
+
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!(test_board.flags_on_mines(), 0);
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!(test_board.flags_on_mines(), 1);
+
+            let loaded = Board::from_save(&test_board.to_save()).unwrap();
+            assert_eq!(loaded.tiles, test_board.tiles);
+            assert_eq!(loaded.flags_on_mines(), 1);
+
+            test_board.push_state(1, 0, PushState::Flag);
+            assert_eq!(test_board.cell(1, 1), Cell::Open(0));
+            assert_eq!(test_board.cell(2, 1), Cell::Open(1));
+            assert_eq!(test_board.won, None);
+
+            test_board.push_state(2, 2, PushState::Flag);
+            assert_eq!(test_board.won, Some(true));
+        }
+
+        #[test]
+        fn stacked_mines() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\nstacks 20,00,00,00,00,00,00,00,00\n\n*..\n...\n..*\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            assert_eq!((test_board.mine_total, test_board.mines()), (2, 4));
+
+            test_board.push_state(1, 1, PushState::Uncover);
+            assert_eq!(test_board.cell(1, 1), Cell::Open(4));
+
+            test_board.push_state(0, 0, PushState::Flag);
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!((test_board.flag_total, test_board.flags_on_mines()), (2, 0));
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!((test_board.flag_total, test_board.flags_on_mines()), (3, 1));
+
+            let loaded = Board::from_save(&test_board.to_save()).unwrap();
+            assert_eq!(loaded.tiles, test_board.tiles);
+            assert_eq!(loaded.mines_left(), 1);
+
+            test_board.push_state(2, 2, PushState::Flag);
+            test_board.push_state(1, 1, PushState::Uncover);
+            assert_eq!(test_board.won, Some(true));
+
+            let mut test_board = Board::with_seed(4, 4, 2, 7).unwrap();
+            test_board.stack_mines(10);
+            assert_eq!(test_board.mines(), 6);
+        }
+
+        #[test]
+        fn rush() {
+            let save = "rs-minesweeper save\nwidth 3\nfirst-uncover false\n\n*..\n...\n...\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            test_board.start_rush();
+            test_board.push_state(1, 1, PushState::Uncover);
+            assert_eq!(test_board.cell(1, 1), Cell::Open(1));
+
+            assert_eq!(test_board.scroll(), 1);
+            assert_eq!((test_board.width(), test_board.height()), (3, 3));
+            assert_eq!(test_board.cell(1, 0), Cell::Open(1));
+            assert_eq!(test_board.mine_total, test_board.tiles.iter().filter(|i| i.mine).count());
+            assert_eq!(test_board.won, None);
+
+            let mut test_board = Board::from_save(save).unwrap();
+            test_board.start_rush();
+            test_board.push_state(0, 0, PushState::Flag);
+            assert_eq!(test_board.scroll(), 0);
+            assert_eq!(test_board.flag_total, 0);
+        }
+
+        #[test]
+        fn pencil() {
+            let mut board = Board::with_seed(5, 5, 3, 9).unwrap();
+            let marks: Vec<_> = (0..6).map(|_| { board.cycle_pencil(4, 4); board.pencil(4, 4) }).collect();
+
+            assert_eq!(marks, [Some(Pencil::Mine), Some(Pencil::Safe), Some(Pencil::Guess(1)), Some(Pencil::Guess(2)), Some(Pencil::Guess(3)), None]);
+
+            // Marks don't change how the game plays, and only covered tiles take them
+            let mut marked = board.clone();
+            for i in 0..25 {
+                marked.cycle_pencil(i % 5, i / 5);
+            }
+
+            board.push_state(0, 0, PushState::Uncover);
+            marked.push_state(0, 0, PushState::Uncover);
+            let cells = |board: &Board| (0..25).map(|i| board.cell(i % 5, i / 5)).collect::<Vec<_>>();
+            assert_eq!(cells(&marked), cells(&board));
+            assert_eq!(marked.won, board.won);
+            assert!(!marked.cycle_pencil(0, 0));
+        }
+
+        #[test]
+        fn line_hints() {
+            let mut board = Board::from_save("rs-minesweeper save\nwidth 3\nfirst-uncover false\nline-hints true\n\n*.*\n..*\n").unwrap();
+            assert_eq!(board.line_counts(), (vec![2, 1], vec![1, 0, 2]));
+
+            board.push_state(2, 1, PushState::Flag);
+            board.push_state(1, 1, PushState::Flag);
+            assert_eq!(board.line_counts(), (vec![2, -1], vec![1, -1, 1]));
+
+            let saved = Board::from_save(&board.to_save()).unwrap();
+            assert!(saved.line_hints);
+            assert_eq!(saved.line_counts(), board.line_counts());
+        }
+
+        #[test]
+        fn tags() {
+            let mut board = Board::with_seed(5, 5, 3, 9).unwrap();
+
+            assert!(board.toggle_tag(1, 1, 2));
+            assert!(board.toggle_tag(3, 3, 0));
+            assert_eq!(board.tag(1, 1), Some(2));
+            assert!(board.toggle_tag(1, 1, 1));
+            assert_eq!(board.tag(1, 1), Some(1));
+            assert!(board.toggle_tag(1, 1, 1));
+            assert_eq!(board.tag(1, 1), None);
+
+            board.clear_tags();
+            assert_eq!(board.tag(3, 3), None);
+
+            board.push_state(0, 0, PushState::Uncover);
+            assert!(!board.toggle_tag(0, 0, 0));
+        }
+
+        #[test]
+        fn layers() {
+            let save = "rs-minesweeper save\nwidth 2\nfirst-uncover false\nlayers 2\n\n*.\n..\n..\n..\n";
+
+            let mut test_board = Board::from_save(save).unwrap();
+            assert_eq!((test_board.layers(), test_board.layer_height()), (2, 2));
+
+            test_board.push_state(1, 3, PushState::Uncover);
+            assert_eq!(test_board.cell(1, 3), Cell::Open(1));
+            assert!(test_board.to_save().contains("\nlayers 2\n"));
+
+            let mut test_board = Board::with_seed(3, 9, 0, 1).unwrap();
+            test_board.split_layers(3);
+            assert_eq!(test_board.neighbors(0).len(), 7);
+            assert_eq!(test_board.neighbors(13).len(), 26);
+
+            assert!(Board::from_save("rs-minesweeper save\nwidth 1\nlayers 2\n\n*\n.\n.\n").is_err());
+        }
     }
 }
\ No newline at end of file